@@ -446,6 +446,7 @@ fn extract_rd_points(corpus: &CorpusReport, metric: Metric) -> Vec<RDPoint> {
                     bpp: result.bits_per_pixel,
                     quality: q,
                     encode_time_ms: Some(result.encode_time.as_millis() as f64),
+                    encode_peak_bytes: result.encode_peak_bytes,
                     image: Some(image.name.clone()),
                 });
             }
@@ -3,11 +3,11 @@
 //! Compares image codecs across formats (JPEG, WebP, AVIF) with
 //! statistical analysis and Pareto front visualization.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use clap::{Parser, Subcommand, ValueEnum};
 use codec_eval::corpus::Corpus;
-use codec_eval::eval::{CorpusReport, ImageData};
+use codec_eval::eval::{CorpusReport, EvalObserver, ImageData, ImageReport, ProgressBar};
 use codec_eval::metrics::MetricConfig;
 use codec_eval::viewing::ViewingCondition;
 use image::GenericImageView;
@@ -291,15 +291,11 @@ fn run_corpus(
 ) -> anyhow::Result<()> {
     println!("Discovering corpus at {}...", corpus_path.display());
 
-    let corpus = Corpus::discover(corpus_path)?;
-    let images = &corpus.images;
+    let mut corpus = Corpus::discover(corpus_path)?;
+    let total = corpus.images.len();
 
-    let image_count = limit.unwrap_or(images.len()).min(images.len());
-    println!(
-        "Found {} images, processing {}...\n",
-        images.len(),
-        image_count
-    );
+    let image_count = limit.unwrap_or(total).min(total);
+    println!("Found {} images, processing {}...\n", total, image_count);
 
     // Setup config
     let config = CompareConfig::new(output)
@@ -318,61 +314,42 @@ fn run_corpus(
     println!("Quality levels: {:?}", quality_levels);
     println!();
 
-    // Create corpus report
-    let mut corpus_report = CorpusReport::new("codec-compare".to_string());
-
-    // Process images
-    for (i, corpus_image) in images.iter().take(image_count).enumerate() {
-        let path = corpus_image.full_path(&corpus.root_path);
-        let name = corpus_image.name();
-
-        print!("[{}/{}] {}... ", i + 1, image_count, name);
-        std::io::Write::flush(&mut std::io::stdout())?;
-
-        // Load image
-        let img = match image::open(&path) {
-            Ok(img) => img,
-            Err(e) => {
-                println!("SKIP ({})", e);
-                continue;
-            }
-        };
-
-        let (width, height) = img.dimensions();
-        let rgb = img.to_rgb8();
-        let pixels: Vec<u8> = rgb.into_raw();
-
-        let image_data = ImageData::RgbSlice {
-            data: pixels,
-            width: width as usize,
-            height: height as usize,
-        };
-
-        // Evaluate
-        match registry.evaluate_image(&name, image_data) {
-            Ok(report) => {
-                let result_count = report.results.len();
-                println!("OK ({} results)", result_count);
-
-                if verbose {
-                    for r in &report.results {
-                        let ssim = r
-                            .metrics
-                            .ssimulacra2
-                            .map(|s| format!("{:.2}", s))
-                            .unwrap_or("-".to_string());
-                        println!(
-                            "    {} q{}: {} bytes, SSIM2={}",
-                            r.codec_id, r.quality as u32, r.file_size, ssim
-                        );
-                    }
-                }
+    // Evaluate all images on the session's thread pool, with a progress bar.
+    // Each image report is written as soon as the image is done, so an
+    // interrupted run keeps the finished images.
+    corpus.images.truncate(image_count);
+    registry
+        .session_mut()
+        .add_observer(ImageReportWriter {
+            dir: output.clone(),
+        })
+        .add_observer(ProgressBar::new());
+    let mut corpus_report = registry
+        .session()
+        .evaluate_corpus(&corpus, load_image)?;
+    corpus_report.name = "codec-compare".to_string();
+
+    for report in &corpus_report.images {
+        if !report.failures.is_empty() {
+            println!(
+                "{}: {} results, {} failed",
+                report.name,
+                report.results.len(),
+                report.failures.len()
+            );
+        }
 
-                registry.write_image_report(&report)?;
-                corpus_report.images.push(report);
-            }
-            Err(e) => {
-                println!("ERROR: {}", e);
+        if verbose {
+            for r in &report.results {
+                let ssim = r
+                    .metrics
+                    .ssimulacra2
+                    .map(|s| format!("{:.2}", s))
+                    .unwrap_or("-".to_string());
+                println!(
+                    "    {} {} q{}: {} bytes, SSIM2={}",
+                    report.name, r.codec_id, r.quality as u32, r.file_size, ssim
+                );
             }
         }
     }
@@ -396,6 +373,43 @@ fn run_corpus(
     Ok(())
 }
 
+/// Decode an image file to RGB8 for the eval session.
+fn load_image(path: &Path) -> codec_eval::Result<ImageData> {
+    let img = image::open(path).map_err(|e| codec_eval::Error::ImageLoad {
+        path: path.to_path_buf(),
+        reason: e.to_string(),
+    })?;
+    let (width, height) = img.dimensions();
+    Ok(ImageData::RgbSlice {
+        data: img.to_rgb8().into_raw(),
+        width: width as usize,
+        height: height as usize,
+    })
+}
+
+/// Writes `<dir>/<image>.json` for every image as it finishes.
+struct ImageReportWriter {
+    dir: PathBuf,
+}
+
+impl ImageReportWriter {
+    fn write(&self, report: &ImageReport) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let json = serde_json::to_string_pretty(report)?;
+        std::fs::write(self.dir.join(format!("{}.json", report.name)), json)?;
+        Ok(())
+    }
+}
+
+impl EvalObserver for ImageReportWriter {
+    fn image_finished(&self, report: &ImageReport) {
+        if let Err(e) = self.write(report) {
+            // Clear the progress bar line; its next update redraws it.
+            eprintln!("\r\x1b[KFailed to write report for {}: {e}", report.name);
+        }
+    }
+}
+
 fn run_single(
     input: &PathBuf,
     output: &PathBuf,
@@ -408,16 +422,7 @@ fn run_single(
     println!("Processing {}...", input.display());
 
     // Load image
-    let img = image::open(input)?;
-    let (width, height) = img.dimensions();
-    let rgb = img.to_rgb8();
-    let pixels: Vec<u8> = rgb.into_raw();
-
-    let image_data = ImageData::RgbSlice {
-        data: pixels,
-        width: width as usize,
-        height: height as usize,
-    };
+    let image_data = load_image(input)?;
 
     let name = input.file_stem().unwrap().to_string_lossy();
 
@@ -438,6 +443,7 @@ fn run_single(
     println!("Quality levels: {:?}\n", quality_levels);
 
     // Evaluate
+    registry.session_mut().add_observer(ProgressBar::new());
     let report = registry.evaluate_image(&name, image_data)?;
     registry.write_image_report(&report)?;
 
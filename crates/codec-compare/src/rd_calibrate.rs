@@ -7,7 +7,7 @@
 use anyhow::Result;
 use butteraugli::{ButteraugliParams, compute_butteraugli};
 use clap::Parser;
-use codec_eval::stats::rd_knee::{CorpusAggregate, FixedFrame, RDCalibration};
+use codec_eval::stats::rd_knee::{ButteraugliNorm, CorpusAggregate, FixedFrame, RDCalibration};
 use rayon::prelude::*;
 use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
@@ -272,6 +272,7 @@ fn main() -> Result<()> {
         codec: args.codec.clone(),
         curve: curve.clone(),
         image_count,
+        butteraugli_norm: ButteraugliNorm::Max,
     };
 
     let frame = FixedFrame::WEB;
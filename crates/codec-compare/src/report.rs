@@ -136,6 +136,7 @@ impl ReportGenerator {
                         bpp: result.bits_per_pixel,
                         quality: q,
                         encode_time_ms: Some(result.encode_time.as_millis() as f64),
+                        encode_peak_bytes: result.encode_peak_bytes,
                         image: Some(image.name.clone()),
                     });
                 }
@@ -0,0 +1,122 @@
+//! Report diff command.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use codec_eval::CorpusReport;
+use codec_eval::eval::{DiffOptions, ReportDiff};
+
+pub fn run(
+    old: PathBuf,
+    new: PathBuf,
+    output: Option<PathBuf>,
+    metrics: Vec<String>,
+    threshold: f64,
+    verbose: bool,
+) -> Result<bool> {
+    let old_report = load_report(&old, verbose)?;
+    let new_report = load_report(&new, verbose)?;
+
+    let options = DiffOptions::default()
+        .with_metrics(metrics)
+        .with_regression_threshold(threshold);
+    let diff = old_report.diff(&new_report, &options);
+
+    if diff.images.is_empty() {
+        bail!(
+            "No matching results between {} and {}",
+            old.display(),
+            new.display()
+        );
+    }
+
+    print_summary(&diff);
+
+    if let Some(output_path) = output {
+        let json = serde_json::to_string_pretty(&diff)?;
+        std::fs::write(&output_path, json)
+            .with_context(|| format!("Failed to write to {}", output_path.display()))?;
+        println!();
+        println!("Saved to: {}", output_path.display());
+    }
+
+    Ok(diff.regressed_images().next().is_none())
+}
+
+fn load_report(path: &Path, verbose: bool) -> Result<CorpusReport> {
+    if verbose {
+        eprintln!("Loading report from: {}", path.display());
+    }
+    Ok(CorpusReport::load(path)?)
+}
+
+fn print_summary(diff: &ReportDiff) {
+    println!(
+        "{} -> {}: {} images matched ({} results only in old, {} only in new)",
+        diff.old_name,
+        diff.new_name,
+        diff.images.len(),
+        diff.only_in_old,
+        diff.only_in_new
+    );
+    println!();
+
+    println!(
+        "{:<20} {:>8} {:>10} {:<14} {:>12} {:>10}",
+        "Codec", "Matched", "dBPP", "Metric", "dScore", "BD-rate"
+    );
+    println!("{:-<79}", "");
+    for codec in &diff.codecs {
+        let mut label = codec.codec_id.clone();
+        if !codec.params.is_empty() {
+            let params: Vec<String> = codec
+                .params
+                .iter()
+                .map(|(k, v)| format!("{k}={v}"))
+                .collect();
+            label = format!("{label}[{}]", params.join(","));
+        }
+        println!(
+            "{:<20} {:>8} {:>+10.4}",
+            label, codec.matched, codec.mean_delta_bpp
+        );
+        for metric in &diff.metrics {
+            let Some(delta) = codec.mean_delta.get(metric) else {
+                continue;
+            };
+            println!(
+                "{:<40} {:<14} {:>+12.4} {:>10}",
+                "",
+                metric,
+                delta,
+                codec
+                    .bd_rate
+                    .get(metric)
+                    .map_or("-".to_string(), |r| format!("{r:+.2}%"))
+            );
+        }
+    }
+
+    let regressed: Vec<_> = diff.regressed_images().collect();
+    println!();
+    if regressed.is_empty() {
+        println!(
+            "No image regressed beyond {:.2}% BD-rate",
+            diff.options.regression_threshold
+        );
+        return;
+    }
+    println!(
+        "{} images regressed beyond {:.2}% BD-rate:",
+        regressed.len(),
+        diff.options.regression_threshold
+    );
+    for img in regressed {
+        for r in &img.regressions {
+            println!(
+                "  {:<30} {:<20} {:<14} {:>+8.2}%",
+                img.name, r.codec_id, r.metric, r.bd_rate
+            );
+        }
+    }
+}
@@ -0,0 +1,45 @@
+//! HTML report command.
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use codec_eval::eval::HtmlReport;
+use codec_eval::{Corpus, CorpusReport};
+
+pub fn run(
+    input: PathBuf,
+    output: PathBuf,
+    title: Option<String>,
+    corpus: Option<PathBuf>,
+    verbose: bool,
+) -> Result<()> {
+    if verbose {
+        eprintln!("Loading report from: {}", input.display());
+    }
+    let report = CorpusReport::load(&input)?;
+
+    let mut html = HtmlReport::new(&report);
+    if let Some(title) = title {
+        html = html.title(title);
+    }
+    let corpus = corpus
+        .map(|path| {
+            if path.is_dir() {
+                Corpus::discover(&path)
+                    .with_context(|| format!("Failed to discover images in {}", path.display()))
+            } else {
+                Corpus::load(&path)
+                    .with_context(|| format!("Failed to load corpus from {}", path.display()))
+            }
+        })
+        .transpose()?;
+    if let Some(corpus) = &corpus {
+        html = html.categories_from_corpus(corpus);
+    }
+
+    html.write(&output)
+        .with_context(|| format!("Failed to write to {}", output.display()))?;
+    println!("Saved to: {}", output.display());
+
+    Ok(())
+}
@@ -56,6 +56,8 @@ pub fn run(
     let has_dssim = results.iter().filter(|r| r.dssim.is_some()).count();
     let has_ssimulacra2 = results.iter().filter(|r| r.ssimulacra2.is_some()).count();
     let has_psnr = results.iter().filter(|r| r.psnr.is_some()).count();
+    let has_luma_ssim = results.iter().filter(|r| r.ssim.is_some()).count();
+    let has_ms_ssim = results.iter().filter(|r| r.ms_ssim.is_some()).count();
 
     println!("Metrics:");
     if has_dssim > 0 {
@@ -67,6 +69,12 @@ pub fn run(
     if has_psnr > 0 {
         println!("  PSNR: {} results", has_psnr);
     }
+    if has_luma_ssim > 0 {
+        println!("  SSIM: {} results", has_luma_ssim);
+    }
+    if has_ms_ssim > 0 {
+        println!("  MS-SSIM: {} results", has_ms_ssim);
+    }
 
     if let Some(output_path) = output {
         let json = serde_json::to_string_pretty(&results)?;
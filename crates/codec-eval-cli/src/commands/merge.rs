@@ -0,0 +1,43 @@
+//! Report merge command.
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, bail};
+use codec_eval::CorpusReport;
+use codec_eval::eval::ReportFilter;
+
+pub fn run(
+    inputs: Vec<PathBuf>,
+    output: PathBuf,
+    filter: ReportFilter,
+    verbose: bool,
+) -> Result<()> {
+    if inputs.is_empty() {
+        bail!("No input reports given");
+    }
+
+    let mut reports = Vec::with_capacity(inputs.len());
+    for input in &inputs {
+        if verbose {
+            eprintln!("Loading report from: {}", input.display());
+        }
+        reports.push(CorpusReport::load(input)?);
+    }
+
+    let merged = CorpusReport::merge(&reports)?.filter(&filter);
+
+    println!(
+        "Merged {} reports: {} images, {} results, {} failures",
+        reports.len(),
+        merged.images.len(),
+        merged.total_results(),
+        merged.failure_summary().total
+    );
+
+    let json = serde_json::to_string_pretty(&merged)?;
+    std::fs::write(&output, json)
+        .with_context(|| format!("Failed to write to {}", output.display()))?;
+    println!("Saved to: {}", output.display());
+
+    Ok(())
+}
@@ -1,7 +1,10 @@
 //! CLI command implementations.
 
 pub mod corpus;
+pub mod diff;
+pub mod html;
 pub mod import;
+pub mod merge;
 pub mod pareto;
 pub mod sparse;
 pub mod stats;
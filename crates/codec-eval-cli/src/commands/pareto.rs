@@ -6,7 +6,13 @@ use anyhow::{Context, Result, bail};
 use codec_eval::import::ExternalResult;
 use codec_eval::stats::{ParetoFront, RDPoint};
 
-pub fn run(input: PathBuf, output: Option<PathBuf>, metric: &str, verbose: bool) -> Result<()> {
+pub fn run(
+    input: PathBuf,
+    output: Option<PathBuf>,
+    metric: &str,
+    with_time: bool,
+    verbose: bool,
+) -> Result<()> {
     if verbose {
         eprintln!("Loading results from: {}", input.display());
     }
@@ -31,6 +37,8 @@ pub fn run(input: PathBuf, output: Option<PathBuf>, metric: &str, verbose: bool)
                 "dssim" => r.dssim.map(|d| -d), // Negate so higher is better
                 "ssimulacra2" | "ssim2" => r.ssimulacra2,
                 "psnr" => r.psnr,
+                "ssim" => r.ssim,
+                "ms_ssim" | "ms-ssim" => r.ms_ssim,
                 "butteraugli" | "ba" => r.butteraugli.map(|b| -b), // Negate
                 _ => return None,
             }?;
@@ -41,6 +49,7 @@ pub fn run(input: PathBuf, output: Option<PathBuf>, metric: &str, verbose: bool)
                 bpp,
                 quality,
                 encode_time_ms: r.encode_time_ms,
+                encode_peak_bytes: None,
                 image: Some(r.image_name.clone()),
             })
         })
@@ -55,7 +64,11 @@ pub fn run(input: PathBuf, output: Option<PathBuf>, metric: &str, verbose: bool)
     }
 
     // Compute overall Pareto front
-    let front = ParetoFront::compute(&points);
+    let front = if with_time {
+        ParetoFront::compute_with_time(&points)
+    } else {
+        ParetoFront::compute(&points)
+    };
 
     println!(
         "Pareto Front ({} points from {} total)",
@@ -3,6 +3,7 @@
 use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
+use codec_eval::eval::ReportFilter;
 
 mod commands;
 
@@ -62,9 +63,86 @@ enum Commands {
         #[arg(short, long)]
         output: Option<PathBuf>,
 
-        /// Quality metric to use (ssimulacra2, dssim, psnr)
+        /// Quality metric to use (ssimulacra2, dssim, butteraugli, psnr, ssim, ms-ssim)
         #[arg(long, default_value = "dssim")]
         metric: String,
+
+        /// Also treat encode time as an objective (faster is better)
+        #[arg(long)]
+        time: bool,
+    },
+
+    /// Compare two corpus reports run-over-run
+    Diff {
+        /// Old (baseline) corpus report JSON
+        #[arg(long)]
+        old: PathBuf,
+
+        /// New corpus report JSON
+        #[arg(long)]
+        new: PathBuf,
+
+        /// Output file (JSON)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Metrics to compare (default: every metric in both reports)
+        #[arg(long)]
+        metric: Vec<String>,
+
+        /// BD-rate (percent) above which an image counts as regressed
+        #[arg(long, default_value_t = 1.0)]
+        threshold: f64,
+
+        /// Exit with status 1 if any image regressed
+        #[arg(long)]
+        fail_on_regression: bool,
+    },
+
+    /// Merge corpus reports from several machines or shards
+    Merge {
+        /// Input corpus report JSON files
+        #[arg(short, long, required = true)]
+        input: Vec<PathBuf>,
+
+        /// Output file (JSON)
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Keep only these codecs
+        #[arg(long)]
+        codec: Vec<String>,
+
+        /// Keep only these images
+        #[arg(long)]
+        image: Vec<String>,
+
+        /// Lowest quality setting to keep
+        #[arg(long)]
+        min_quality: Option<f64>,
+
+        /// Highest quality setting to keep
+        #[arg(long)]
+        max_quality: Option<f64>,
+    },
+
+    /// Render a corpus report as a self-contained HTML page
+    Html {
+        /// Input corpus report JSON
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Output HTML file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Page title (default: report name)
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Corpus manifest or directory, for per-category breakdowns
+        #[arg(long)]
+        corpus: Option<PathBuf>,
     },
 
     /// Show statistics for benchmark results
@@ -261,7 +339,44 @@ fn main() -> anyhow::Result<()> {
             input,
             output,
             metric,
-        } => commands::pareto::run(input, output, &metric, cli.verbose),
+            time,
+        } => commands::pareto::run(input, output, &metric, time, cli.verbose),
+        Commands::Diff {
+            old,
+            new,
+            output,
+            metric,
+            threshold,
+            fail_on_regression,
+        } => {
+            let clean = commands::diff::run(old, new, output, metric, threshold, cli.verbose)?;
+            if fail_on_regression && !clean {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Commands::Merge {
+            input,
+            output,
+            codec,
+            image,
+            min_quality,
+            max_quality,
+        } => {
+            let filter = ReportFilter {
+                codecs: codec,
+                images: image,
+                min_quality,
+                max_quality,
+            };
+            commands::merge::run(input, output, filter, cli.verbose)
+        }
+        Commands::Html {
+            input,
+            output,
+            title,
+            corpus,
+        } => commands::html::run(input, output, title, corpus, cli.verbose),
         Commands::Stats {
             input,
             by_codec,
@@ -13,39 +13,55 @@ pub fn compute_checksum(path: &Path) -> Result<String> {
     let file = File::open(path)?;
     let mut reader = BufReader::new(file);
     let mut buffer = [0u8; 8192];
-
-    // FNV-1a 64-bit hash
-    let mut hash: u64 = 0xcbf29ce484222325;
-    const FNV_PRIME: u64 = 0x100000001b3;
+    let mut hasher = Fnv1a::new();
 
     loop {
         let bytes_read = reader.read(&mut buffer)?;
         if bytes_read == 0 {
             break;
         }
-
-        for &byte in &buffer[..bytes_read] {
-            hash ^= u64::from(byte);
-            hash = hash.wrapping_mul(FNV_PRIME);
-        }
+        hasher.update(&buffer[..bytes_read]);
     }
 
-    Ok(format!("{hash:016x}"))
+    Ok(hasher.finish_hex())
 }
 
 /// Compute a checksum for in-memory data.
 #[must_use]
-#[allow(dead_code)] // Used in tests, may be useful for API consumers
 pub fn compute_checksum_bytes(data: &[u8]) -> String {
-    let mut hash: u64 = 0xcbf29ce484222325;
-    const FNV_PRIME: u64 = 0x100000001b3;
+    let mut hasher = Fnv1a::new();
+    hasher.update(data);
+    hasher.finish_hex()
+}
 
-    for &byte in data {
-        hash ^= u64::from(byte);
-        hash = hash.wrapping_mul(FNV_PRIME);
+/// Incremental FNV-1a 64-bit hasher.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Fnv1a(u64);
+
+impl Fnv1a {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    pub(crate) fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
     }
 
-    format!("{hash:016x}")
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    /// Hash a length-prefixed field, so adjacent fields can't run together.
+    pub(crate) fn update_field(&mut self, data: &[u8]) {
+        self.update(&(data.len() as u64).to_le_bytes());
+        self.update(data);
+    }
+
+    pub(crate) fn finish_hex(self) -> String {
+        format!("{:016x}", self.0)
+    }
 }
 
 #[cfg(test)]
@@ -67,6 +83,14 @@ mod tests {
         assert_ne!(checksum, checksum3);
     }
 
+    #[test]
+    fn test_fnv1a_incremental_matches_oneshot() {
+        let mut hasher = Fnv1a::new();
+        hasher.update(b"hello ");
+        hasher.update(b"world");
+        assert_eq!(hasher.finish_hex(), compute_checksum_bytes(b"hello world"));
+    }
+
     #[test]
     fn test_checksum_empty() {
         let checksum = compute_checksum_bytes(b"");
@@ -28,7 +28,8 @@ use std::path::{Path, PathBuf};
 use serde::{Deserialize, Serialize};
 
 pub use category::ImageCategory;
-pub use checksum::compute_checksum;
+pub(crate) use checksum::Fnv1a;
+pub use checksum::{compute_checksum, compute_checksum_bytes};
 pub use sparse::{SparseCheckout, SparseFilter, SparseStatus};
 
 use crate::error::Result;
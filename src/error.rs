@@ -86,6 +86,11 @@ pub enum Error {
     #[error("Cache error: {0}")]
     Cache(String),
 
+    /// The run was stopped through a
+    /// [`CancellationToken`](crate::eval::CancellationToken).
+    #[error("Evaluation cancelled")]
+    Cancelled,
+
     /// I/O error wrapper.
     #[error(transparent)]
     Io(#[from] std::io::Error),
@@ -98,3 +103,28 @@ pub enum Error {
     #[error("CSV error: {0}")]
     Csv(#[from] csv::Error),
 }
+
+impl Error {
+    /// Short, stable name of the error variant (e.g. `"codec"`), used in
+    /// failure records and reports.
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::ImageLoad { .. } => "image_load",
+            Self::Codec { .. } => "codec",
+            Self::DimensionMismatch { .. } => "dimension_mismatch",
+            Self::MetricCalculation { .. } => "metric_calculation",
+            Self::Corpus(_) => "corpus",
+            Self::CsvImport { .. } => "csv_import",
+            Self::InvalidQuality(_) => "invalid_quality",
+            Self::QualityBelowThreshold { .. } => "quality_below_threshold",
+            Self::UnsupportedFormat(_) => "unsupported_format",
+            Self::Report(_) => "report",
+            Self::Cache(_) => "cache",
+            Self::Cancelled => "cancelled",
+            Self::Io(_) => "io",
+            Self::Json(_) => "json",
+            Self::Csv(_) => "csv",
+        }
+    }
+}
@@ -0,0 +1,271 @@
+//! Content-addressed on-disk cache for encoded bitstreams.
+//!
+//! Entries are keyed by the source image checksum, codec id, codec version,
+//! quality and encode parameters, so re-running an evaluation with a different
+//! metric configuration reuses the existing encodes. Each entry is stored as
+//! `<cache_dir>/<codec>/<key>.bin` (the bitstream) plus `<key>.json` (metadata
+//! including the original encode time). The metadata file is written last, so
+//! an entry without it is treated as a miss.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::corpus::Fnv1a;
+use crate::error::{Error, Result};
+use crate::eval::session::EncodeRequest;
+use crate::eval::timing::Timing;
+
+/// Metadata stored next to each cached bitstream.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheMeta {
+    source_checksum: String,
+    codec_id: String,
+    codec_version: String,
+    quality: f64,
+    params: BTreeMap<String, String>,
+    encode_time_ms: f64,
+    /// Absent in entries written before repeated timing was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encode_timing: Option<Timing>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encode_peak_bytes: Option<u64>,
+}
+
+/// A cache hit: the stored bitstream and how long it originally took to encode.
+pub(crate) struct CachedEncode {
+    pub(crate) data: Vec<u8>,
+    pub(crate) encode_time: Duration,
+    pub(crate) encode_timing: Option<Timing>,
+    pub(crate) encode_peak_bytes: Option<u64>,
+}
+
+/// Encode cache rooted at `EvalConfig::cache_dir`.
+#[derive(Debug, Clone)]
+pub(crate) struct EncodeCache {
+    dir: PathBuf,
+}
+
+/// Location of one cache entry.
+pub(crate) struct CacheSlot {
+    bin: PathBuf,
+    meta_path: PathBuf,
+    meta: CacheMeta,
+}
+
+impl CacheSlot {
+    /// Path of the cached bitstream.
+    pub(crate) fn path(&self) -> &Path {
+        &self.bin
+    }
+
+    /// Load a cached encode, or `None` if the entry is missing or incomplete.
+    pub(crate) fn load(&self) -> Result<Option<CachedEncode>> {
+        let Ok(meta) = std::fs::read_to_string(&self.meta_path) else {
+            return Ok(None);
+        };
+        let Ok(meta) = serde_json::from_str::<CacheMeta>(&meta) else {
+            return Ok(None);
+        };
+        match std::fs::read(&self.bin) {
+            Ok(data) => Ok(Some(CachedEncode {
+                data,
+                encode_time: Duration::from_secs_f64(meta.encode_time_ms.max(0.0) / 1000.0),
+                encode_timing: meta.encode_timing,
+                encode_peak_bytes: meta.encode_peak_bytes,
+            })),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(Error::Cache(format!(
+                "failed to read {}: {e}",
+                self.bin.display()
+            ))),
+        }
+    }
+
+    /// Store an encode. Files are written atomically via rename.
+    pub(crate) fn store(
+        &self,
+        data: &[u8],
+        encode_time: Duration,
+        timing: &Timing,
+        peak_bytes: Option<u64>,
+    ) -> Result<()> {
+        let meta = CacheMeta {
+            encode_time_ms: encode_time.as_secs_f64() * 1000.0,
+            encode_timing: Some(timing.clone()),
+            encode_peak_bytes: peak_bytes,
+            ..self.meta.clone()
+        };
+        let meta = serde_json::to_vec_pretty(&meta)?;
+
+        write_atomic(&self.bin, data)?;
+        write_atomic(&self.meta_path, &meta)
+    }
+}
+
+impl EncodeCache {
+    pub(crate) fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Compute the slot for a (source, codec, version, request) combination.
+    pub(crate) fn slot(
+        &self,
+        source_checksum: &str,
+        codec_id: &str,
+        codec_version: &str,
+        request: &EncodeRequest,
+    ) -> CacheSlot {
+        let key = cache_key(source_checksum, codec_id, codec_version, request);
+        let dir = self.dir.join(sanitize(codec_id));
+        CacheSlot {
+            bin: dir.join(format!("{key}.bin")),
+            meta_path: dir.join(format!("{key}.json")),
+            meta: CacheMeta {
+                source_checksum: source_checksum.to_string(),
+                codec_id: codec_id.to_string(),
+                codec_version: codec_version.to_string(),
+                quality: request.quality,
+                params: request.params.clone().into_iter().collect(),
+                encode_time_ms: 0.0,
+                encode_timing: None,
+                encode_peak_bytes: None,
+            },
+        }
+    }
+}
+
+/// Hash every field that influences the encoded bitstream.
+fn cache_key(
+    source_checksum: &str,
+    codec_id: &str,
+    codec_version: &str,
+    request: &EncodeRequest,
+) -> String {
+    let mut hasher = Fnv1a::new();
+    hasher.update_field(source_checksum.as_bytes());
+    hasher.update_field(codec_id.as_bytes());
+    hasher.update_field(codec_version.as_bytes());
+    hasher.update(&request.quality.to_bits().to_le_bytes());
+
+    let params: BTreeMap<&String, &String> = request.params.iter().collect();
+    for (key, value) in params {
+        hasher.update_field(key.as_bytes());
+        hasher.update_field(value.as_bytes());
+    }
+    hasher.finish_hex()
+}
+
+/// Make a codec id (or image name) safe to use as a file or directory name.
+pub(crate) fn sanitize(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let to_cache_err =
+        |e: std::io::Error| Error::Cache(format!("failed to write {}: {e}", path.display()));
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(to_cache_err)?;
+    }
+    // Unique per process and per write, so concurrent writers of the same
+    // entry (e.g. duplicate images in a corpus) never share a temp file.
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = path.with_file_name(format!(
+        "{file_name}.{}-{}.tmp",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&tmp, data).map_err(to_cache_err)?;
+    std::fs::rename(&tmp, path).map_err(to_cache_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::timing::TimingSummary;
+
+    #[test]
+    fn test_cache_key_depends_on_every_field() {
+        let base = cache_key("abc", "webp", "1.0", &EncodeRequest::new(80.0));
+        assert_eq!(
+            base,
+            cache_key("abc", "webp", "1.0", &EncodeRequest::new(80.0))
+        );
+        assert_ne!(
+            base,
+            cache_key("abd", "webp", "1.0", &EncodeRequest::new(80.0))
+        );
+        assert_ne!(
+            base,
+            cache_key("abc", "avif", "1.0", &EncodeRequest::new(80.0))
+        );
+        assert_ne!(
+            base,
+            cache_key("abc", "webp", "1.1", &EncodeRequest::new(80.0))
+        );
+        assert_ne!(
+            base,
+            cache_key("abc", "webp", "1.0", &EncodeRequest::new(81.0))
+        );
+        assert_ne!(
+            base,
+            cache_key(
+                "abc",
+                "webp",
+                "1.0",
+                &EncodeRequest::new(80.0).with_param("method", "6")
+            )
+        );
+    }
+
+    #[test]
+    fn test_cache_key_param_order_independent() {
+        let a = EncodeRequest::new(80.0)
+            .with_param("a", "1")
+            .with_param("b", "2");
+        let b = EncodeRequest::new(80.0)
+            .with_param("b", "2")
+            .with_param("a", "1");
+        assert_eq!(
+            cache_key("abc", "webp", "1.0", &a),
+            cache_key("abc", "webp", "1.0", &b)
+        );
+    }
+
+    #[test]
+    fn test_cache_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = EncodeCache::new(dir.path());
+        let request = EncodeRequest::new(75.0);
+        let slot = cache.slot("abc", "my/codec", "1.0", &request);
+
+        assert!(slot.load().unwrap().is_none());
+        let timing = Timing {
+            samples: 1,
+            wall: TimingSummary::from_samples(&[Duration::from_millis(12)]),
+            cpu: None,
+        };
+        slot.store(b"bitstream", Duration::from_millis(12), &timing, Some(64))
+            .unwrap();
+
+        let hit = slot.load().unwrap().unwrap();
+        assert_eq!(hit.data, b"bitstream");
+        assert_eq!(hit.encode_time, Duration::from_millis(12));
+        assert_eq!(hit.encode_timing, Some(timing));
+        assert_eq!(hit.encode_peak_bytes, Some(64));
+        assert!(slot.path().starts_with(dir.path().join("my_codec")));
+    }
+}
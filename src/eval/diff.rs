@@ -0,0 +1,555 @@
+//! Run-over-run comparison of corpus reports.
+//!
+//! [`ReportDiff::compute`] (or [`CorpusReport::diff`]) matches the results of
+//! an old and a new [`CorpusReport`] by image, codec, quality setting and
+//! encoder parameters, and reports per-result, per-image and per-codec deltas
+//! in bits per pixel and in every metric both runs share. Each codec's old
+//! and new rate-distortion curves are compared with BD-rate, and images whose
+//! BD-rate exceeds [`DiffOptions::regression_threshold`] are flagged.
+//!
+//! Codec versions are deliberately not part of the match, so a report from a
+//! new encoder build can be diffed against the previous one.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use super::report::{CodecResult, CorpusReport};
+use super::session::format_params;
+use crate::metrics::custom::BUILTIN_METRICS;
+use crate::stats::{bd_rate, mean};
+
+/// Options for [`ReportDiff::compute`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffOptions {
+    /// Metrics to compare. When empty, every built-in or custom metric with
+    /// a score on both sides of at least one matched result is compared.
+    pub metrics: Vec<String>,
+
+    /// BD-rate, in percent, above which an image is flagged as regressed for
+    /// a codec and metric (positive BD-rate means the new run needs more
+    /// bits for the same score).
+    pub regression_threshold: f64,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            metrics: Vec::new(),
+            regression_threshold: 1.0,
+        }
+    }
+}
+
+impl DiffOptions {
+    /// Compare only these metrics.
+    #[must_use]
+    pub fn with_metrics(mut self, metrics: Vec<String>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Set the BD-rate regression threshold, in percent.
+    #[must_use]
+    pub fn with_regression_threshold(mut self, percent: f64) -> Self {
+        self.regression_threshold = percent;
+        self
+    }
+}
+
+/// Old and new score of one metric for a matched result.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MetricDelta {
+    /// Score in the old report.
+    pub old: f64,
+    /// Score in the new report.
+    pub new: f64,
+}
+
+impl MetricDelta {
+    /// `new - old`.
+    #[must_use]
+    pub fn delta(&self) -> f64 {
+        self.new - self.old
+    }
+}
+
+/// A result present in both reports.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResultDelta {
+    /// Codec identifier.
+    pub codec_id: String,
+    /// Codec version in the old report.
+    pub old_version: String,
+    /// Codec version in the new report.
+    pub new_version: String,
+    /// Quality setting.
+    pub quality: f64,
+    /// Encoder parameters.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, String>,
+    /// Bits per pixel in the old report.
+    pub old_bpp: f64,
+    /// Bits per pixel in the new report.
+    pub new_bpp: f64,
+    /// Compared metrics scored on both sides.
+    pub metrics: BTreeMap<String, MetricDelta>,
+}
+
+impl ResultDelta {
+    /// `new_bpp - old_bpp`.
+    #[must_use]
+    pub fn delta_bpp(&self) -> f64 {
+        self.new_bpp - self.old_bpp
+    }
+
+    /// Relative bpp change in percent (positive means larger files).
+    #[must_use]
+    pub fn delta_bpp_percent(&self) -> Option<f64> {
+        (self.old_bpp > 0.0).then(|| (self.new_bpp / self.old_bpp - 1.0) * 100.0)
+    }
+}
+
+/// One codec's RD curves of an image, or of a whole corpus, old vs new.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodecDelta {
+    /// Codec identifier.
+    pub codec_id: String,
+    /// Encoder parameters shared by the compared results.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, String>,
+    /// Number of matched results.
+    pub matched: usize,
+    /// Mean `new_bpp - old_bpp` over matched results.
+    pub mean_delta_bpp: f64,
+    /// Mean `new - old` score per metric over matched results.
+    pub mean_delta: BTreeMap<String, f64>,
+    /// BD-rate of the new curve against the old one per metric, in percent.
+    /// For a corpus this is the mean of the per-image BD-rates. Metrics whose
+    /// curves are too short or do not overlap are missing.
+    pub bd_rate: BTreeMap<String, f64>,
+}
+
+/// An image and codec whose new curve needs more bits than the threshold
+/// allows.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Regression {
+    /// Codec identifier.
+    pub codec_id: String,
+    /// Encoder parameters.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, String>,
+    /// Metric the BD-rate was computed with.
+    pub metric: String,
+    /// BD-rate in percent.
+    pub bd_rate: f64,
+}
+
+/// Comparison of one image present in both reports.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageDiff {
+    /// Image name.
+    pub name: String,
+    /// Matched results, in new-report order.
+    pub results: Vec<ResultDelta>,
+    /// Per-codec deltas and BD-rates.
+    pub codecs: Vec<CodecDelta>,
+    /// Codecs whose BD-rate exceeds the regression threshold.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regressions: Vec<Regression>,
+}
+
+impl ImageDiff {
+    /// Whether any codec regressed on this image.
+    #[must_use]
+    pub fn regressed(&self) -> bool {
+        !self.regressions.is_empty()
+    }
+}
+
+/// Comparison of two corpus reports.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportDiff {
+    /// Name of the old report.
+    pub old_name: String,
+    /// Name of the new report.
+    pub new_name: String,
+    /// Compared metrics.
+    pub metrics: Vec<String>,
+    /// Options the diff was computed with.
+    pub options: DiffOptions,
+    /// Images with at least one matched result, in new-report order.
+    pub images: Vec<ImageDiff>,
+    /// Aggregate deltas per codec and parameter set, over all images.
+    pub codecs: Vec<CodecDelta>,
+    /// Results only in the old report.
+    pub only_in_old: usize,
+    /// Results only in the new report.
+    pub only_in_new: usize,
+}
+
+/// Key a result is matched by.
+type MatchKey<'a> = (&'a str, &'a str, u64, String);
+
+fn match_key<'a>(image: &'a str, r: &'a CodecResult) -> MatchKey<'a> {
+    let params: BTreeMap<_, _> = r.codec_params.iter().collect();
+    (
+        image,
+        &r.codec_id,
+        r.quality.to_bits(),
+        format_params(params),
+    )
+}
+
+impl ReportDiff {
+    /// Compare `new` against `old`.
+    ///
+    /// Duplicate results within one report (same image, codec, quality and
+    /// parameters) match only their first occurrence.
+    #[must_use]
+    pub fn compute(old: &CorpusReport, new: &CorpusReport, options: &DiffOptions) -> Self {
+        let mut old_results: HashMap<MatchKey<'_>, &CodecResult> = HashMap::new();
+        for img in &old.images {
+            for r in &img.results {
+                old_results.entry(match_key(&img.name, r)).or_insert(r);
+            }
+        }
+        let old_total = old_results.len();
+
+        // Matched (old, new) pairs per image, in new-report order.
+        let mut matched: Vec<(&str, Vec<(&CodecResult, &CodecResult)>)> = Vec::new();
+        let mut new_total = 0;
+        let mut seen = BTreeSet::new();
+        for img in &new.images {
+            let mut pairs = Vec::new();
+            for r in &img.results {
+                let key = match_key(&img.name, r);
+                if !seen.insert(key.clone()) {
+                    continue;
+                }
+                new_total += 1;
+                if let Some(&old_r) = old_results.get(&key) {
+                    pairs.push((old_r, r));
+                }
+            }
+            if !pairs.is_empty() {
+                matched.push((&img.name, pairs));
+            }
+        }
+        let matched_total: usize = matched.iter().map(|(_, pairs)| pairs.len()).sum();
+
+        let metrics = if options.metrics.is_empty() {
+            shared_metrics(matched.iter().flat_map(|(_, pairs)| pairs))
+        } else {
+            options.metrics.clone()
+        };
+
+        let images: Vec<ImageDiff> = matched
+            .iter()
+            .map(|(name, pairs)| image_diff(name, pairs, &metrics, options))
+            .collect();
+
+        Self {
+            old_name: old.name.clone(),
+            new_name: new.name.clone(),
+            codecs: aggregate(&images),
+            metrics,
+            options: options.clone(),
+            images,
+            only_in_old: old_total - matched_total,
+            only_in_new: new_total - matched_total,
+        }
+    }
+
+    /// Images with at least one regression.
+    pub fn regressed_images(&self) -> impl Iterator<Item = &ImageDiff> {
+        self.images.iter().filter(|img| img.regressed())
+    }
+
+    /// Aggregate deltas of one codec (the first parameter set, if the codec
+    /// was swept over a parameter grid).
+    #[must_use]
+    pub fn codec(&self, codec_id: &str) -> Option<&CodecDelta> {
+        self.codecs.iter().find(|c| c.codec_id == codec_id)
+    }
+}
+
+impl CorpusReport {
+    /// Compare `new` against this report; see [`ReportDiff::compute`].
+    #[must_use]
+    pub fn diff(&self, new: &CorpusReport, options: &DiffOptions) -> ReportDiff {
+        ReportDiff::compute(self, new, options)
+    }
+}
+
+/// Built-in metrics (in [`BUILTIN_METRICS`] order), then custom metrics by
+/// name, that are scored on both sides of some pair.
+fn shared_metrics<'a>(
+    pairs: impl Iterator<Item = &'a (&'a CodecResult, &'a CodecResult)>,
+) -> Vec<String> {
+    let mut builtin = BTreeSet::new();
+    let mut custom = BTreeSet::new();
+    for (old, new) in pairs {
+        for &(name, _) in BUILTIN_METRICS {
+            if old.metrics.value(name).is_some() && new.metrics.value(name).is_some() {
+                builtin.insert(name);
+            }
+        }
+        for name in old.metrics.custom.keys() {
+            if new.metrics.custom.contains_key(name) {
+                custom.insert(name.clone());
+            }
+        }
+    }
+    BUILTIN_METRICS
+        .iter()
+        .filter(|(name, _)| builtin.contains(name))
+        .map(|(name, _)| (*name).to_string())
+        .chain(custom)
+        .collect()
+}
+
+fn image_diff(
+    name: &str,
+    pairs: &[(&CodecResult, &CodecResult)],
+    metrics: &[String],
+    options: &DiffOptions,
+) -> ImageDiff {
+    let results: Vec<ResultDelta> = pairs
+        .iter()
+        .map(|(old, new)| ResultDelta {
+            codec_id: new.codec_id.clone(),
+            old_version: old.codec_version.clone(),
+            new_version: new.codec_version.clone(),
+            quality: new.quality,
+            params: new
+                .codec_params
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            old_bpp: old.bits_per_pixel,
+            new_bpp: new.bits_per_pixel,
+            metrics: metrics
+                .iter()
+                .filter_map(|m| {
+                    let delta = MetricDelta {
+                        old: old.metrics.value(m)?,
+                        new: new.metrics.value(m)?,
+                    };
+                    Some((m.clone(), delta))
+                })
+                .collect(),
+        })
+        .collect();
+
+    let mut codecs = Vec::new();
+    let mut regressions = Vec::new();
+    for group in group_by_codec(&results) {
+        let first = group[0];
+        let mut bd_rates = BTreeMap::new();
+        for metric in metrics {
+            let curve = |pick: fn(&ResultDelta, &MetricDelta) -> (f64, f64)| -> Vec<(f64, f64)> {
+                group
+                    .iter()
+                    .filter_map(|r| Some(pick(r, r.metrics.get(metric)?)))
+                    .collect()
+            };
+            let old_curve = curve(|r, m| (r.old_bpp, m.old));
+            let new_curve = curve(|r, m| (r.new_bpp, m.new));
+            if let Some(rate) = bd_rate(&old_curve, &new_curve) {
+                if rate > options.regression_threshold {
+                    regressions.push(Regression {
+                        codec_id: first.codec_id.clone(),
+                        params: first.params.clone(),
+                        metric: metric.clone(),
+                        bd_rate: rate,
+                    });
+                }
+                bd_rates.insert(metric.clone(), rate);
+            }
+        }
+        codecs.push(codec_delta(&group, metrics, bd_rates));
+    }
+
+    ImageDiff {
+        name: name.to_string(),
+        results,
+        codecs,
+        regressions,
+    }
+}
+
+/// Results grouped by codec and parameters, in order of first appearance.
+fn group_by_codec(results: &[ResultDelta]) -> Vec<Vec<&ResultDelta>> {
+    let mut groups: Vec<Vec<&ResultDelta>> = Vec::new();
+    for r in results {
+        match groups
+            .iter_mut()
+            .find(|g| g[0].codec_id == r.codec_id && g[0].params == r.params)
+        {
+            Some(group) => group.push(r),
+            None => groups.push(vec![r]),
+        }
+    }
+    groups
+}
+
+fn codec_delta(
+    group: &[&ResultDelta],
+    metrics: &[String],
+    bd_rate: BTreeMap<String, f64>,
+) -> CodecDelta {
+    let bpp: Vec<f64> = group.iter().map(|r| r.delta_bpp()).collect();
+    let mean_delta = metrics
+        .iter()
+        .filter_map(|m| {
+            let deltas: Vec<f64> = group
+                .iter()
+                .filter_map(|r| Some(r.metrics.get(m)?.delta()))
+                .collect();
+            (!deltas.is_empty()).then(|| (m.clone(), mean(&deltas)))
+        })
+        .collect();
+    CodecDelta {
+        codec_id: group[0].codec_id.clone(),
+        params: group[0].params.clone(),
+        matched: group.len(),
+        mean_delta_bpp: mean(&bpp),
+        mean_delta,
+        bd_rate,
+    }
+}
+
+/// Corpus-wide deltas: per-result means over all images, and the mean of the
+/// per-image BD-rates.
+fn aggregate(images: &[ImageDiff]) -> Vec<CodecDelta> {
+    let all: Vec<ResultDelta> = images.iter().flat_map(|img| img.results.clone()).collect();
+    let metrics: Vec<String> = images
+        .iter()
+        .flat_map(|img| img.codecs.iter().flat_map(|c| c.mean_delta.keys().cloned()))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    group_by_codec(&all)
+        .into_iter()
+        .map(|group| {
+            let mut rates: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+            for c in images.iter().flat_map(|img| &img.codecs) {
+                if c.codec_id == group[0].codec_id && c.params == group[0].params {
+                    for (metric, &rate) in &c.bd_rate {
+                        rates.entry(metric.clone()).or_default().push(rate);
+                    }
+                }
+            }
+            let bd_rate = rates.into_iter().map(|(m, r)| (m, mean(&r))).collect();
+            codec_delta(&group, &metrics, bd_rate)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::report::ImageReport;
+    use crate::metrics::MetricResult;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    fn result(codec: &str, quality: f64, bpp: f64, ssim2: f64) -> CodecResult {
+        CodecResult {
+            codec_id: codec.to_string(),
+            codec_version: "1.0".to_string(),
+            quality,
+            file_size: (bpp * 1000.0) as usize,
+            bits_per_pixel: bpp,
+            encode_time: Duration::from_millis(10),
+            decode_time: None,
+            encode_timing: None,
+            decode_timing: None,
+            encode_peak_bytes: None,
+            decode_peak_bytes: None,
+            metrics: MetricResult {
+                ssimulacra2: Some(ssim2),
+                ..MetricResult::default()
+            },
+            perception: None,
+            cached_path: None,
+            heatmaps: BTreeMap::new(),
+            search: None,
+            generations: Vec::new(),
+            codec_params: HashMap::new(),
+        }
+    }
+
+    /// Codec "a"'s (bpp, ssimulacra2) curve on an image with `scale`.
+    fn curve(scale: f64) -> Vec<(f64, f64)> {
+        [(0.5, 60.0), (1.0, 70.0), (2.0, 80.0), (4.0, 90.0)]
+            .map(|(bpp, score)| (bpp * scale, score))
+            .to_vec()
+    }
+
+    fn report(name: &str, images: &[(&str, f64)]) -> CorpusReport {
+        // `scale` multiplies every file size of codec "a" on that image.
+        let mut report = CorpusReport::new(name.to_string());
+        for &(image, scale) in images {
+            let mut img = ImageReport::new(image.to_string(), 10, 10);
+            for (q, bpp, score) in [(50.0, 0.5, 60.0), (70.0, 1.0, 70.0), (80.0, 2.0, 80.0)] {
+                img.results.push(result("a", q, bpp * scale, score));
+                img.results.push(result("b", q, bpp, score));
+            }
+            img.results.push(result("a", 90.0, 4.0 * scale, 90.0));
+            img.results.push(result("b", 90.0, 4.0, 90.0));
+            report.images.push(img);
+        }
+        report
+    }
+
+    #[test]
+    fn test_diff_flags_regressed_image() {
+        let old = report("old", &[("x", 1.0), ("y", 1.0)]);
+        let mut new = report("new", &[("x", 1.0), ("y", 1.2), ("z", 1.0)]);
+        new.images[0].results[0].codec_version = "2.0".to_string();
+
+        let diff = old.diff(&new, &DiffOptions::default());
+        assert_eq!(diff.metrics, vec!["ssimulacra2".to_string()]);
+        assert_eq!(diff.images.len(), 2);
+        assert_eq!(diff.only_in_old, 0);
+        assert_eq!(diff.only_in_new, 8);
+        assert_eq!(diff.images[0].results[0].new_version, "2.0");
+
+        let regressed: Vec<&str> = diff.regressed_images().map(|i| i.name.as_str()).collect();
+        assert_eq!(regressed, vec!["y"]);
+        let regression = &diff.images[1].regressions[0];
+        assert_eq!(regression.codec_id, "a");
+        let expected = bd_rate(&curve(1.0), &curve(1.2)).unwrap();
+        assert!(
+            (expected - 20.0).abs() < 1e-6,
+            "1.2x rate should be +20%, got {expected}"
+        );
+        assert!(
+            (regression.bd_rate - expected).abs() < 1e-9,
+            "{regression:?}"
+        );
+
+        let a = diff.codec("a").unwrap();
+        assert_eq!(a.matched, 8);
+        assert!(a.mean_delta_bpp > 0.0);
+        assert_eq!(a.mean_delta["ssimulacra2"], 0.0);
+        // Mean of image x (unchanged) and image y.
+        assert!((a.bd_rate["ssimulacra2"] - expected / 2.0).abs() < 1e-9);
+        assert!(diff.codec("b").unwrap().bd_rate["ssimulacra2"].abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_diff_threshold_and_metric_selection() {
+        let old = report("old", &[("x", 1.0)]);
+        let new = report("new", &[("x", 1.2)]);
+        let lenient = DiffOptions::default().with_regression_threshold(100.0);
+        assert_eq!(old.diff(&new, &lenient).regressed_images().count(), 0);
+
+        let psnr_only = DiffOptions::default().with_metrics(vec!["psnr".to_string()]);
+        let diff = old.diff(&new, &psnr_only);
+        assert!(diff.images[0].results[0].metrics.is_empty());
+        assert!(diff.codec("a").unwrap().bd_rate.is_empty());
+    }
+}
@@ -0,0 +1,191 @@
+//! Generation-loss evaluation.
+//!
+//! Images are often recompressed several times on their way to a viewer:
+//! upload, CDN, social share. In [`EvalMode::GenerationLoss`] the session
+//! encodes each image at every quality level as usual, then feeds the decoded
+//! output back through the same codec, or through a configured chain of
+//! codecs, for a number of generations. Every generation is scored against
+//! the original image and recorded in
+//! [`CodecResult::generations`](super::CodecResult::generations), so the
+//! speed at which each codec degrades under re-encoding can be charted with
+//! [`CorpusReport::generation_curves`].
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::report::CorpusReport;
+#[cfg(doc)]
+use super::search::EvalMode;
+use super::session::format_params;
+use crate::metrics::MetricResult;
+use crate::stats::mean;
+
+/// Settings for [`EvalMode::GenerationLoss`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerationLoss {
+    /// Total number of encodes per job, including the first.
+    pub generations: usize,
+    /// Codecs for generations 2 and up, cycled in order. Empty means every
+    /// generation re-encodes with the job's own codec, quality and params.
+    pub chain: Vec<ChainStep>,
+}
+
+impl GenerationLoss {
+    /// Re-encode with the same codec and settings for `generations` encodes
+    /// in total.
+    #[must_use]
+    pub fn new(generations: usize) -> Self {
+        Self {
+            generations: generations.max(1),
+            chain: Vec::new(),
+        }
+    }
+
+    /// Append a step to the chain of codecs used after the first generation.
+    ///
+    /// The codec must be registered with the session.
+    #[must_use]
+    pub fn then(mut self, codec_id: &str, quality: f64) -> Self {
+        self.chain.push(ChainStep {
+            codec_id: codec_id.to_string(),
+            quality,
+        });
+        self
+    }
+
+    /// Codec and quality of `generation` (2 or more), or `None` to repeat the
+    /// job's own.
+    #[must_use]
+    pub fn step(&self, generation: usize) -> Option<&ChainStep> {
+        if self.chain.is_empty() || generation < 2 {
+            return None;
+        }
+        Some(&self.chain[(generation - 2) % self.chain.len()])
+    }
+
+    /// `generations=N`, plus the chain as `codec@quality` steps if set.
+    pub(crate) fn summary(&self) -> String {
+        if self.chain.is_empty() {
+            return format!("generations={}", self.generations);
+        }
+        let steps: Vec<String> = self
+            .chain
+            .iter()
+            .map(|step| format!("{}@{}", step.codec_id, step.quality))
+            .collect();
+        format!(
+            "generations={} chain=[{}]",
+            self.generations,
+            steps.join(",")
+        )
+    }
+}
+
+/// One re-encode in a [`GenerationLoss`] chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainStep {
+    /// Registered codec id.
+    pub codec_id: String,
+    /// Encoder quality setting.
+    pub quality: f64,
+}
+
+/// Size and scores of one generation, stored in
+/// [`CodecResult::generations`](super::CodecResult::generations).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationResult {
+    /// 1 for the first encode of the original.
+    pub generation: usize,
+    /// Codec that produced this generation.
+    pub codec_id: String,
+    /// Encoder quality setting of this generation.
+    pub quality: f64,
+    /// Encoded size in bytes.
+    pub file_size: usize,
+    /// Bits per pixel of this generation's encode.
+    pub bits_per_pixel: f64,
+    /// Scores of this generation's decode against the original image.
+    pub metrics: MetricResult,
+}
+
+/// Mean size and score per generation for one codec setting, averaged over
+/// images.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GenerationCurve {
+    /// Codec of the first generation.
+    pub codec_id: String,
+    /// Quality setting of the first generation.
+    pub quality: f64,
+    /// Encoder parameters of the first generation, `name=value` comma-separated.
+    pub params: String,
+    /// Mean bits per pixel, indexed by generation - 1.
+    pub mean_bpp: Vec<f64>,
+    /// Mean score of the metric, indexed by generation - 1.
+    pub mean_score: Vec<f64>,
+}
+
+impl CorpusReport {
+    /// Per codec, quality and params, the mean bpp and `metric` score of
+    /// each generation across images.
+    ///
+    /// Results without generations (other modes) are ignored. Curves are
+    /// ordered by codec id, then quality.
+    #[must_use]
+    pub fn generation_curves(&self, metric: &str) -> Vec<GenerationCurve> {
+        type Samples = Vec<(Vec<f64>, Vec<f64>)>;
+        let mut groups: BTreeMap<(String, u64, String), Samples> = BTreeMap::new();
+        for result in self.images.iter().flat_map(|img| &img.results) {
+            if result.generations.is_empty() {
+                continue;
+            }
+            let params: BTreeMap<&String, &String> = result.codec_params.iter().collect();
+            let key = (
+                result.codec_id.clone(),
+                result.quality.to_bits(),
+                format_params(params),
+            );
+            let samples = groups.entry(key).or_default();
+            for generation in &result.generations {
+                let index = generation.generation.saturating_sub(1);
+                if samples.len() <= index {
+                    samples.resize(index + 1, (Vec::new(), Vec::new()));
+                }
+                samples[index].0.push(generation.bits_per_pixel);
+                if let Some(score) = generation.metrics.value(metric) {
+                    samples[index].1.push(score);
+                }
+            }
+        }
+
+        // Bit patterns of non-negative floats sort numerically.
+        groups
+            .into_iter()
+            .map(|((codec_id, quality, params), samples)| GenerationCurve {
+                codec_id,
+                quality: f64::from_bits(quality),
+                params,
+                mean_bpp: samples.iter().map(|(bpp, _)| mean(bpp)).collect(),
+                mean_score: samples.iter().map(|(_, score)| mean(score)).collect(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_steps_cycle() {
+        let same = GenerationLoss::new(5);
+        assert_eq!(same.step(3), None);
+        assert_eq!(GenerationLoss::new(0).generations, 1);
+
+        let chain = GenerationLoss::new(6).then("webp", 80.0).then("jpeg", 75.0);
+        assert_eq!(chain.step(1), None);
+        assert_eq!(chain.step(2).unwrap().codec_id, "webp");
+        assert_eq!(chain.step(3).unwrap().codec_id, "jpeg");
+        assert_eq!(chain.step(4).unwrap().codec_id, "webp");
+    }
+}
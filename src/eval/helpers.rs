@@ -33,7 +33,8 @@
 
 use crate::error::{Error, Result};
 use crate::metrics::{
-    self, MetricConfig, MetricResult, PerceptionLevel, butteraugli, dssim, ssimulacra2,
+    self, MetricConfig, MetricResult, PerceptionLevel, butteraugli, ciede2000, dssim, ssim,
+    ssimulacra2,
 };
 use crate::viewing::ViewingCondition;
 use imgref::ImgVec;
@@ -144,8 +145,14 @@ pub fn evaluate_single(
         result.dssim = Some(dssim::calculate_dssim(&ref_rgba, &enc_rgba, &viewing)?);
     }
 
-    // SSIMULACRA2 and Butteraugli use raw u8 buffers
-    if config.ssimulacra2 || config.butteraugli || config.psnr {
+    // SSIMULACRA2, Butteraugli, PSNR, SSIM and CIEDE2000 use raw u8 buffers
+    if config.ssimulacra2
+        || config.butteraugli
+        || config.psnr
+        || config.ssim
+        || config.ms_ssim
+        || config.ciede2000
+    {
         let ref_buf: Vec<u8> = reference_final
             .pixels()
             .flat_map(|p| [p.r, p.g, p.b])
@@ -159,14 +166,35 @@ pub fn evaluate_single(
         }
 
         if config.butteraugli {
-            result.butteraugli = Some(butteraugli::calculate_butteraugli(
-                &ref_buf, &enc_buf, width, height,
-            )?);
+            let (scores, _) = butteraugli::calculate_butteraugli_scores(
+                &ref_buf,
+                &enc_buf,
+                width,
+                height,
+                config.butteraugli_p,
+            )?;
+            result.butteraugli = Some(scores.max);
+            result.butteraugli_pnorm = Some(scores.pnorm);
         }
 
         if config.psnr {
             result.psnr = Some(metrics::calculate_psnr(&ref_buf, &enc_buf, width, height));
         }
+
+        if config.ssim {
+            result.ssim = Some(ssim::calculate_ssim(&ref_buf, &enc_buf, width, height)?);
+        }
+
+        if config.ms_ssim {
+            result.ms_ssim = Some(ssim::calculate_ms_ssim(&ref_buf, &enc_buf, width, height)?);
+        }
+
+        if config.ciede2000 {
+            let (scores, _) = ciede2000::calculate_ciede2000(&ref_buf, &enc_buf, width, height)?;
+            result.ciede2000 = Some(scores.mean);
+            result.ciede2000_p95 = Some(scores.p95);
+            result.ciede2000_max = Some(scores.max);
+        }
     }
 
     Ok(result)
@@ -222,6 +250,7 @@ pub fn assert_quality(
         butteraugli: false,
         psnr: false,
         xyb_roundtrip: false,
+        ..MetricConfig::default()
     };
 
     let result = evaluate_single(reference, encoded, &config)?;
@@ -299,6 +328,7 @@ pub fn assert_perception_level(
         butteraugli: false,
         psnr: false,
         xyb_roundtrip: false,
+        ..MetricConfig::default()
     };
 
     let result = evaluate_single(reference, encoded, &config)?;
@@ -345,6 +375,13 @@ mod tests {
         assert!(result.dssim.unwrap() < 0.0001);
         assert!(result.ssimulacra2.unwrap() > 99.0);
         assert!(result.butteraugli.unwrap() < 0.1);
+        assert!(result.butteraugli_pnorm.unwrap() <= result.butteraugli.unwrap());
+
+        let config = MetricConfig::fast().with_ssim().with_ciede2000();
+        let result = evaluate_single(&img, &img, &config).unwrap();
+        assert_eq!(result.ssim, Some(1.0));
+        assert_eq!(result.ms_ssim, Some(1.0));
+        assert_eq!(result.ciede2000_max, Some(0.0));
     }
 
     #[test]
@@ -0,0 +1,979 @@
+//! Self-contained HTML report.
+//!
+//! [`HtmlReport`] renders a [`CorpusReport`] as a single HTML file with no
+//! network assets: styles, scripts, charts and images are all inline. The
+//! page has
+//!
+//! - a sortable per-codec summary table,
+//! - one rate-distortion chart per metric (mean bpp and score per codec and
+//!   quality setting) with hover tooltips,
+//! - a per-category breakdown when image categories are known,
+//! - a per-image view with every result and an inspector that flips or
+//!   slides between the original and each decoded variant or heatmap.
+//!
+//! Reports do not store pixels, so the inspector only has images when a
+//! loader is registered with [`HtmlReport::images`]; heatmaps written by
+//! `EvalConfig::heatmaps` are embedded from [`CodecResult::heatmaps`].
+//!
+//! ```rust,ignore
+//! use codec_eval::eval::HtmlReport;
+//!
+//! HtmlReport::new(&report)
+//!     .title("JPEG encoders, October")
+//!     .categories_from_corpus(&corpus)
+//!     .images(|image, result| match result {
+//!         None => load_original(&image.name),
+//!         Some(r) => decode(r.cached_path.as_ref()?),
+//!     })
+//!     .write("report.html")?;
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::path::Path;
+
+use serde::Serialize;
+
+use super::report::{CodecResult, CorpusReport, ImageReport};
+use super::session::ImageData;
+use crate::corpus::{Corpus, ImageCategory};
+use crate::error::{Error, Result};
+use crate::metrics::custom::{BUILTIN_METRICS, builtin_direction};
+use crate::stats::{QualityDirection, mean, median};
+
+/// Supplies the original (`None`) or a decoded result of an image.
+type ImageLoader<'a> = Box<dyn Fn(&ImageReport, Option<&CodecResult>) -> Option<ImageData> + 'a>;
+
+/// Series colours, cycled per codec.
+const PALETTE: [&str; 10] = [
+    "#1f77b4", "#d62728", "#2ca02c", "#ff7f0e", "#9467bd", "#8c564b", "#e377c2", "#17becf",
+    "#bcbd22", "#7f7f7f",
+];
+
+/// Builder for a self-contained HTML report of a [`CorpusReport`].
+pub struct HtmlReport<'a> {
+    report: &'a CorpusReport,
+    title: Option<String>,
+    metrics: Vec<String>,
+    categories: HashMap<String, ImageCategory>,
+    images: Option<ImageLoader<'a>>,
+}
+
+impl<'a> HtmlReport<'a> {
+    /// Create a report page for `report`.
+    #[must_use]
+    pub fn new(report: &'a CorpusReport) -> Self {
+        Self {
+            report,
+            title: None,
+            metrics: Vec::new(),
+            categories: HashMap::new(),
+            images: None,
+        }
+    }
+
+    /// Set the page title (defaults to the report name).
+    #[must_use]
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Show only these metrics, in this order. By default every built-in
+    /// metric with a score, then every custom metric, is shown.
+    #[must_use]
+    pub fn metrics(mut self, metrics: Vec<String>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Assign `category` to the image named `image`.
+    #[must_use]
+    pub fn category(mut self, image: impl Into<String>, category: ImageCategory) -> Self {
+        self.categories.insert(image.into(), category);
+        self
+    }
+
+    /// Take image categories from the corpus the report was evaluated on.
+    #[must_use]
+    pub fn categories_from_corpus(mut self, corpus: &Corpus) -> Self {
+        for image in &corpus.images {
+            if let Some(category) = image.category {
+                self.categories.insert(image.name().to_string(), category);
+            }
+        }
+        self
+    }
+
+    /// Embed images in the inspector.
+    ///
+    /// `loader` is called with `None` for each original and with each result
+    /// for its decoded variant; returning `None` leaves that image out. Every
+    /// embedded image is stored as a PNG, so for large corpora only return
+    /// the variants worth sharing.
+    #[must_use]
+    pub fn images(
+        mut self,
+        loader: impl Fn(&ImageReport, Option<&CodecResult>) -> Option<ImageData> + 'a,
+    ) -> Self {
+        self.images = Some(Box::new(loader));
+        self
+    }
+
+    /// Render the page.
+    pub fn render(&self) -> Result<String> {
+        let metrics = self.shown_metrics();
+        let title = self.title.as_deref().unwrap_or(&self.report.name);
+
+        let mut body = String::new();
+        self.write_header(&mut body, title);
+        self.write_codec_table(&mut body, &metrics);
+        self.write_charts(&mut body, &metrics);
+        self.write_categories(&mut body, &metrics);
+        body.push_str(
+            "<section id=\"images\"><h2>Images</h2>\
+             <p><select id=\"image-select\"></select></p>\
+             <div id=\"inspector\"></div><div id=\"image-results\"></div></section>\n",
+        );
+
+        let payload = self.payload(&metrics)?;
+        let json = serde_json::to_string(&payload)?.replace("</", "<\\/");
+
+        Ok(format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+             <title>{}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n{body}\
+             <div id=\"tip\"></div>\n\
+             <script type=\"application/json\" id=\"report-data\">{json}</script>\n\
+             <script>{SCRIPT}</script>\n</body>\n</html>\n",
+            escape(title)
+        ))
+    }
+
+    /// Render the page and write it to `path`.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, self.render()?)?;
+        Ok(())
+    }
+
+    fn shown_metrics(&self) -> Vec<String> {
+        if !self.metrics.is_empty() {
+            return self.metrics.clone();
+        }
+        let results: Vec<&CodecResult> = self.results().map(|(_, r)| r).collect();
+        let custom: BTreeSet<&String> = results
+            .iter()
+            .flat_map(|r| r.metrics.custom.keys())
+            .collect();
+        BUILTIN_METRICS
+            .iter()
+            .map(|(name, _)| *name)
+            .filter(|name| results.iter().any(|r| r.metrics.value(name).is_some()))
+            .map(str::to_string)
+            .chain(custom.into_iter().cloned())
+            .collect()
+    }
+
+    fn results(&self) -> impl Iterator<Item = (&'a ImageReport, &'a CodecResult)> {
+        self.report
+            .images
+            .iter()
+            .flat_map(|img| img.results.iter().map(move |r| (img, r)))
+    }
+
+    fn write_header(&self, out: &mut String, title: &str) {
+        let failures = self.report.failure_summary().total;
+        let _ = write!(
+            out,
+            "<header><h1>{}</h1><p>{} images · {} codecs · {} results",
+            escape(title),
+            self.report.images.len(),
+            self.report.codec_ids().len(),
+            self.report.total_results()
+        );
+        if failures > 0 {
+            let _ = write!(out, " · <span class=\"bad\">{failures} failed jobs</span>");
+        }
+        let _ = write!(
+            out,
+            " · generated {}</p>",
+            escape(
+                &self
+                    .report
+                    .timestamp
+                    .format("%Y-%m-%d %H:%M UTC")
+                    .to_string()
+            )
+        );
+        if !self.report.config_summary.is_empty() {
+            let _ = write!(
+                out,
+                "<p class=\"config\">{}</p>",
+                escape(&self.report.config_summary)
+            );
+        }
+        out.push_str("</header>\n");
+    }
+
+    fn write_codec_table(&self, out: &mut String, metrics: &[String]) {
+        let failures = self.report.failure_summary();
+        out.push_str("<section><h2>Codecs</h2><table class=\"sortable\"><thead><tr>");
+        out.push_str(
+            "<th>Codec</th><th>Version</th><th>Images</th><th>Results</th><th>Mean bpp</th>",
+        );
+        for metric in metrics {
+            let _ = write!(out, "<th>{}</th>", metric_header(metric));
+        }
+        out.push_str("<th>Median encode ms</th><th>Median decode ms</th><th>Failures</th></tr></thead><tbody>");
+        for codec in self.report.codec_ids() {
+            let results: Vec<(&ImageReport, &CodecResult)> = self
+                .results()
+                .filter(|(_, r)| r.codec_id == codec)
+                .collect();
+            let versions: BTreeSet<&str> = results
+                .iter()
+                .map(|(_, r)| r.codec_version.as_str())
+                .collect();
+            let images: BTreeSet<&str> = results.iter().map(|(img, _)| img.name.as_str()).collect();
+            let bpp: Vec<f64> = results.iter().map(|(_, r)| r.bits_per_pixel).collect();
+            let encode: Vec<f64> = results
+                .iter()
+                .map(|(_, r)| r.encode_time.as_secs_f64() * 1000.0)
+                .collect();
+            let decode: Vec<f64> = results
+                .iter()
+                .filter_map(|(_, r)| Some(r.decode_time?.as_secs_f64() * 1000.0))
+                .collect();
+
+            let _ = write!(
+                out,
+                "<tr><td>{}</td><td>{}</td>{}{}{}",
+                escape(&codec),
+                escape(&versions.into_iter().collect::<Vec<_>>().join(", ")),
+                number_cell(Some(images.len() as f64), 0),
+                number_cell(Some(results.len() as f64), 0),
+                number_cell(Some(mean(&bpp)), 4)
+            );
+            for metric in metrics {
+                let values: Vec<f64> = results
+                    .iter()
+                    .filter_map(|(_, r)| r.metrics.value(metric))
+                    .collect();
+                out.push_str(&metric_cell(
+                    metric,
+                    (!values.is_empty()).then(|| mean(&values)),
+                ));
+            }
+            let _ = write!(
+                out,
+                "{}{}{}</tr>",
+                number_cell(Some(median(&encode)), 1),
+                number_cell((!decode.is_empty()).then(|| median(&decode)), 1),
+                number_cell(
+                    Some(failures.by_codec.get(&codec).copied().unwrap_or_default() as f64),
+                    0
+                )
+            );
+        }
+        out.push_str("</tbody></table></section>\n");
+    }
+
+    fn write_charts(&self, out: &mut String, metrics: &[String]) {
+        out.push_str("<section><h2>Rate-distortion</h2><div class=\"charts\">");
+        for metric in metrics {
+            let series: Vec<Series> = self
+                .report
+                .codec_ids()
+                .into_iter()
+                .enumerate()
+                .map(|(i, codec)| Series {
+                    points: self.mean_curve(&codec, metric),
+                    color: PALETTE[i % PALETTE.len()],
+                    codec,
+                })
+                .filter(|s| !s.points.is_empty())
+                .collect();
+            if !series.is_empty() {
+                out.push_str(&rd_chart(metric, &series));
+            }
+        }
+        out.push_str("</div></section>\n");
+    }
+
+    /// Mean `(bpp, score, quality)` per quality setting, sorted by bpp.
+    fn mean_curve(&self, codec: &str, metric: &str) -> Vec<(f64, f64, f64)> {
+        let mut by_quality: BTreeMap<u64, (f64, Vec<f64>, Vec<f64>)> = BTreeMap::new();
+        for (_, r) in self.results().filter(|(_, r)| r.codec_id == codec) {
+            if let Some(score) = r.metrics.value(metric) {
+                let entry = by_quality
+                    .entry(r.quality.to_bits())
+                    .or_insert_with(|| (r.quality, Vec::new(), Vec::new()));
+                entry.1.push(r.bits_per_pixel);
+                entry.2.push(score);
+            }
+        }
+        let mut points: Vec<(f64, f64, f64)> = by_quality
+            .into_values()
+            .map(|(quality, bpp, score)| (mean(&bpp), mean(&score), quality))
+            .filter(|(bpp, score, _)| bpp.is_finite() && score.is_finite())
+            .collect();
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        points
+    }
+
+    fn write_categories(&self, out: &mut String, metrics: &[String]) {
+        let mut categories: BTreeMap<String, Vec<&ImageReport>> = BTreeMap::new();
+        for img in &self.report.images {
+            if let Some(category) = self.categories.get(&img.name) {
+                categories
+                    .entry(category.to_string())
+                    .or_default()
+                    .push(img);
+            }
+        }
+        if categories.is_empty() {
+            return;
+        }
+        out.push_str("<section><h2>Categories</h2><table class=\"sortable\"><thead><tr>");
+        out.push_str("<th>Category</th><th>Codec</th><th>Images</th><th>Mean bpp</th>");
+        for metric in metrics {
+            let _ = write!(out, "<th>{}</th>", metric_header(metric));
+        }
+        out.push_str("</tr></thead><tbody>");
+        for (category, images) in &categories {
+            for codec in self.report.codec_ids() {
+                let results: Vec<&CodecResult> = images
+                    .iter()
+                    .flat_map(|img| img.results_for_codec(&codec))
+                    .collect();
+                if results.is_empty() {
+                    continue;
+                }
+                let bpp: Vec<f64> = results.iter().map(|r| r.bits_per_pixel).collect();
+                let _ = write!(
+                    out,
+                    "<tr><td>{}</td><td>{}</td>{}{}",
+                    escape(category),
+                    escape(&codec),
+                    number_cell(Some(images.len() as f64), 0),
+                    number_cell(Some(mean(&bpp)), 4)
+                );
+                for metric in metrics {
+                    let values: Vec<f64> = results
+                        .iter()
+                        .filter_map(|r| r.metrics.value(metric))
+                        .collect();
+                    out.push_str(&metric_cell(
+                        metric,
+                        (!values.is_empty()).then(|| mean(&values)),
+                    ));
+                }
+                out.push_str("</tr>");
+            }
+        }
+        out.push_str("</tbody></table></section>\n");
+    }
+
+    fn payload(&self, metrics: &[String]) -> Result<Payload> {
+        let mut images = Vec::with_capacity(self.report.images.len());
+        for img in &self.report.images {
+            let original = self
+                .images
+                .as_ref()
+                .and_then(|load| load(img, None))
+                .map(|data| png_data_uri(&data))
+                .transpose()?;
+            let mut results = Vec::with_capacity(img.results.len());
+            for r in &img.results {
+                let image = self
+                    .images
+                    .as_ref()
+                    .and_then(|load| load(img, Some(r)))
+                    .map(|data| png_data_uri(&data))
+                    .transpose()?;
+                let mut heatmaps = BTreeMap::new();
+                for (metric, path) in &r.heatmaps {
+                    if let Ok(bytes) = std::fs::read(path) {
+                        heatmaps.insert(
+                            metric.clone(),
+                            format!("data:image/png;base64,{}", base64(&bytes)),
+                        );
+                    }
+                }
+                let params: BTreeMap<&String, &String> = r.codec_params.iter().collect();
+                results.push(ResultPayload {
+                    codec: r.codec_id.clone(),
+                    version: r.codec_version.clone(),
+                    quality: r.quality,
+                    params: params
+                        .into_iter()
+                        .map(|(k, v)| format!("{k}={v}"))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    bpp: r.bits_per_pixel,
+                    bytes: r.file_size,
+                    encode_ms: r.encode_time.as_secs_f64() * 1000.0,
+                    scores: metrics.iter().map(|m| r.metrics.value(m)).collect(),
+                    image,
+                    heatmaps,
+                });
+            }
+            images.push(ImagePayload {
+                name: img.name.clone(),
+                category: self.categories.get(&img.name).map(ToString::to_string),
+                width: img.width,
+                height: img.height,
+                original,
+                results,
+                failures: img
+                    .failures
+                    .iter()
+                    .map(|f| {
+                        let quality = f.quality.map_or_else(String::new, |q| format!(" q{q}"));
+                        format!("{}{quality}: {}", f.codec_id, f.message)
+                    })
+                    .collect(),
+            });
+        }
+        Ok(Payload {
+            metrics: metrics.to_vec(),
+            images,
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct Payload {
+    metrics: Vec<String>,
+    images: Vec<ImagePayload>,
+}
+
+#[derive(Serialize)]
+struct ImagePayload {
+    name: String,
+    category: Option<String>,
+    width: u32,
+    height: u32,
+    original: Option<String>,
+    results: Vec<ResultPayload>,
+    failures: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ResultPayload {
+    codec: String,
+    version: String,
+    quality: f64,
+    params: String,
+    bpp: f64,
+    bytes: usize,
+    encode_ms: f64,
+    scores: Vec<Option<f64>>,
+    image: Option<String>,
+    heatmaps: BTreeMap<String, String>,
+}
+
+/// One codec's curve in an RD chart.
+struct Series {
+    codec: String,
+    color: &'static str,
+    points: Vec<(f64, f64, f64)>,
+}
+
+/// Inline SVG chart of score against bpp, one polyline per codec.
+fn rd_chart(metric: &str, series: &[Series]) -> String {
+    const W: f64 = 560.0;
+    const H: f64 = 340.0;
+    const LEFT: f64 = 60.0;
+    const RIGHT: f64 = 16.0;
+    const TOP: f64 = 28.0;
+    const BOTTOM: f64 = 44.0;
+
+    let points = series.iter().flat_map(|s| &s.points);
+    let (mut x0, mut x1, mut y0, mut y1) = (f64::MAX, f64::MIN, f64::MAX, f64::MIN);
+    for &(x, y, _) in points {
+        x0 = x0.min(x);
+        x1 = x1.max(x);
+        y0 = y0.min(y);
+        y1 = y1.max(y);
+    }
+    let pad = |lo: f64, hi: f64| {
+        let span = if hi > lo { hi - lo } else { lo.abs().max(1.0) };
+        (lo - span * 0.05, hi + span * 0.05)
+    };
+    let (x0, x1) = pad(x0, x1);
+    let (y0, y1) = pad(y0, y1);
+    let px = |x: f64| LEFT + (x - x0) / (x1 - x0) * (W - LEFT - RIGHT);
+    let py = |y: f64| H - BOTTOM - (y - y0) / (y1 - y0) * (H - TOP - BOTTOM);
+
+    let mut svg = String::new();
+    let _ = write!(
+        svg,
+        "<figure class=\"chart\"><svg xmlns=\"http://www.w3.org/2000/svg\" \
+         viewBox=\"0 0 {W} {H}\" role=\"img\"><text x=\"{}\" y=\"18\" class=\"title\">{}</text>",
+        W / 2.0,
+        metric_header(metric)
+    );
+    for i in 0..=4 {
+        let t = f64::from(i) / 4.0;
+        let (x, y) = (x0 + (x1 - x0) * t, y0 + (y1 - y0) * t);
+        let (cx, cy) = (px(x), py(y));
+        let _ = write!(
+            svg,
+            "<line class=\"grid\" x1=\"{LEFT}\" x2=\"{}\" y1=\"{cy:.1}\" y2=\"{cy:.1}\"/>\
+             <text class=\"tick\" x=\"{}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>\
+             <text class=\"tick\" x=\"{cx:.1}\" y=\"{}\" text-anchor=\"middle\">{x:.2}</text>",
+            W - RIGHT,
+            LEFT - 6.0,
+            cy + 4.0,
+            escape(&format_metric(metric, y)),
+            H - BOTTOM + 16.0,
+        );
+    }
+    let _ = write!(
+        svg,
+        "<text class=\"axis\" x=\"{}\" y=\"{}\" text-anchor=\"middle\">bits per pixel</text>",
+        (LEFT + W - RIGHT) / 2.0,
+        H - 8.0
+    );
+    for (i, s) in series.iter().enumerate() {
+        let line: Vec<String> = s
+            .points
+            .iter()
+            .map(|&(x, y, _)| format!("{:.1},{:.1}", px(x), py(y)))
+            .collect();
+        let _ = write!(
+            svg,
+            "<polyline fill=\"none\" stroke=\"{}\" stroke-width=\"2\" points=\"{}\"/>",
+            s.color,
+            line.join(" ")
+        );
+        for &(x, y, quality) in &s.points {
+            let tip = format!(
+                "{} q{quality}: {x:.4} bpp, {metric} {}",
+                s.codec,
+                format_metric(metric, y)
+            );
+            let _ = write!(
+                svg,
+                "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"4\" fill=\"{}\" data-tip=\"{}\"><title>{}</title></circle>",
+                px(x),
+                py(y),
+                s.color,
+                escape(&tip),
+                escape(&tip)
+            );
+        }
+        let _ = write!(
+            svg,
+            "<rect x=\"{}\" y=\"{}\" width=\"10\" height=\"10\" fill=\"{}\"/>\
+             <text class=\"legend\" x=\"{}\" y=\"{}\">{}</text>",
+            LEFT + 10.0,
+            TOP + 4.0 + 16.0 * i as f64,
+            s.color,
+            LEFT + 26.0,
+            TOP + 13.0 + 16.0 * i as f64,
+            escape(&s.codec)
+        );
+    }
+    svg.push_str("</svg></figure>");
+    svg
+}
+
+/// Column header with the metric's direction, when known.
+fn metric_header(metric: &str) -> String {
+    match builtin_direction(metric) {
+        Some(QualityDirection::HigherIsBetter) => format!("{} ↑", escape(metric)),
+        Some(QualityDirection::LowerIsBetter) => format!("{} ↓", escape(metric)),
+        None => escape(metric),
+    }
+}
+
+fn format_metric(metric: &str, value: f64) -> String {
+    match metric {
+        "dssim" => format!("{value:.6}"),
+        "butteraugli" | "alpha_rmse" => format!("{value:.3}"),
+        "ssimulacra2" | "psnr" => format!("{value:.2}"),
+        _ => format!("{value:.4}"),
+    }
+}
+
+fn metric_cell(metric: &str, value: Option<f64>) -> String {
+    match value {
+        Some(v) => format!("<td data-v=\"{v}\">{}</td>", format_metric(metric, v)),
+        None => "<td data-v=\"\">-</td>".to_string(),
+    }
+}
+
+fn number_cell(value: Option<f64>, decimals: usize) -> String {
+    match value {
+        Some(v) => format!("<td data-v=\"{v}\">{v:.decimals$}</td>"),
+        None => "<td data-v=\"\">-</td>".to_string(),
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Encode an image as a PNG `data:` URI, in sRGB with alpha if present.
+fn png_data_uri(image: &ImageData) -> Result<String> {
+    let rgb = image.to_rgb8_srgb()?;
+    let (pixels, color) = match image.alpha_vec() {
+        Some(alpha) => (
+            rgb.chunks_exact(3)
+                .zip(alpha)
+                .flat_map(|(p, a)| [p[0], p[1], p[2], a])
+                .collect(),
+            png::ColorType::Rgba,
+        ),
+        None => (rgb, png::ColorType::Rgb),
+    };
+    let to_err = |e: png::EncodingError| Error::Report(format!("failed to encode PNG: {e}"));
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, image.width() as u32, image.height() as u32);
+    encoder.set_color(color);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(to_err)?;
+    writer.write_image_data(&pixels).map_err(to_err)?;
+    writer.finish().map_err(to_err)?;
+    Ok(format!("data:image/png;base64,{}", base64(&bytes)))
+}
+
+/// Standard base64 with padding.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+const STYLE: &str = r#"
+:root { color-scheme: light dark; --fg: #222; --bg: #fff; --muted: #666; --line: #ddd; --hi: #eef4ff; }
+@media (prefers-color-scheme: dark) { :root { --fg: #ddd; --bg: #1b1b1b; --muted: #999; --line: #444; --hi: #23304a; } }
+body { font: 14px/1.4 system-ui, sans-serif; color: var(--fg); background: var(--bg); margin: 0 auto; max-width: 1280px; padding: 0 16px 48px; }
+h1 { margin-bottom: 4px; } h2 { border-bottom: 1px solid var(--line); padding-bottom: 4px; margin-top: 32px; }
+.config, header p { color: var(--muted); } .bad { color: #d62728; }
+table { border-collapse: collapse; width: 100%; font-variant-numeric: tabular-nums; }
+th, td { padding: 4px 8px; border-bottom: 1px solid var(--line); text-align: right; white-space: nowrap; }
+th:first-child, td:first-child, th:nth-child(2), td:nth-child(2) { text-align: left; }
+table.sortable th { cursor: pointer; user-select: none; }
+th.asc::after { content: " ▲"; } th.desc::after { content: " ▼"; }
+tr.has-image { cursor: pointer; } tr.selected td { background: var(--hi); }
+.charts { display: flex; flex-wrap: wrap; gap: 16px; } .chart { margin: 0; flex: 1 1 520px; max-width: 640px; }
+.chart svg { width: 100%; height: auto; } .chart text { fill: var(--fg); font-size: 11px; }
+.chart .title { font-size: 14px; text-anchor: middle; } .chart .grid { stroke: var(--line); }
+.chart circle:hover { r: 6; stroke: var(--fg); }
+#tip { position: fixed; pointer-events: none; background: var(--bg); border: 1px solid var(--line); padding: 4px 8px; border-radius: 4px; display: none; box-shadow: 0 2px 6px rgba(0,0,0,.2); }
+.controls { margin: 8px 0; display: flex; gap: 12px; align-items: center; flex-wrap: wrap; }
+.viewer { position: relative; display: inline-block; max-width: 100%; border: 1px solid var(--line); image-rendering: pixelated; }
+.viewer img { display: block; max-width: 100%; } .viewer img.top { position: absolute; top: 0; left: 0; width: 100%; height: 100%; }
+.viewer .divider { position: absolute; top: 0; bottom: 0; width: 2px; background: #fff; mix-blend-mode: difference; pointer-events: none; }
+.label { color: var(--muted); }
+"#;
+
+const SCRIPT: &str = r#"
+(function () {
+  const data = JSON.parse(document.getElementById('report-data').textContent);
+  const tip = document.getElementById('tip');
+
+  function sortable(table) {
+    table.querySelectorAll('th').forEach((th, col) => {
+      th.addEventListener('click', () => {
+        const asc = !th.classList.contains('asc');
+        table.querySelectorAll('th').forEach(h => h.classList.remove('asc', 'desc'));
+        th.classList.add(asc ? 'asc' : 'desc');
+        const body = table.tBodies[0];
+        const key = row => {
+          const cell = row.cells[col];
+          const v = cell.dataset.v;
+          return v === undefined ? cell.textContent : (v === '' ? NaN : parseFloat(v));
+        };
+        const rows = Array.from(body.rows);
+        rows.sort((a, b) => {
+          const x = key(a), y = key(b);
+          if (typeof x === 'string') return asc ? x.localeCompare(y) : y.localeCompare(x);
+          if (isNaN(x)) return 1; if (isNaN(y)) return -1;
+          return asc ? x - y : y - x;
+        });
+        rows.forEach(r => body.appendChild(r));
+      });
+    });
+  }
+  document.querySelectorAll('table.sortable').forEach(sortable);
+
+  document.addEventListener('mousemove', e => {
+    const t = e.target.dataset && e.target.dataset.tip;
+    if (t) {
+      tip.textContent = t;
+      tip.style.display = 'block';
+      tip.style.left = (e.clientX + 12) + 'px';
+      tip.style.top = (e.clientY + 12) + 'px';
+    } else {
+      tip.style.display = 'none';
+    }
+  });
+
+  function fmt(metric, v) {
+    if (v === null || v === undefined) return '-';
+    if (metric === 'dssim') return v.toFixed(6);
+    if (metric === 'butteraugli' || metric === 'alpha_rmse') return v.toFixed(3);
+    if (metric === 'ssimulacra2' || metric === 'psnr') return v.toFixed(2);
+    return v.toFixed(4);
+  }
+
+  function el(tag, attrs, children) {
+    const e = document.createElement(tag);
+    Object.entries(attrs || {}).forEach(([k, v]) => {
+      if (k === 'text') e.textContent = v; else e.setAttribute(k, v);
+    });
+    (children || []).forEach(c => e.appendChild(c));
+    return e;
+  }
+
+  const select = document.getElementById('image-select');
+  const inspector = document.getElementById('inspector');
+  const results = document.getElementById('image-results');
+  data.images.forEach((img, i) => {
+    const label = img.name + (img.category ? ' (' + img.category + ')' : '');
+    select.appendChild(el('option', { value: i, text: label }));
+  });
+
+  function layers(img, r) {
+    const out = [];
+    if (r && r.image) out.push(['decoded', r.image]);
+    if (r) Object.entries(r.heatmaps).forEach(([m, uri]) => out.push([m + ' heatmap', uri]));
+    return out;
+  }
+
+  function showInspector(img, r) {
+    inspector.innerHTML = '';
+    const available = layers(img, r);
+    if (!r || available.length === 0) {
+      inspector.appendChild(el('p', { class: 'label', text: r
+        ? 'No images embedded for this result.'
+        : 'Select a result to inspect it.' }));
+      return;
+    }
+    if (!img.original) {
+      inspector.appendChild(el('p', { class: 'label', text: 'Original not embedded.' }));
+    }
+    const base = img.original || available[0][1];
+    const controls = el('div', { class: 'controls' });
+    const layer = el('select');
+    available.forEach(([name, uri], i) => layer.appendChild(el('option', { value: i, text: name })));
+    const mode = el('select');
+    ['slide', 'flip'].forEach(m => mode.appendChild(el('option', { value: m, text: m })));
+    const slider = el('input', { type: 'range', min: 0, max: 100, value: 50 });
+    const state = el('span', { class: 'label' });
+    controls.append('Layer ', layer, ' Mode ', mode, slider, state);
+    const viewer = el('div', { class: 'viewer' });
+    const bottom = el('img', { src: base, alt: 'original' });
+    const top = el('img', { class: 'top', alt: 'variant' });
+    const divider = el('div', { class: 'divider' });
+    viewer.append(bottom, top, divider);
+    let flipped = false;
+    function update() {
+      top.src = available[layer.value][1];
+      const slide = mode.value === 'slide';
+      slider.style.display = slide ? '' : 'none';
+      divider.style.display = slide ? '' : 'none';
+      if (slide) {
+        top.style.visibility = 'visible';
+        top.style.clipPath = 'inset(0 0 0 ' + slider.value + '%)';
+        divider.style.left = slider.value + '%';
+        state.textContent = 'left: original, right: ' + available[layer.value][0];
+      } else {
+        top.style.clipPath = 'none';
+        top.style.visibility = flipped ? 'visible' : 'hidden';
+        state.textContent = (flipped ? available[layer.value][0] : 'original') +
+          ' (click or press space to flip)';
+      }
+    }
+    layer.onchange = mode.onchange = slider.oninput = update;
+    viewer.addEventListener('click', () => { flipped = !flipped; update(); });
+    document.onkeydown = e => {
+      if (e.code === 'Space' && mode.value === 'flip' && e.target === document.body) {
+        e.preventDefault(); flipped = !flipped; update();
+      }
+    };
+    inspector.append(controls, viewer);
+    update();
+  }
+
+  function showImage(i) {
+    const img = data.images[i];
+    results.innerHTML = '';
+    const head = el('tr', {}, ['Codec', 'Version', 'Quality', 'Params', 'Bytes', 'bpp', 'Encode ms']
+      .concat(data.metrics).map(h => el('th', { text: h })));
+    const body = el('tbody');
+    img.results.forEach(r => {
+      const num = (v, text) => el('td', { 'data-v': v, text: text });
+      const row = el('tr', {}, [
+        el('td', { text: r.codec }), el('td', { text: r.version }), num(r.quality, r.quality),
+        el('td', { text: r.params }), num(r.bytes, r.bytes), num(r.bpp, r.bpp.toFixed(4)),
+        num(r.encode_ms, r.encode_ms.toFixed(1)),
+      ].concat(r.scores.map((s, k) => num(s === null ? '' : s, fmt(data.metrics[k], s)))));
+      if (r.image || Object.keys(r.heatmaps).length) {
+        row.classList.add('has-image');
+        row.addEventListener('click', () => {
+          body.querySelectorAll('tr').forEach(t => t.classList.remove('selected'));
+          row.classList.add('selected');
+          showInspector(img, r);
+        });
+      }
+      body.appendChild(row);
+    });
+    const table = el('table', { class: 'sortable' }, [el('thead', {}, [head]), body]);
+    results.appendChild(el('p', { class: 'label',
+      text: img.width + '×' + img.height + ' · ' + img.results.length + ' results' }));
+    results.appendChild(table);
+    img.failures.forEach(f => results.appendChild(el('p', { class: 'bad', text: f })));
+    sortable(table);
+    const first = img.results.find(r => r.image || Object.keys(r.heatmaps).length);
+    if (first) body.rows[img.results.indexOf(first)].click(); else showInspector(img, null);
+  }
+  select.onchange = () => showImage(select.value);
+  if (data.images.length) showImage(0);
+})();
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::report::FailureRecord;
+    use crate::metrics::MetricResult;
+    use imgref::ImgVec;
+    use rgb::RGB8;
+    use std::time::Duration;
+
+    fn result(codec: &str, quality: f64, bpp: f64, ssim2: f64) -> CodecResult {
+        CodecResult {
+            codec_id: codec.to_string(),
+            codec_version: "1.0".to_string(),
+            quality,
+            file_size: (bpp * 8.0) as usize,
+            bits_per_pixel: bpp,
+            encode_time: Duration::from_millis(5),
+            decode_time: None,
+            encode_timing: None,
+            decode_timing: None,
+            encode_peak_bytes: None,
+            decode_peak_bytes: None,
+            metrics: MetricResult {
+                ssimulacra2: Some(ssim2),
+                ..MetricResult::default()
+            },
+            perception: None,
+            cached_path: None,
+            heatmaps: BTreeMap::new(),
+            search: None,
+            generations: Vec::new(),
+            codec_params: HashMap::new(),
+        }
+    }
+
+    fn report() -> CorpusReport {
+        let mut report = CorpusReport::new("nightly".to_string());
+        for name in ["sky.png", "</script><b>x</b>.png"] {
+            let mut img = ImageReport::new(name.to_string(), 4, 4);
+            for (q, bpp, score) in [(50.0, 0.5, 60.0), (90.0, 2.0, 85.0)] {
+                img.results.push(result("jpegli", q, bpp, score));
+                img.results.push(result("webp", q, bpp * 1.1, score - 2.0));
+            }
+            report.images.push(img);
+        }
+        report
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn test_render_is_self_contained() {
+        let report = report();
+        let html = HtmlReport::new(&report)
+            .title("Nightly <run>")
+            .category("sky.png", ImageCategory::Photo)
+            .images(|img, r| {
+                (img.name == "sky.png").then(|| {
+                    let shade = r.map_or(128, |r| r.quality as u8);
+                    ImageData::Rgb8(ImgVec::new(vec![RGB8::new(shade, shade, shade); 16], 4, 4))
+                })
+            })
+            .render()
+            .unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Nightly &lt;run&gt;</title>"));
+        // No external assets.
+        assert!(!html.contains("src=\"http"));
+        assert!(!html.contains("href=\""));
+        // The JSON payload cannot close its script element early.
+        assert_eq!(html.matches("</script>").count(), 2);
+        // Charts, tables and categories.
+        assert!(html.contains("ssimulacra2 ↑"));
+        assert!(html.contains("data-tip=\"jpegli q90: 2.0000 bpp, ssimulacra2 85.00\""));
+        assert!(html.contains("<h2>Categories</h2>"));
+        assert!(html.contains("<td>photo</td><td>jpegli</td>"));
+        // Original plus four variants of sky.png are embedded.
+        assert_eq!(html.matches("data:image/png;base64,").count(), 5);
+    }
+
+    #[test]
+    fn test_write_without_images() {
+        let mut report = report();
+        let error = Error::Codec {
+            codec: "webp".to_string(),
+            message: "boom".to_string(),
+        };
+        let failures = &mut report.images[0].failures;
+        failures.push(FailureRecord::new("webp", "1.0", Some(95.0), &error));
+        failures.push(FailureRecord::new("avif", "1.0", None, &error));
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out").join("report.html");
+        HtmlReport::new(&report)
+            .metrics(vec!["ssimulacra2".to_string(), "psnr".to_string()])
+            .write(&path)
+            .unwrap();
+        let html = std::fs::read_to_string(&path).unwrap();
+        assert!(!html.contains("data:image/png"));
+        assert!(!html.contains("<h2>Categories</h2>"));
+        assert!(html.contains("psnr ↑"));
+        assert!(html.contains("\"webp q95: Codec error (webp): boom\""));
+        assert!(html.contains("\"avif: Codec error (webp): boom\""));
+    }
+}
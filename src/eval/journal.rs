@@ -0,0 +1,295 @@
+//! Append-only results journal for resumable runs.
+//!
+//! With `EvalConfig::journal` enabled, every [`CodecResult`] is appended to
+//! `<report_dir>/journal.jsonl` as soon as it is produced, one JSON object per
+//! line. A later session with the same configuration reuses every journaled
+//! (image, codec, version, quality, params) job instead of running it again,
+//! and skips loading images whose jobs are all complete.
+//!
+//! The first line records a fingerprint of the metric and viewing settings;
+//! a journal written with different settings is rejected rather than mixed
+//! into the new results. Failed jobs are not journaled, so they are retried.
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::eval::report::CodecResult;
+
+/// File name of the journal inside the report directory.
+pub(crate) const JOURNAL_FILE: &str = "journal.jsonl";
+
+/// One line of the journal.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Entry {
+    /// Settings the journal was written with.
+    Config { fingerprint: String },
+    /// An image was evaluated.
+    Image {
+        name: String,
+        width: u32,
+        height: u32,
+    },
+    /// A job finished.
+    Result {
+        image: String,
+        job: String,
+        result: Box<CodecResult>,
+    },
+}
+
+/// Journaled state of one image.
+#[derive(Debug, Default)]
+struct ImageEntry {
+    dimensions: Option<(u32, u32)>,
+    results: HashMap<String, CodecResult>,
+}
+
+/// An open journal: what earlier runs completed, plus the append handle.
+#[derive(Debug)]
+pub(crate) struct Journal {
+    images: Mutex<HashMap<String, ImageEntry>>,
+    file: Mutex<File>,
+}
+
+impl Journal {
+    /// Open (or create) the journal at `path`.
+    ///
+    /// A torn final line from an interrupted run is ignored and cut off, but
+    /// only once the journal's settings fingerprint has been accepted: an
+    /// incompatible journal is left untouched on disk.
+    pub(crate) fn open(path: &Path, fingerprint: &str) -> Result<Self> {
+        let mut images: HashMap<String, ImageEntry> = HashMap::new();
+        let mut existing_fingerprint = None;
+
+        // Length of the intact prefix; anything after it is a torn write.
+        let mut valid_len = 0;
+        // Length of the existing file and whether its intact prefix lacks a
+        // final newline.
+        let mut existing = None;
+        if path.exists() {
+            let text = std::fs::read_to_string(path)?;
+            let line_count = text.split_inclusive('\n').count();
+            for (i, line) in text.split_inclusive('\n').enumerate() {
+                if line.trim().is_empty() {
+                    valid_len += line.len();
+                    continue;
+                }
+                let entry = match serde_json::from_str::<Entry>(line) {
+                    Ok(entry) => entry,
+                    Err(_) if i + 1 == line_count => break,
+                    Err(e) => {
+                        return Err(Error::Report(format!(
+                            "corrupt journal {} at line {}: {e}",
+                            path.display(),
+                            i + 1
+                        )));
+                    }
+                };
+                valid_len += line.len();
+                match entry {
+                    Entry::Config { fingerprint } => existing_fingerprint = Some(fingerprint),
+                    Entry::Image {
+                        name,
+                        width,
+                        height,
+                    } => images.entry(name).or_default().dimensions = Some((width, height)),
+                    Entry::Result { image, job, result } => {
+                        images
+                            .entry(image)
+                            .or_default()
+                            .results
+                            .insert(job, *result);
+                    }
+                }
+            }
+            let prefix = &text[..valid_len];
+            existing = Some((text.len(), !prefix.is_empty() && !prefix.ends_with('\n')));
+        }
+
+        if let Some(existing) = &existing_fingerprint
+            && existing != fingerprint
+        {
+            return Err(Error::Report(format!(
+                "journal {} was written with different evaluation settings; \
+                 remove it or use another report_dir",
+                path.display()
+            )));
+        }
+
+        if let Some((len, missing_newline)) = existing {
+            if valid_len < len {
+                OpenOptions::new()
+                    .write(true)
+                    .open(path)?
+                    .set_len(valid_len as u64)?;
+            }
+            if missing_newline {
+                OpenOptions::new()
+                    .append(true)
+                    .open(path)?
+                    .write_all(b"\n")?;
+            }
+        }
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let journal = Self {
+            images: Mutex::new(images),
+            file: Mutex::new(file),
+        };
+        if existing_fingerprint.is_none() {
+            journal.append(&Entry::Config {
+                fingerprint: fingerprint.to_string(),
+            })?;
+        }
+        Ok(journal)
+    }
+
+    /// Dimensions of a journaled image.
+    pub(crate) fn dimensions(&self, image: &str) -> Option<(u32, u32)> {
+        self.lock_images().get(image)?.dimensions
+    }
+
+    /// A journaled result for `job` of `image`.
+    pub(crate) fn result(&self, image: &str, job: &str) -> Option<CodecResult> {
+        self.lock_images().get(image)?.results.get(job).cloned()
+    }
+
+    /// Record that `image` is being evaluated (once per image).
+    pub(crate) fn record_image(&self, image: &str, width: u32, height: u32) -> Result<()> {
+        {
+            let mut images = self.lock_images();
+            let entry = images.entry(image.to_string()).or_default();
+            if entry.dimensions.is_some() {
+                return Ok(());
+            }
+            entry.dimensions = Some((width, height));
+        }
+        self.append(&Entry::Image {
+            name: image.to_string(),
+            width,
+            height,
+        })
+    }
+
+    /// Append a finished job.
+    pub(crate) fn record_result(&self, image: &str, job: &str, result: &CodecResult) -> Result<()> {
+        self.append(&Entry::Result {
+            image: image.to_string(),
+            job: job.to_string(),
+            result: Box::new(result.clone()),
+        })?;
+        self.lock_images()
+            .entry(image.to_string())
+            .or_default()
+            .results
+            .insert(job.to_string(), result.clone());
+        Ok(())
+    }
+
+    /// Write one entry as a single line and flush it to disk.
+    fn append(&self, entry: &Entry) -> Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        file.write_all(&line)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    fn lock_images(&self) -> std::sync::MutexGuard<'_, HashMap<String, ImageEntry>> {
+        self.images.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::MetricResult;
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    fn result(quality: f64) -> CodecResult {
+        CodecResult {
+            codec_id: "c".to_string(),
+            codec_version: "1".to_string(),
+            quality,
+            file_size: 100,
+            bits_per_pixel: 1.0,
+            encode_time: Duration::from_millis(5),
+            decode_time: None,
+            encode_timing: None,
+            decode_timing: None,
+            encode_peak_bytes: None,
+            decode_peak_bytes: None,
+            metrics: MetricResult::default(),
+            perception: None,
+            cached_path: None,
+            heatmaps: BTreeMap::new(),
+            search: None,
+            generations: Vec::new(),
+            codec_params: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_journal_roundtrip_and_torn_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(JOURNAL_FILE);
+        {
+            let journal = Journal::open(&path, "fp").unwrap();
+            journal.record_image("img", 8, 4).unwrap();
+            journal.record_image("img", 8, 4).unwrap();
+            journal
+                .record_result("img", "job-a", &result(50.0))
+                .unwrap();
+        }
+        // Simulate a crash in the middle of writing a line.
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"type":"result","image":"img","#)
+            .unwrap();
+        drop(file);
+
+        let journal = Journal::open(&path, "fp").unwrap();
+        assert_eq!(journal.dimensions("img"), Some((8, 4)));
+        assert_eq!(journal.result("img", "job-a").unwrap().quality, 50.0);
+        assert!(journal.result("img", "job-b").is_none());
+        journal
+            .record_result("img", "job-b", &result(60.0))
+            .unwrap();
+        drop(journal);
+
+        // The torn line was dropped, so later appends stay readable.
+        let journal = Journal::open(&path, "fp").unwrap();
+        assert_eq!(journal.result("img", "job-b").unwrap().quality, 60.0);
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text.matches(r#""type":"image""#).count(), 1);
+        assert_eq!(text.matches(r#""type":"config""#).count(), 1);
+    }
+
+    #[test]
+    fn test_journal_rejects_other_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(JOURNAL_FILE);
+        Journal::open(&path, "fp").unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"type":"result","image":"img","#)
+            .unwrap();
+        drop(file);
+        let before = std::fs::read(&path).unwrap();
+
+        let err = Journal::open(&path, "other").unwrap_err();
+        assert!(matches!(err, Error::Report(_)), "{err}");
+        // A rejected journal, torn line included, is left as it was.
+        assert_eq!(std::fs::read(&path).unwrap(), before);
+    }
+}
@@ -0,0 +1,141 @@
+//! Peak heap measurement for encode and decode callbacks.
+//!
+//! Rust has no portable way to ask how much memory a function call used, so
+//! measurement needs the binary to route allocations through
+//! [`TrackingAllocator`]:
+//!
+//! ```rust,ignore
+//! #[global_allocator]
+//! static ALLOC: codec_eval::TrackingAllocator = codec_eval::TrackingAllocator::new();
+//! ```
+//!
+//! With `EvalConfig::measure_memory` enabled, the session then records the
+//! peak number of live heap bytes above the starting level during each encode
+//! and decode. The counters are process-wide, so jobs run one at a time while
+//! measuring; threads spawned by the codec itself are included, which is what
+//! an image server sees.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Live heap bytes allocated through the tracker.
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+/// Highest value of `CURRENT` since the last reset.
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+/// Set on the first allocation through the tracker.
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Global allocator that counts live and peak heap bytes, delegating the
+/// allocation itself to [`System`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TrackingAllocator;
+
+impl TrackingAllocator {
+    /// Create the allocator, for use in a `#[global_allocator]` static.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
+    }
+
+    /// Whether this process allocates through a `TrackingAllocator`.
+    #[must_use]
+    pub fn is_installed() -> bool {
+        INSTALLED.load(Ordering::Relaxed)
+    }
+
+    /// Heap bytes currently allocated through the tracker.
+    #[must_use]
+    pub fn current_bytes() -> usize {
+        CURRENT.load(Ordering::Relaxed)
+    }
+}
+
+fn grow(bytes: usize) {
+    if !INSTALLED.load(Ordering::Relaxed) {
+        INSTALLED.store(true, Ordering::Relaxed);
+    }
+    let now = CURRENT.fetch_add(bytes, Ordering::Relaxed) + bytes;
+    PEAK.fetch_max(now, Ordering::Relaxed);
+}
+
+fn shrink(bytes: usize) {
+    CURRENT.fetch_sub(bytes, Ordering::Relaxed);
+}
+
+// SAFETY: every method forwards to `System` with the caller's arguments
+// unchanged; the tracker only updates counters.
+#[allow(unsafe_code)]
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // SAFETY: forwarded from the caller.
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        // SAFETY: forwarded from the caller.
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: forwarded from the caller.
+        unsafe { System.dealloc(ptr, layout) };
+        shrink(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // SAFETY: forwarded from the caller.
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            if new_size > layout.size() {
+                grow(new_size - layout.size());
+            } else {
+                shrink(layout.size() - new_size);
+            }
+        }
+        new_ptr
+    }
+}
+
+/// Run `f` and return its output with the peak heap growth during the call,
+/// in bytes. Assumes nothing else allocates concurrently.
+pub(crate) fn measure_peak<T>(f: impl FnOnce() -> T) -> (T, u64) {
+    let baseline = CURRENT.load(Ordering::SeqCst);
+    PEAK.store(baseline, Ordering::SeqCst);
+    let output = f();
+    let peak = PEAK.load(Ordering::SeqCst);
+    (output, peak.saturating_sub(baseline) as u64)
+}
+
+/// Serializes tests that measure peaks, since the counters are global.
+#[cfg(test)]
+pub(crate) static MEASURE_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[global_allocator]
+    static ALLOC: TrackingAllocator = TrackingAllocator::new();
+
+    #[test]
+    fn test_measure_peak() {
+        let _lock = MEASURE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        assert!(TrackingAllocator::is_installed());
+
+        let (len, peak) = measure_peak(|| {
+            let mut buf = vec![0u8; 1 << 20];
+            buf.resize(2 << 20, 1);
+            std::hint::black_box(&buf).len()
+        });
+        assert_eq!(len, 2 << 20);
+        assert!(peak >= 2 << 20, "peak {peak}");
+    }
+}
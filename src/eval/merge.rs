@@ -0,0 +1,425 @@
+//! Loading, merging and filtering corpus reports.
+//!
+//! Distributed runs write one [`CorpusReport`] per machine or shard.
+//! [`CorpusReport::load`] reads them back, [`CorpusReport::merge`] stitches
+//! them into one report and [`CorpusReport::filter`] narrows a report to some
+//! codecs, images or quality settings before Pareto or BD-rate analysis.
+//!
+//! Results are identified by image, codec, codec version, quality setting and
+//! encoder parameters. The same result in two reports is kept once if the
+//! file size and every metric agree; timings and cache paths may differ
+//! between machines. Any other disagreement is a conflict and fails the
+//! merge.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::report::{CodecResult, CorpusReport, FailureRecord, ImageReport};
+use super::session::format_params;
+use crate::error::{Error, Result};
+use crate::metrics::custom::BUILTIN_METRICS;
+
+/// Selects codecs, images and quality settings in [`CorpusReport::filter`].
+///
+/// Empty lists and unset bounds match everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReportFilter {
+    /// Codec ids to keep.
+    pub codecs: Vec<String>,
+    /// Image names to keep.
+    pub images: Vec<String>,
+    /// Lowest quality setting to keep (inclusive).
+    pub min_quality: Option<f64>,
+    /// Highest quality setting to keep (inclusive).
+    pub max_quality: Option<f64>,
+}
+
+impl ReportFilter {
+    /// Create a filter that keeps everything.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also keep results of `codec_id`.
+    #[must_use]
+    pub fn codec(mut self, codec_id: impl Into<String>) -> Self {
+        self.codecs.push(codec_id.into());
+        self
+    }
+
+    /// Also keep the image named `name`.
+    #[must_use]
+    pub fn image(mut self, name: impl Into<String>) -> Self {
+        self.images.push(name.into());
+        self
+    }
+
+    /// Keep only quality settings in `min..=max`.
+    #[must_use]
+    pub fn quality_range(mut self, min: f64, max: f64) -> Self {
+        self.min_quality = Some(min);
+        self.max_quality = Some(max);
+        self
+    }
+
+    /// Whether results of `codec_id` are kept.
+    #[must_use]
+    pub fn matches_codec(&self, codec_id: &str) -> bool {
+        self.codecs.is_empty() || self.codecs.iter().any(|c| c == codec_id)
+    }
+
+    /// Whether the image named `name` is kept.
+    #[must_use]
+    pub fn matches_image(&self, name: &str) -> bool {
+        self.images.is_empty() || self.images.iter().any(|i| i == name)
+    }
+
+    /// Whether the quality setting is kept. Jobs without a fixed quality
+    /// (target-mode failures) are kept only when no bound is set.
+    #[must_use]
+    pub fn matches_quality(&self, quality: Option<f64>) -> bool {
+        if self.min_quality.is_none() && self.max_quality.is_none() {
+            return true;
+        }
+        quality.is_some_and(|q| {
+            self.min_quality.is_none_or(|min| q >= min)
+                && self.max_quality.is_none_or(|max| q <= max)
+        })
+    }
+
+    fn matches_result(&self, r: &CodecResult) -> bool {
+        self.matches_codec(&r.codec_id) && self.matches_quality(Some(r.quality))
+    }
+
+    fn matches_failure(&self, f: &FailureRecord) -> bool {
+        self.matches_codec(&f.codec_id) && self.matches_quality(f.quality)
+    }
+}
+
+impl CorpusReport {
+    /// Load a corpus report written as JSON (e.g. by
+    /// [`EvalSession::write_corpus_report`](super::EvalSession::write_corpus_report)).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Report`] if the file cannot be read or is not a
+    /// corpus report.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| Error::Report(format!("failed to read {}: {e}", path.display())))?;
+        serde_json::from_str(&json)
+            .map_err(|e| Error::Report(format!("failed to parse {}: {e}", path.display())))
+    }
+
+    /// Merge reports from several machines or shards into one.
+    ///
+    /// Images with the same name are combined, keeping the order in which
+    /// images and results first appear. The merged report takes the first
+    /// report's name, the latest timestamp and every distinct config summary.
+    /// A failure is dropped if another report has a result for the same job.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Report`] if `reports` is empty, if an image has
+    /// different dimensions in two reports, or if two reports hold different
+    /// results for the same job.
+    pub fn merge<'a>(reports: impl IntoIterator<Item = &'a CorpusReport>) -> Result<Self> {
+        let mut reports = reports.into_iter().peekable();
+        let first = reports
+            .peek()
+            .ok_or_else(|| Error::Report("no reports to merge".to_string()))?;
+        let mut merged = CorpusReport::new(first.name.clone());
+        merged.timestamp = first.timestamp;
+
+        let mut summaries: Vec<&str> = Vec::new();
+        let mut image_index: HashMap<String, usize> = HashMap::new();
+        let mut result_index: HashMap<(usize, JobKey), usize> = HashMap::new();
+
+        for report in reports {
+            merged.timestamp = merged.timestamp.max(report.timestamp);
+            if !report.config_summary.is_empty()
+                && !summaries.contains(&report.config_summary.as_str())
+            {
+                summaries.push(&report.config_summary);
+            }
+
+            for img in &report.images {
+                let idx = *image_index.entry(img.name.clone()).or_insert_with(|| {
+                    let mut empty = img.clone();
+                    empty.results.clear();
+                    empty.failures.clear();
+                    merged.images.push(empty);
+                    merged.images.len() - 1
+                });
+                let target = &mut merged.images[idx];
+                if (target.width, target.height) != (img.width, img.height) {
+                    return Err(Error::Report(format!(
+                        "conflicting dimensions for {}: {}x{} vs {}x{}",
+                        img.name, target.width, target.height, img.width, img.height
+                    )));
+                }
+                if target.source_path.is_none() {
+                    target.source_path.clone_from(&img.source_path);
+                }
+                target.timestamp = target.timestamp.max(img.timestamp);
+
+                for r in &img.results {
+                    let key = (idx, JobKey::of_result(r));
+                    if let Some(&existing) = result_index.get(&key) {
+                        let existing = &target.results[existing];
+                        if let Some(difference) = first_difference(existing, r) {
+                            let params = if key.1.params.is_empty() {
+                                String::new()
+                            } else {
+                                format!(" [{}]", format_params(&key.1.params))
+                            };
+                            return Err(Error::Report(format!(
+                                "conflicting results for {} {}@{} q{}{params}: {difference}",
+                                img.name, r.codec_id, r.codec_version, r.quality,
+                            )));
+                        }
+                    } else {
+                        result_index.insert(key, target.results.len());
+                        target.results.push(r.clone());
+                    }
+                }
+                for f in &img.failures {
+                    if !target.failures.contains(f) {
+                        target.failures.push(f.clone());
+                    }
+                }
+            }
+        }
+
+        // Jobs that failed on one machine but succeeded on another.
+        for (idx, img) in merged.images.iter_mut().enumerate() {
+            img.failures.retain(|f| {
+                f.quality
+                    .is_none_or(|_| !result_index.contains_key(&(idx, JobKey::of_failure(f))))
+            });
+        }
+
+        merged.config_summary = summaries.join(" | ");
+        Ok(merged)
+    }
+
+    /// Copy of this report with only the codecs, images and quality settings
+    /// selected by `filter`. Failures are filtered the same way.
+    #[must_use]
+    pub fn filter(&self, filter: &ReportFilter) -> Self {
+        let images = self
+            .images
+            .iter()
+            .filter(|img| filter.matches_image(&img.name))
+            .map(|img| ImageReport {
+                results: img
+                    .results
+                    .iter()
+                    .filter(|r| filter.matches_result(r))
+                    .cloned()
+                    .collect(),
+                failures: img
+                    .failures
+                    .iter()
+                    .filter(|f| filter.matches_failure(f))
+                    .cloned()
+                    .collect(),
+                ..img.clone()
+            })
+            .collect();
+        Self {
+            name: self.name.clone(),
+            images,
+            timestamp: self.timestamp,
+            config_summary: self.config_summary.clone(),
+        }
+    }
+}
+
+/// Identity of a job within one image.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct JobKey {
+    codec_id: String,
+    codec_version: String,
+    quality: u64,
+    params: BTreeMap<String, String>,
+}
+
+impl JobKey {
+    fn of_result(r: &CodecResult) -> Self {
+        Self {
+            codec_id: r.codec_id.clone(),
+            codec_version: r.codec_version.clone(),
+            quality: r.quality.to_bits(),
+            params: r
+                .codec_params
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        }
+    }
+
+    fn of_failure(f: &FailureRecord) -> Self {
+        Self {
+            codec_id: f.codec_id.clone(),
+            codec_version: f.codec_version.clone(),
+            quality: f.quality.unwrap_or_default().to_bits(),
+            params: f.params.clone(),
+        }
+    }
+}
+
+/// The first field on which two results of the same job disagree, ignoring
+/// timing and where their bitstreams were cached.
+fn first_difference(a: &CodecResult, b: &CodecResult) -> Option<String> {
+    fn show(value: Option<f64>) -> String {
+        value.map_or_else(|| "none".to_string(), |v| v.to_string())
+    }
+
+    if a.file_size != b.file_size {
+        return Some(format!("file_size {} vs {}", a.file_size, b.file_size));
+    }
+    if a.bits_per_pixel.to_bits() != b.bits_per_pixel.to_bits() {
+        return Some(format!(
+            "bits_per_pixel {} vs {}",
+            a.bits_per_pixel, b.bits_per_pixel
+        ));
+    }
+    let custom = a.metrics.custom.keys().chain(b.metrics.custom.keys());
+    let names = BUILTIN_METRICS.iter().map(|&(name, _)| name);
+    for name in names.chain(custom.map(String::as_str)) {
+        let (x, y) = (a.metrics.value(name), b.metrics.value(name));
+        if x.map(f64::to_bits) != y.map(f64::to_bits) {
+            return Some(format!("{name} {} vs {}", show(x), show(y)));
+        }
+    }
+    if serde_json::to_value(&a.metrics.tiles).ok() != serde_json::to_value(&b.metrics.tiles).ok() {
+        return Some("tile scores".to_string());
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::MetricResult;
+    use std::time::Duration;
+
+    fn result(codec: &str, quality: f64, file_size: usize) -> CodecResult {
+        CodecResult {
+            codec_id: codec.to_string(),
+            codec_version: "1.0".to_string(),
+            quality,
+            file_size,
+            bits_per_pixel: file_size as f64 * 8.0 / 100.0,
+            encode_time: Duration::from_millis(10),
+            decode_time: None,
+            encode_timing: None,
+            decode_timing: None,
+            encode_peak_bytes: None,
+            decode_peak_bytes: None,
+            metrics: MetricResult {
+                ssimulacra2: Some(quality),
+                ..MetricResult::default()
+            },
+            perception: None,
+            cached_path: None,
+            heatmaps: BTreeMap::new(),
+            search: None,
+            generations: Vec::new(),
+            codec_params: HashMap::new(),
+        }
+    }
+
+    fn shard(images: &[(&str, &[(&str, f64)])]) -> CorpusReport {
+        let mut report = CorpusReport::new("corpus".to_string());
+        report.config_summary = "codecs=[a@1.0]".to_string();
+        for &(name, results) in images {
+            let mut img = ImageReport::new(name.to_string(), 10, 10);
+            for &(codec, quality) in results {
+                img.results.push(result(codec, quality, quality as usize));
+            }
+            report.images.push(img);
+        }
+        report
+    }
+
+    #[test]
+    fn test_merge_combines_shards() {
+        let a = shard(&[("x", &[("a", 50.0), ("a", 90.0)]), ("y", &[("a", 50.0)])]);
+        let mut b = shard(&[("y", &[("a", 50.0), ("b", 50.0)]), ("z", &[("a", 50.0)])]);
+        // Timing differs between machines; still the same result.
+        b.images[0].results[0].encode_time = Duration::from_millis(99);
+        let codec_error = Error::Codec {
+            codec: "a".to_string(),
+            message: "boom".to_string(),
+        };
+        b.images[0]
+            .failures
+            .push(FailureRecord::new("a", "1.0", Some(90.0), &codec_error));
+        let mut c = shard(&[("y", &[("a", 90.0)])]);
+        c.config_summary = "codecs=[a@1.1]".to_string();
+
+        let merged = CorpusReport::merge([&a, &b, &c]).unwrap();
+        let names: Vec<&str> = merged.images.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["x", "y", "z"]);
+        assert_eq!(merged.total_results(), 6);
+        assert_eq!(merged.images[1].results.len(), 3);
+        // The failed q90 job succeeded in shard `c`.
+        assert!(merged.images[1].failures.is_empty());
+        assert_eq!(merged.config_summary, "codecs=[a@1.0] | codecs=[a@1.1]");
+    }
+
+    #[test]
+    fn test_merge_rejects_conflicts() {
+        let a = shard(&[("x", &[("a", 50.0)])]);
+        let mut b = shard(&[("x", &[("a", 50.0)])]);
+        b.images[0].results[0].file_size += 1;
+        let err = CorpusReport::merge([&a, &b]).unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("conflicting results for x a@1.0 q50: file_size 50 vs 51")
+        );
+
+        // Same size, different score: the metric is named.
+        let mut d = shard(&[("x", &[("a", 50.0)])]);
+        d.images[0].results[0].metrics.ssimulacra2 = Some(49.5);
+        let err = CorpusReport::merge([&a, &d]).unwrap_err();
+        assert!(
+            err.to_string().ends_with(": ssimulacra2 50 vs 49.5"),
+            "{err}"
+        );
+
+        let mut c = shard(&[("x", &[])]);
+        c.images[0].width = 20;
+        assert!(CorpusReport::merge([&a, &c]).is_err());
+        assert!(CorpusReport::merge([]).is_err());
+    }
+
+    #[test]
+    fn test_filter_and_load() {
+        let report = shard(&[
+            ("x", &[("a", 50.0), ("a", 90.0), ("b", 70.0)]),
+            ("y", &[("a", 50.0)]),
+        ]);
+        let filtered = report.filter(&ReportFilter::new().codec("a").quality_range(60.0, 100.0));
+        assert_eq!(filtered.total_results(), 1);
+        assert_eq!(filtered.images.len(), 2);
+        assert_eq!(filtered.images[0].results[0].quality, 90.0);
+
+        let only_y = report.filter(&ReportFilter::new().image("y"));
+        assert_eq!(only_y.images.len(), 1);
+        assert_eq!(only_y.images[0].name, "y");
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.json");
+        std::fs::write(&path, serde_json::to_string(&report).unwrap()).unwrap();
+        let loaded = CorpusReport::load(&path).unwrap();
+        assert_eq!(loaded.total_results(), 4);
+        assert!(CorpusReport::load(dir.path().join("missing.json")).is_err());
+    }
+}
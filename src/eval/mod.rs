@@ -6,12 +6,49 @@
 //! - [`session::EvalConfig`]: Configuration for evaluation
 //! - [`session::ImageData`]: Image data types accepted by the session
 //! - [`report`]: Report types for evaluation results
+//! - [`diff`]: Run-over-run comparison of corpus reports
+//! - [`generation`]: Generation-loss mode, re-encoding decoded outputs
+//! - [`merge`]: Loading, merging and filtering corpus reports
+//! - `html`: Self-contained HTML report (requires `html` feature)
+//! - [`observer`]: Progress callbacks and cancellation for long runs
+//! - [`search`]: Target-quality and target-size searches over the encoder
+//!   quality setting
+//! - [`validation`]: Metric validation against subjective (MOS/JND) datasets
 //! - [`helpers`]: Lightweight helpers for simple quality evaluation
 
+mod cache;
+pub mod diff;
+pub mod generation;
 pub mod helpers;
+#[cfg(feature = "html")]
+pub mod html;
+mod journal;
+pub mod memory;
+pub mod merge;
+pub mod observer;
 pub mod report;
+pub mod search;
 pub mod session;
+pub mod timing;
+pub mod validation;
 
+pub use diff::{
+    CodecDelta, DiffOptions, ImageDiff, MetricDelta, Regression, ReportDiff, ResultDelta,
+};
+pub use generation::{ChainStep, GenerationCurve, GenerationLoss, GenerationResult};
 pub use helpers::{assert_perception_level, assert_quality, evaluate_single};
-pub use report::{CodecResult, CorpusReport, ImageReport};
-pub use session::{EvalConfig, EvalSession, ImageData};
+#[cfg(feature = "html")]
+pub use html::HtmlReport;
+pub use memory::TrackingAllocator;
+pub use merge::ReportFilter;
+pub use observer::{CancellationToken, EvalObserver, Progress, ProgressBar};
+pub use report::{
+    CodecResult, CorpusReport, FailureRecord, FailureSummary, ImageReport, MemorySummary,
+    PeakMemory,
+};
+pub use search::{EvalMode, QualityTarget, SearchInfo, SizeBudget, SizeTarget};
+pub use session::{EvalConfig, EvalSession, ImageData, ParamGrid};
+pub use timing::{Timing, TimingPolicy, TimingStatistic, TimingSummary};
+pub use validation::{
+    FittedThresholds, MetricCorrelation, MosDataset, RatedPair, ValidationReport, ValidationSample,
+};
@@ -0,0 +1,356 @@
+//! Progress reporting and cancellation for evaluation runs.
+//!
+//! An [`EvalObserver`] registered with
+//! [`EvalSession::add_observer`](super::EvalSession::add_observer) is told
+//! when each image starts and finishes, when each job produces a result or
+//! fails, and how far the run has come, with an estimate of the time left.
+//! Callbacks run on the session's worker threads as jobs complete, so a
+//! dashboard can stream results while the run is still going.
+//!
+//! A [`CancellationToken`] stops a run early: jobs that have not started yet
+//! are skipped and the evaluation returns [`Error::Cancelled`]. Results
+//! finished before that are still in the journal, if one is enabled, so the
+//! run can be resumed later.
+//!
+//! [`ProgressBar`] is a ready-made observer that draws a one-line progress
+//! bar on stderr.
+
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::report::{CodecResult, FailureRecord, ImageReport};
+#[cfg(doc)]
+use crate::error::Error;
+
+/// Callbacks for an evaluation run.
+///
+/// Every method has an empty default, so an observer implements only what it
+/// needs. Callbacks are invoked concurrently from the session's thread pool
+/// and should return quickly.
+pub trait EvalObserver: Send + Sync {
+    /// An image is about to be evaluated.
+    fn image_started(&self, name: &str) {
+        let _ = name;
+    }
+
+    /// An image is done. `report` holds all its results and failures.
+    ///
+    /// Images restored from the journal are reported here without a
+    /// preceding [`image_started`](Self::image_started).
+    fn image_finished(&self, report: &ImageReport) {
+        let _ = report;
+    }
+
+    /// A job produced a result. Results reused from the journal are not
+    /// reported again.
+    fn result(&self, image: &str, result: &CodecResult) {
+        let _ = (image, result);
+    }
+
+    /// A job, or loading an image, failed.
+    ///
+    /// Called for every failure, including the one that aborts a
+    /// [`fail_fast`](super::EvalConfig::fail_fast) run.
+    fn failure(&self, image: &str, failure: &FailureRecord) {
+        let _ = (image, failure);
+    }
+
+    /// A job or an image finished, successfully or not.
+    fn progress(&self, progress: &Progress) {
+        let _ = progress;
+    }
+}
+
+/// Shared observers, so the caller can keep a handle to read back what it
+/// collected after the run.
+impl<T: EvalObserver + ?Sized> EvalObserver for Arc<T> {
+    fn image_started(&self, name: &str) {
+        (**self).image_started(name);
+    }
+
+    fn image_finished(&self, report: &ImageReport) {
+        (**self).image_finished(report);
+    }
+
+    fn result(&self, image: &str, result: &CodecResult) {
+        (**self).result(image, result);
+    }
+
+    fn failure(&self, image: &str, failure: &FailureRecord) {
+        (**self).failure(image, failure);
+    }
+
+    fn progress(&self, progress: &Progress) {
+        (**self).progress(progress);
+    }
+}
+
+/// How far an evaluation run has come.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// Jobs finished, including failed ones and ones reused from the journal.
+    pub jobs_done: usize,
+    /// Jobs in the whole run.
+    pub jobs_total: usize,
+    /// Images finished.
+    pub images_done: usize,
+    /// Images in the whole run.
+    pub images_total: usize,
+    /// Time since the run started.
+    pub elapsed: Duration,
+    /// Estimated time left, from the average time per job run so far.
+    /// `None` until a job has actually run.
+    pub eta: Option<Duration>,
+}
+
+impl Progress {
+    /// Fraction of jobs done, from 0.0 to 1.0.
+    #[must_use]
+    pub fn fraction(&self) -> f64 {
+        if self.jobs_total == 0 {
+            1.0
+        } else {
+            self.jobs_done as f64 / self.jobs_total as f64
+        }
+    }
+
+    /// Whether every job and image has finished.
+    #[must_use]
+    pub fn is_done(&self) -> bool {
+        self.jobs_done >= self.jobs_total && self.images_done >= self.images_total
+    }
+}
+
+/// Shared flag to cancel a running evaluation.
+///
+/// Clones share the flag, so one clone can be handed to a signal handler or
+/// another thread while the session holds the other.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// A token that is not cancelled.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation. Jobs already running finish; no new ones start.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether cancellation has been requested.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Job and image counters for one `evaluate_*` call.
+pub(super) struct RunProgress {
+    started: Instant,
+    jobs_total: usize,
+    images_total: usize,
+    jobs_done: AtomicUsize,
+    /// Jobs counted as done without running (journaled).
+    jobs_reused: AtomicUsize,
+    images_done: AtomicUsize,
+}
+
+impl RunProgress {
+    pub(super) fn new(images: usize, jobs_per_image: usize) -> Self {
+        Self {
+            started: Instant::now(),
+            jobs_total: images * jobs_per_image,
+            images_total: images,
+            jobs_done: AtomicUsize::new(0),
+            jobs_reused: AtomicUsize::new(0),
+            images_done: AtomicUsize::new(0),
+        }
+    }
+
+    /// Count `jobs` finished jobs, of which `reused` came from the journal.
+    pub(super) fn jobs_finished(&self, jobs: usize, reused: usize) -> Progress {
+        self.jobs_reused.fetch_add(reused, Ordering::Relaxed);
+        self.jobs_done.fetch_add(jobs, Ordering::Relaxed);
+        self.snapshot()
+    }
+
+    pub(super) fn image_finished(&self) {
+        self.images_done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn snapshot(&self) -> Progress {
+        let jobs_done = self.jobs_done.load(Ordering::Relaxed);
+        let ran = jobs_done.saturating_sub(self.jobs_reused.load(Ordering::Relaxed));
+        let elapsed = self.started.elapsed();
+        let remaining = self.jobs_total.saturating_sub(jobs_done);
+        let eta = if remaining == 0 {
+            Some(Duration::ZERO)
+        } else {
+            (ran > 0).then(|| elapsed.mul_f64(remaining as f64 / ran as f64))
+        };
+        Progress {
+            jobs_done,
+            jobs_total: self.jobs_total,
+            images_done: self.images_done.load(Ordering::Relaxed),
+            images_total: self.images_total,
+            elapsed,
+            eta,
+        }
+    }
+}
+
+/// Observer that draws a progress bar with job counts and ETA on stderr,
+/// and prints failures above it as they happen.
+#[derive(Debug)]
+pub struct ProgressBar {
+    width: usize,
+    /// When the bar was last drawn; redraws are limited to ten per second.
+    last_draw: Mutex<Option<Instant>>,
+}
+
+impl Default for ProgressBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressBar {
+    /// A 30-character progress bar.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            width: 30,
+            last_draw: Mutex::new(None),
+        }
+    }
+
+    /// Set the width of the bar itself, in characters.
+    #[must_use]
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = width.max(1);
+        self
+    }
+
+    /// The bar line for `progress`, without the leading carriage return.
+    #[must_use]
+    pub fn render(&self, progress: &Progress) -> String {
+        let filled = ((progress.fraction() * self.width as f64) as usize).min(self.width);
+        let eta = progress
+            .eta
+            .map_or_else(|| "--".to_string(), format_duration);
+        format!(
+            "[{}{}] {}/{} jobs, {}/{} images, {} elapsed, ETA {eta}",
+            "#".repeat(filled),
+            "-".repeat(self.width - filled),
+            progress.jobs_done,
+            progress.jobs_total,
+            progress.images_done,
+            progress.images_total,
+            format_duration(progress.elapsed),
+        )
+    }
+}
+
+impl EvalObserver for ProgressBar {
+    fn failure(&self, image: &str, failure: &FailureRecord) {
+        let _guard = self.last_draw.lock();
+        let quality = failure
+            .quality
+            .map_or_else(String::new, |q| format!(" q{q}"));
+        // Clear the bar line; the next progress update redraws it.
+        eprintln!(
+            "\r\x1b[KFAILED {image} {}{quality}: {}",
+            failure.codec_id, failure.message
+        );
+    }
+
+    fn progress(&self, progress: &Progress) {
+        let Ok(mut last_draw) = self.last_draw.lock() else {
+            return;
+        };
+        let now = Instant::now();
+        let done = progress.is_done();
+        if !done && last_draw.is_some_and(|t| now - t < Duration::from_millis(100)) {
+            return;
+        }
+        *last_draw = Some(now);
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[K{}", self.render(progress));
+        if done {
+            let _ = writeln!(stderr);
+        }
+        let _ = stderr.flush();
+    }
+}
+
+/// `1h02m`, `3m05s` or `12s`.
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, s) => format!("{m}m{s:02}s"),
+        (h, m, _) => format!("{h}h{m:02}m"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_progress_eta() {
+        let run = RunProgress::new(2, 5);
+        assert_eq!(run.snapshot().eta, None);
+
+        // Journaled jobs count as done but do not feed the estimate.
+        let progress = run.jobs_finished(5, 5);
+        assert_eq!(progress.jobs_done, 5);
+        assert_eq!(progress.jobs_total, 10);
+        assert_eq!(progress.eta, None);
+
+        std::thread::sleep(Duration::from_millis(10));
+        let progress = run.jobs_finished(1, 0);
+        let eta = progress.eta.unwrap();
+        // Four jobs left at the pace of the one that ran.
+        assert!(eta >= progress.elapsed.mul_f64(3.9));
+
+        let progress = run.jobs_finished(4, 0);
+        assert_eq!(progress.eta, Some(Duration::ZERO));
+        // Done once the images are counted as well.
+        assert!(!progress.is_done());
+        run.image_finished();
+        run.image_finished();
+        assert!(run.snapshot().is_done());
+    }
+
+    #[test]
+    fn test_cancellation_token_shared() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!token.is_cancelled());
+        clone.cancel();
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn test_progress_bar_render() {
+        let bar = ProgressBar::new().with_width(10);
+        let progress = Progress {
+            jobs_done: 5,
+            jobs_total: 10,
+            images_done: 1,
+            images_total: 2,
+            elapsed: Duration::from_secs(65),
+            eta: Some(Duration::from_secs(3725)),
+        };
+        assert_eq!(
+            bar.render(&progress),
+            "[#####-----] 5/10 jobs, 1/2 images, 1m05s elapsed, ETA 1h02m"
+        );
+    }
+}
@@ -3,13 +3,18 @@
 //! This module defines the data structures for evaluation reports that can be
 //! serialized to JSON or CSV.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::PathBuf;
 use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 
+use super::generation::GenerationResult;
+use super::search::SearchInfo;
+use super::timing::Timing;
 use crate::metrics::{MetricResult, PerceptionLevel};
+use crate::stats::rd_knee::{ButteraugliNorm, CorpusAggregate};
+use crate::stats::{QualityDirection, RDPoint, bd_rate, mean, median};
 
 /// Result from evaluating a single codec on a single image at a single quality.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,6 +42,24 @@ pub struct CodecResult {
     #[serde(with = "duration_millis_option")]
     pub decode_time: Option<Duration>,
 
+    /// Statistics of the timed encode runs, per `EvalConfig::timing`.
+    /// `encode_time` is the configured statistic of their wall time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encode_timing: Option<Timing>,
+
+    /// Statistics of the timed decode runs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decode_timing: Option<Timing>,
+
+    /// Peak heap growth during the encode, in bytes, when
+    /// `EvalConfig::measure_memory` is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encode_peak_bytes: Option<u64>,
+
+    /// Peak heap growth during the decode, in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decode_peak_bytes: Option<u64>,
+
     /// Quality metrics comparing decoded to reference.
     pub metrics: MetricResult,
 
@@ -46,6 +69,20 @@ pub struct CodecResult {
     /// Path to cached encoded file (if caching enabled).
     pub cached_path: Option<PathBuf>,
 
+    /// False-colour distortion heatmaps by metric name (if
+    /// `EvalConfig::heatmaps` is enabled).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub heatmaps: BTreeMap<String, PathBuf>,
+
+    /// How the quality setting was found (target-quality mode only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search: Option<SearchInfo>,
+
+    /// Size and scores of every generation, the first being this result's
+    /// own encode (generation-loss mode only).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub generations: Vec<GenerationResult>,
+
     /// Additional codec-specific parameters used.
     #[serde(default)]
     pub codec_params: HashMap<String, String>,
@@ -63,6 +100,64 @@ impl CodecResult {
     }
 }
 
+/// A codec job that failed and was skipped.
+///
+/// Recorded in [`ImageReport::failures`] unless `EvalConfig::fail_fast` is
+/// set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FailureRecord {
+    /// Codec identifier (empty if the source image failed to load).
+    pub codec_id: String,
+
+    /// Codec version string.
+    pub codec_version: String,
+
+    /// Quality setting of the failed job (`None` for target-mode searches
+    /// and load failures).
+    pub quality: Option<f64>,
+
+    /// Encoder parameters of the failed job.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, String>,
+
+    /// Error kind, as returned by [`Error::kind`](crate::Error::kind).
+    pub kind: String,
+
+    /// Error message.
+    pub message: String,
+}
+
+impl FailureRecord {
+    /// Record `error` for a job of `codec_id`.
+    #[must_use]
+    pub fn new(
+        codec_id: &str,
+        codec_version: &str,
+        quality: Option<f64>,
+        error: &crate::Error,
+    ) -> Self {
+        Self {
+            codec_id: codec_id.to_string(),
+            codec_version: codec_version.to_string(),
+            quality,
+            params: BTreeMap::new(),
+            kind: error.kind().to_string(),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Failure counts across a corpus report.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailureSummary {
+    /// Total number of failed jobs.
+    pub total: usize,
+    /// Failed jobs per codec id.
+    pub by_codec: BTreeMap<String, usize>,
+    /// Failed jobs per error kind.
+    pub by_kind: BTreeMap<String, usize>,
+}
+
 /// Report for a single image evaluated across multiple codecs and quality levels.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImageReport {
@@ -82,6 +177,10 @@ pub struct ImageReport {
     /// Results for each codec/quality combination.
     pub results: Vec<CodecResult>,
 
+    /// Jobs that failed, in job order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<FailureRecord>,
+
     /// When this report was generated.
     #[serde(with = "chrono_serde")]
     pub timestamp: chrono::DateTime<chrono::Utc>,
@@ -98,6 +197,7 @@ impl ImageReport {
             height,
             uncompressed_size: (width as usize) * (height as usize) * 3,
             results: Vec::new(),
+            failures: Vec::new(),
             timestamp: chrono::Utc::now(),
         }
     }
@@ -108,6 +208,10 @@ impl ImageReport {
     }
 
     /// Get the best result (highest quality metric) at or below a target file size.
+    ///
+    /// Only considers results that were already evaluated; use
+    /// [`EvalMode::TargetSize`](super::EvalMode::TargetSize) to search the
+    /// quality setting for a budget instead.
     #[must_use]
     pub fn best_at_size(&self, max_bytes: usize) -> Option<&CodecResult> {
         self.results
@@ -123,6 +227,18 @@ impl ImageReport {
             })
     }
 
+    /// Rate-distortion curve of one codec as `(bits_per_pixel, score)` pairs
+    /// for a built-in or custom metric, in result order.
+    ///
+    /// Results without that metric are skipped. The pairs can be passed
+    /// straight to [`crate::stats::bd_rate`].
+    #[must_use]
+    pub fn rd_curve(&self, codec_id: &str, metric: &str) -> Vec<(f64, f64)> {
+        self.results_for_codec(codec_id)
+            .filter_map(|r| Some((r.bits_per_pixel, r.metrics.value(metric)?)))
+            .collect()
+    }
+
     /// Get the smallest file that achieves at least the target quality.
     #[must_use]
     pub fn smallest_at_quality(&self, max_dssim: f64) -> Option<&CodecResult> {
@@ -133,6 +249,38 @@ impl ImageReport {
     }
 }
 
+/// Peak heap growth of one codec's encodes or decodes across a corpus.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PeakMemory {
+    /// Number of measured encodes or decodes.
+    pub samples: usize,
+    /// Median peak, in bytes.
+    pub median_bytes: f64,
+    /// Largest peak, in bytes.
+    pub max_bytes: u64,
+}
+
+impl PeakMemory {
+    fn from_samples(samples: &[u64]) -> Option<Self> {
+        let max_bytes = *samples.iter().max()?;
+        let values: Vec<f64> = samples.iter().map(|&b| b as f64).collect();
+        Some(Self {
+            samples: samples.len(),
+            median_bytes: median(&values),
+            max_bytes,
+        })
+    }
+}
+
+/// Peak memory of one codec across a corpus report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct MemorySummary {
+    /// Encode peaks, if any were measured.
+    pub encode: Option<PeakMemory>,
+    /// Decode peaks, if any were measured.
+    pub decode: Option<PeakMemory>,
+}
+
 /// Report for a corpus of images.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CorpusReport {
@@ -168,6 +316,133 @@ impl CorpusReport {
         self.images.iter().map(|img| img.results.len()).sum()
     }
 
+    /// Failure counts per codec and error kind across all images.
+    #[must_use]
+    pub fn failure_summary(&self) -> FailureSummary {
+        let mut summary = FailureSummary::default();
+        for failure in self.images.iter().flat_map(|img| &img.failures) {
+            summary.total += 1;
+            *summary
+                .by_codec
+                .entry(failure.codec_id.clone())
+                .or_default() += 1;
+            *summary.by_kind.entry(failure.kind.clone()).or_default() += 1;
+        }
+        summary
+    }
+
+    /// Peak memory per codec id, for codecs with measured results (see
+    /// `EvalConfig::measure_memory`).
+    #[must_use]
+    pub fn memory_summary(&self) -> BTreeMap<String, MemorySummary> {
+        let mut peaks: BTreeMap<&str, (Vec<u64>, Vec<u64>)> = BTreeMap::new();
+        for r in self.images.iter().flat_map(|img| &img.results) {
+            if r.encode_peak_bytes.is_none() && r.decode_peak_bytes.is_none() {
+                continue;
+            }
+            let (encode, decode) = peaks.entry(&r.codec_id).or_default();
+            encode.extend(r.encode_peak_bytes);
+            decode.extend(r.decode_peak_bytes);
+        }
+        peaks
+            .into_iter()
+            .map(|(codec, (encode, decode))| {
+                let summary = MemorySummary {
+                    encode: PeakMemory::from_samples(&encode),
+                    decode: PeakMemory::from_samples(&decode),
+                };
+                (codec.to_string(), summary)
+            })
+            .collect()
+    }
+
+    /// Rate-distortion points for a built-in or custom metric across all
+    /// images, oriented by `direction` so that higher is better (as
+    /// [`ParetoFront`](crate::stats::ParetoFront) expects).
+    #[must_use]
+    pub fn rd_points(&self, metric: &str, direction: QualityDirection) -> Vec<RDPoint> {
+        self.images
+            .iter()
+            .flat_map(|img| img.results.iter().map(move |r| (img, r)))
+            .filter_map(|(img, r)| {
+                let mut point = RDPoint::new(
+                    r.codec_id.clone(),
+                    r.quality,
+                    r.bits_per_pixel,
+                    direction.higher_is_better(r.metrics.value(metric)?),
+                );
+                point.encode_time_ms = Some(r.encode_time.as_secs_f64() * 1000.0);
+                point.encode_peak_bytes = r.encode_peak_bytes;
+                point.image = Some(img.name.clone());
+                Some(point)
+            })
+            .collect()
+    }
+
+    /// Mean per-image BD-rate of `test_codec` against `reference_codec` for a
+    /// built-in or custom metric. Negative means `test_codec` needs fewer bits.
+    ///
+    /// Images where either curve is too short or the curves do not overlap
+    /// are skipped; returns `None` if no image qualifies.
+    #[must_use]
+    pub fn bd_rate(&self, reference_codec: &str, test_codec: &str, metric: &str) -> Option<f64> {
+        let rates: Vec<f64> = self
+            .images
+            .iter()
+            .filter_map(|img| {
+                bd_rate(
+                    &img.rd_curve(reference_codec, metric),
+                    &img.rd_curve(test_codec, metric),
+                )
+            })
+            .collect();
+        if rates.is_empty() {
+            None
+        } else {
+            Some(rates.iter().sum::<f64>() / rates.len() as f64)
+        }
+    }
+
+    /// Corpus-aggregate R-D curve of `codec_id` for knee detection, with
+    /// the Butteraugli axis filled from `norm`.
+    ///
+    /// Results are averaged per quality setting over the images that have
+    /// both SSIMULACRA2 and the chosen Butteraugli score. Results of every
+    /// parameter combination are pooled, so filter a parameter grid first.
+    #[must_use]
+    pub fn rd_aggregate(&self, codec_id: &str, norm: ButteraugliNorm) -> CorpusAggregate {
+        type Samples = (Vec<f64>, Vec<f64>, Vec<f64>);
+        let mut by_quality: BTreeMap<u64, Samples> = BTreeMap::new();
+        let mut images = BTreeSet::new();
+        for img in &self.images {
+            for r in img.results.iter().filter(|r| r.codec_id == codec_id) {
+                let (Some(s2), Some(ba)) = (r.metrics.ssimulacra2, r.metrics.value(norm.metric()))
+                else {
+                    continue;
+                };
+                let (bpp_samples, s2_samples, ba_samples) =
+                    by_quality.entry(r.quality.to_bits()).or_default();
+                bpp_samples.push(r.bits_per_pixel);
+                s2_samples.push(s2);
+                ba_samples.push(ba);
+                images.insert(img.name.as_str());
+            }
+        }
+
+        let mut curve: Vec<(f64, f64, f64)> = by_quality
+            .values()
+            .map(|(bpp, s2, ba)| (mean(bpp), mean(s2), mean(ba)))
+            .collect();
+        curve.sort_by(|a, b| a.0.total_cmp(&b.0));
+        CorpusAggregate {
+            corpus: self.name.clone(),
+            codec: codec_id.to_string(),
+            curve,
+            image_count: images.len(),
+            butteraugli_norm: norm,
+        }
+    }
+
     /// Get unique codec IDs in this report.
     #[must_use]
     pub fn codec_ids(&self) -> Vec<String> {
@@ -249,6 +524,31 @@ mod chrono_serde {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_failure_summary() {
+        let codec_error = crate::Error::Codec {
+            codec: "a".to_string(),
+            message: "boom".to_string(),
+        };
+        let io_error = crate::Error::Io(std::io::Error::other("disk"));
+        let mut img = ImageReport::new("img".to_string(), 8, 8);
+        img.failures = vec![
+            FailureRecord::new("a", "1", Some(90.0), &codec_error),
+            FailureRecord::new("a", "1", Some(95.0), &io_error),
+            FailureRecord::new("b", "2", None, &codec_error),
+        ];
+        assert_eq!(img.failures[0].kind, "codec");
+        assert_eq!(img.failures[0].message, "Codec error (a): boom");
+
+        let mut report = CorpusReport::new("corpus".to_string());
+        report.images.push(img);
+        let summary = report.failure_summary();
+        assert_eq!(summary.total, 3);
+        assert_eq!(summary.by_codec["a"], 2);
+        assert_eq!(summary.by_kind["codec"], 2);
+        assert_eq!(summary.by_kind["io"], 1);
+    }
+
     #[test]
     fn test_image_report_new() {
         let report = ImageReport::new("test.png".to_string(), 1920, 1080);
@@ -268,9 +568,16 @@ mod tests {
             bits_per_pixel: 0.5,
             encode_time: Duration::from_millis(100),
             decode_time: None,
+            encode_timing: None,
+            decode_timing: None,
+            encode_peak_bytes: None,
+            decode_peak_bytes: None,
             metrics: MetricResult::default(),
             perception: None,
             cached_path: None,
+            heatmaps: BTreeMap::new(),
+            search: None,
+            generations: Vec::new(),
             codec_params: HashMap::new(),
         };
 
@@ -289,9 +596,16 @@ mod tests {
             bits_per_pixel: 0.8,
             encode_time: Duration::from_millis(50),
             decode_time: None,
+            encode_timing: None,
+            decode_timing: None,
+            encode_peak_bytes: None,
+            decode_peak_bytes: None,
             metrics: MetricResult::default(),
             perception: None,
             cached_path: None,
+            heatmaps: BTreeMap::new(),
+            search: None,
+            generations: Vec::new(),
             codec_params: HashMap::new(),
         });
         img.results.push(CodecResult {
@@ -302,9 +616,16 @@ mod tests {
             bits_per_pixel: 0.72,
             encode_time: Duration::from_millis(60),
             decode_time: None,
+            encode_timing: None,
+            decode_timing: None,
+            encode_peak_bytes: None,
+            decode_peak_bytes: None,
             metrics: MetricResult::default(),
             perception: None,
             cached_path: None,
+            heatmaps: BTreeMap::new(),
+            search: None,
+            generations: Vec::new(),
             codec_params: HashMap::new(),
         });
         report.images.push(img);
@@ -314,4 +635,95 @@ mod tests {
         assert!(ids.contains(&"mozjpeg".to_string()));
         assert!(ids.contains(&"webp".to_string()));
     }
+
+    fn custom_result(codec: &str, bpp: f64, mae: f64) -> CodecResult {
+        let mut metrics = MetricResult::default();
+        metrics.custom.insert("mae".to_string(), mae);
+        CodecResult {
+            codec_id: codec.to_string(),
+            codec_version: "1.0".to_string(),
+            quality: bpp * 100.0,
+            file_size: (bpp * 1000.0) as usize,
+            bits_per_pixel: bpp,
+            encode_time: Duration::from_millis(10),
+            decode_time: None,
+            encode_timing: None,
+            decode_timing: None,
+            encode_peak_bytes: None,
+            decode_peak_bytes: None,
+            metrics,
+            perception: None,
+            cached_path: None,
+            heatmaps: BTreeMap::new(),
+            search: None,
+            generations: Vec::new(),
+            codec_params: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_custom_metric_rd_points_and_bd_rate() {
+        let mut report = CorpusReport::new("test".to_string());
+        let mut img = ImageReport::new("img1.png".to_string(), 100, 100);
+        for (bpp, mae) in [(0.5, 8.0), (1.0, 6.0), (2.0, 4.0), (4.0, 2.0)] {
+            img.results.push(custom_result("ref", bpp, mae));
+            // Same quality at half the rate.
+            img.results.push(custom_result("test", bpp / 2.0, mae));
+        }
+        report.images.push(img);
+
+        let points = report.rd_points("mae", QualityDirection::LowerIsBetter);
+        assert_eq!(points.len(), 8);
+        assert_eq!(points[0].quality, -8.0);
+        assert_eq!(points[0].image.as_deref(), Some("img1.png"));
+        assert!(
+            report
+                .rd_points("ssimulacra2", QualityDirection::HigherIsBetter)
+                .is_empty()
+        );
+
+        let bd = report.bd_rate("ref", "test", "mae").unwrap();
+        assert!(
+            (bd + 50.0).abs() < 1e-6,
+            "half the rate should be -50%, got {bd}"
+        );
+        assert!(report.bd_rate("ref", "missing", "mae").is_none());
+    }
+
+    #[test]
+    fn test_rd_aggregate_butteraugli_norm() {
+        let mut report = CorpusReport::new("corpus".to_string());
+        for (name, offset) in [("a.png", 0.0), ("b.png", 1.0)] {
+            let mut img = ImageReport::new(name.to_string(), 100, 100);
+            for bpp in [2.0, 0.5, 1.0] {
+                let mut result = custom_result("jpeg", bpp + offset, 0.0);
+                result.quality = bpp * 10.0;
+                result.metrics.ssimulacra2 = Some(60.0 + bpp * 10.0);
+                result.metrics.butteraugli = Some(6.0 / bpp);
+                result.metrics.butteraugli_pnorm = Some(2.0 / bpp);
+                img.results.push(result);
+            }
+            report.images.push(img);
+        }
+        // No p-norm on this one: left out of the p-norm curve only.
+        report.images[1].results[0].metrics.butteraugli_pnorm = None;
+
+        let max = report.rd_aggregate("jpeg", ButteraugliNorm::Max);
+        assert_eq!(max.image_count, 2);
+        assert_eq!(
+            max.curve,
+            [(1.0, 65.0, 12.0), (1.5, 70.0, 6.0), (2.5, 80.0, 3.0)]
+        );
+
+        let pnorm = report.rd_aggregate("jpeg", ButteraugliNorm::PNorm);
+        assert_eq!(pnorm.butteraugli_norm, ButteraugliNorm::PNorm);
+        assert_eq!(pnorm.curve[2], (2.0, 80.0, 1.0));
+        assert_eq!(pnorm.curve[0].2, 4.0);
+        assert!(
+            report
+                .rd_aggregate("webp", ButteraugliNorm::Max)
+                .curve
+                .is_empty()
+        );
+    }
 }
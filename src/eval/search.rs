@@ -0,0 +1,358 @@
+//! Searching the encoder quality dial for a target.
+//!
+//! In [`EvalMode::TargetQuality`] the session does not sweep fixed quality
+//! levels. Instead, for every codec and image it bisects the encoder quality
+//! setting until the decoded image scores within a tolerance of a target
+//! metric value (e.g. SSIMULACRA2 = 80), and reports the resulting file size
+//! together with a [`SearchInfo`] describing the search. When no probe lands
+//! within the tolerance, the lowest quality setting that still meets the
+//! target is reported.
+//!
+//! [`EvalMode::TargetSize`] is the opposite: it finds the highest quality
+//! setting whose encode fits a byte or bits-per-pixel budget, then scores
+//! only that encode.
+//!
+//! Both searches assume file size and metric scores improve monotonically
+//! with the quality setting.
+
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use super::generation::GenerationLoss;
+use crate::error::Result;
+
+/// How the session chooses encoder settings.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum EvalMode {
+    /// Encode at every level in `EvalConfig::quality_levels`.
+    #[default]
+    Sweep,
+    /// Search the quality setting for each target, one result per target.
+    TargetQuality(Vec<QualityTarget>),
+    /// Search the largest encode within each budget, one result per target.
+    TargetSize(Vec<SizeTarget>),
+    /// Encode at every quality level, then re-encode the decoded output for
+    /// several generations, scoring each against the original.
+    GenerationLoss(GenerationLoss),
+}
+
+/// A metric score to hit by searching the encoder quality setting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityTarget {
+    /// Metric name, as accepted by
+    /// [`MetricResult::value`](crate::metrics::MetricResult::value).
+    pub metric: String,
+    /// Score to hit.
+    pub target: f64,
+    /// Accept a probe whose score is within this distance of `target`.
+    ///
+    /// With `0.0` the search runs until the quality setting is pinned down to
+    /// `precision`.
+    pub tolerance: f64,
+    /// Inclusive range of encoder quality settings to search.
+    pub quality_range: (f64, f64),
+    /// Quality settings are probed in multiples of this step.
+    pub precision: f64,
+    /// Maximum number of encodes per codec and image.
+    pub max_encodes: usize,
+}
+
+impl QualityTarget {
+    /// Target `value` for `metric`, searching quality 0-100 in whole steps.
+    #[must_use]
+    pub fn new(metric: &str, value: f64) -> Self {
+        Self {
+            metric: metric.to_string(),
+            target: value,
+            tolerance: 0.0,
+            quality_range: (0.0, 100.0),
+            precision: 1.0,
+            max_encodes: 12,
+        }
+    }
+
+    /// Set the score tolerance.
+    #[must_use]
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance.abs();
+        self
+    }
+
+    /// Set the inclusive quality range to search.
+    #[must_use]
+    pub fn with_quality_range(mut self, min: f64, max: f64) -> Self {
+        self.quality_range = (min.min(max), min.max(max));
+        self
+    }
+
+    /// Set the quality step.
+    #[must_use]
+    pub fn with_precision(mut self, precision: f64) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Set the encode budget per codec and image.
+    #[must_use]
+    pub fn with_max_encodes(mut self, max_encodes: usize) -> Self {
+        self.max_encodes = max_encodes.max(1);
+        self
+    }
+}
+
+/// Encoded size limit for [`SizeTarget`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SizeBudget {
+    /// At most this many bytes.
+    Bytes(usize),
+    /// At most this many bits per pixel of the source image.
+    Bpp(f64),
+}
+
+impl SizeBudget {
+    /// Largest allowed encode in bytes for a `width` x `height` image.
+    #[must_use]
+    pub fn max_bytes(self, width: usize, height: usize) -> usize {
+        match self {
+            Self::Bytes(bytes) => bytes,
+            Self::Bpp(bpp) => (bpp.max(0.0) * (width * height) as f64 / 8.0).floor() as usize,
+        }
+    }
+
+    /// Unit name, used as [`SearchInfo::metric`].
+    #[must_use]
+    pub fn unit(self) -> &'static str {
+        match self {
+            Self::Bytes(_) => "bytes",
+            Self::Bpp(_) => "bpp",
+        }
+    }
+
+    /// Budget in its own unit.
+    #[must_use]
+    pub fn value(self) -> f64 {
+        match self {
+            Self::Bytes(bytes) => bytes as f64,
+            Self::Bpp(bpp) => bpp,
+        }
+    }
+}
+
+/// A size budget to fill by searching the encoder quality setting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeTarget {
+    /// Encoded size limit.
+    pub budget: SizeBudget,
+    /// Inclusive range of encoder quality settings to search.
+    pub quality_range: (f64, f64),
+    /// Quality settings are probed in multiples of this step.
+    pub precision: f64,
+    /// Maximum number of encodes per codec and image.
+    pub max_encodes: usize,
+}
+
+impl SizeTarget {
+    /// Budget of `bytes`, searching quality 0-100 in whole steps.
+    #[must_use]
+    pub fn bytes(bytes: usize) -> Self {
+        Self::new(SizeBudget::Bytes(bytes))
+    }
+
+    /// Budget of `bpp` bits per pixel, searching quality 0-100 in whole steps.
+    #[must_use]
+    pub fn bpp(bpp: f64) -> Self {
+        Self::new(SizeBudget::Bpp(bpp))
+    }
+
+    fn new(budget: SizeBudget) -> Self {
+        Self {
+            budget,
+            quality_range: (0.0, 100.0),
+            precision: 1.0,
+            max_encodes: 12,
+        }
+    }
+
+    /// Set the inclusive quality range to search.
+    #[must_use]
+    pub fn with_quality_range(mut self, min: f64, max: f64) -> Self {
+        self.quality_range = (min.min(max), min.max(max));
+        self
+    }
+
+    /// Set the quality step.
+    #[must_use]
+    pub fn with_precision(mut self, precision: f64) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Set the encode budget per codec and image.
+    #[must_use]
+    pub fn with_max_encodes(mut self, max_encodes: usize) -> Self {
+        self.max_encodes = max_encodes.max(1);
+        self
+    }
+}
+
+/// How a search for a target went, stored in
+/// [`CodecResult::search`](super::CodecResult::search).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchInfo {
+    /// Metric that was searched for, or `bytes` / `bpp` for size budgets.
+    pub metric: String,
+    /// Target score or size budget.
+    pub target: f64,
+    /// Score or size of the reported result.
+    pub achieved: f64,
+    /// Number of encodes spent, including the reported one.
+    pub encodes: usize,
+    /// Whether the search hit the target within tolerance or pinned down the
+    /// quality setting at the boundary (lowest meeting a score target,
+    /// highest fitting a budget). `false` if the encode budget ran out or the
+    /// target is out of reach within the quality range.
+    pub converged: bool,
+}
+
+/// Result of [`bisect`].
+pub(crate) struct Bisection<T> {
+    pub(crate) value: T,
+    pub(crate) encodes: usize,
+    pub(crate) converged: bool,
+}
+
+/// Bisect quality settings in `range`, in steps of `precision`.
+///
+/// `probe` encodes at a quality setting and reports how that setting compares
+/// to the ideal: `Equal` for a hit, `Greater` if it is higher than needed,
+/// `Less` if it is too low. Settings on the `keep` side are acceptable; when
+/// nothing hits exactly, the acceptable probe closest to the boundary is
+/// returned, or failing that the unacceptable probe closest to it.
+pub(crate) fn bisect<T>(
+    range: (f64, f64),
+    precision: f64,
+    max_encodes: usize,
+    keep: Ordering,
+    mut probe: impl FnMut(f64) -> Result<(Ordering, T)>,
+) -> Result<Bisection<T>> {
+    let step = if precision > 0.0 { precision } else { 1.0 };
+    let snap = |q: f64| ((q / step).round() * step).clamp(range.0, range.1);
+    let (mut lo, mut hi) = (snap(range.0), snap(range.1));
+
+    let mut encodes = 0;
+    // Closest probe on each side of the boundary, by quality setting.
+    let mut above: Option<T> = None;
+    let mut below: Option<T> = None;
+    while lo <= hi && encodes < max_encodes.max(1) {
+        let quality = snap(lo + (hi - lo) / 2.0);
+        let (ordering, value) = probe(quality)?;
+        encodes += 1;
+        match ordering {
+            Ordering::Equal => {
+                return Ok(Bisection {
+                    value,
+                    encodes,
+                    converged: true,
+                });
+            }
+            Ordering::Greater => {
+                hi = quality - step;
+                above = Some(value);
+            }
+            Ordering::Less => {
+                lo = quality + step;
+                below = Some(value);
+            }
+        }
+    }
+
+    let exhausted = lo > hi;
+    let (kept, other) = match keep {
+        Ordering::Less => (below, above),
+        _ => (above, below),
+    };
+    match (kept, other) {
+        (Some(value), _) => Ok(Bisection {
+            value,
+            encodes,
+            converged: exhausted,
+        }),
+        (None, Some(value)) => Ok(Bisection {
+            value,
+            encodes,
+            converged: false,
+        }),
+        (None, None) => unreachable!("bisect always probes at least once"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Probe a linear "codec" whose score is `quality / 2`.
+    fn search(target: f64, tolerance: f64, max_encodes: usize) -> (f64, usize, bool) {
+        let result = bisect((0.0, 100.0), 1.0, max_encodes, Ordering::Greater, |q| {
+            let score = q / 2.0;
+            let ordering = if (score - target).abs() <= tolerance {
+                Ordering::Equal
+            } else if score > target {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            };
+            Ok((ordering, q))
+        })
+        .unwrap();
+        (result.value, result.encodes, result.converged)
+    }
+
+    #[test]
+    fn test_bisect_hits_target() {
+        let (quality, encodes, converged) = search(40.0, 0.0, 12);
+        assert_eq!(quality, 80.0);
+        assert!(converged);
+        assert!(encodes <= 7, "{encodes} encodes");
+    }
+
+    #[test]
+    fn test_bisect_keeps_lowest_passing_quality() {
+        // Score 30.25 lies between quality 60 and 61.
+        let (quality, _, converged) = search(30.25, 0.0, 12);
+        assert_eq!(quality, 61.0);
+        assert!(converged);
+    }
+
+    #[test]
+    fn test_bisect_tolerance_stops_early() {
+        let (_, exact, _) = search(30.25, 0.0, 12);
+        let (quality, loose, converged) = search(30.25, 6.0, 12);
+        assert!(converged);
+        assert_eq!(loose, 1);
+        assert!(loose < exact);
+        assert_eq!(quality, 50.0);
+    }
+
+    #[test]
+    fn test_bisect_unreachable_target() {
+        let (quality, _, converged) = search(60.0, 0.0, 12);
+        assert_eq!(quality, 100.0);
+        assert!(!converged);
+    }
+
+    #[test]
+    fn test_size_budget() {
+        assert_eq!(SizeBudget::Bytes(1000).max_bytes(64, 64), 1000);
+        assert_eq!(SizeBudget::Bpp(0.5).max_bytes(64, 64), 256);
+        assert_eq!(SizeBudget::Bpp(-1.0).max_bytes(64, 64), 0);
+        assert_eq!(SizeTarget::bpp(0.5).budget.unit(), "bpp");
+    }
+
+    #[test]
+    fn test_bisect_budget() {
+        let (_, encodes, converged) = search(30.25, 0.0, 3);
+        assert_eq!(encodes, 3);
+        assert!(!converged);
+    }
+}
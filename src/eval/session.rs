@@ -4,18 +4,42 @@
 //! External crates provide encode/decode callbacks, and the session handles
 //! metrics calculation, caching, and report generation.
 
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write as _;
 use std::path::{Path, PathBuf};
-use std::time::Instant;
+use std::sync::OnceLock;
 
-use imgref::ImgVec;
-use rgb::{RGB8, RGBA8};
+use imgref::{ImgRef, ImgVec};
+use rayon::prelude::*;
+use rgb::{FromSlice, RGB8, RGBA, RGBA8};
 
-use crate::error::Result;
-use crate::eval::report::{CodecResult, CorpusReport, ImageReport};
-use crate::metrics::dssim::rgb8_to_dssim_image;
-use crate::metrics::{MetricConfig, MetricResult, calculate_psnr};
-use crate::viewing::ViewingCondition;
+use crate::corpus::{Corpus, Fnv1a};
+use crate::error::{Error, Result};
+use crate::eval::cache::EncodeCache;
+#[cfg(feature = "heatmap")]
+use crate::eval::cache::sanitize;
+use crate::eval::generation::{GenerationLoss, GenerationResult};
+use crate::eval::journal::{JOURNAL_FILE, Journal};
+use crate::eval::memory::{self, TrackingAllocator};
+use crate::eval::observer::{CancellationToken, EvalObserver, RunProgress};
+use crate::eval::report::{CodecResult, CorpusReport, FailureRecord, ImageReport};
+use crate::eval::search::{EvalMode, QualityTarget, SearchInfo, SizeBudget, SizeTarget, bisect};
+use crate::eval::timing::{Timing, TimingPolicy, TimingSummary};
+use crate::metrics::alpha::{alpha_rmse, composite_over, composite_over_f32};
+use crate::metrics::custom::builtin_direction;
+#[cfg(feature = "heatmap")]
+use crate::metrics::heatmap::heatmap_scale;
+use crate::metrics::resample::resample_linear;
+use crate::metrics::tiles::{Rect, Region, TileScore, TileScores};
+use crate::metrics::transfer::{SDR_WHITE_NITS, linearize};
+use crate::metrics::{
+    DistortionMap, Metric, MetricConfig, MetricResult, ReferenceContext, TransferFunction,
+    calculate_psnr_f32,
+};
+use crate::metrics::{butteraugli, ciede2000, dssim, ssim};
+use crate::stats::QualityDirection;
+use crate::viewing::{SimulationMode, SimulationParams, ViewingCondition};
 
 /// Image data accepted by the evaluation session.
 ///
@@ -63,6 +87,54 @@ pub enum ImageData {
         /// ICC profile data (raw bytes from the image).
         icc_profile: Vec<u8>,
     },
+
+    /// High bit depth RGB raw slice (e.g. 10/12-bit AVIF or JPEG XL output).
+    ///
+    /// Samples range from 0 to `(1 << bit_depth) - 1`.
+    Rgb16 {
+        /// Pixel data in row-major order, 3 samples per pixel.
+        data: Vec<u16>,
+        /// Image width.
+        width: usize,
+        /// Image height.
+        height: usize,
+        /// Significant bits per sample (1-16).
+        bit_depth: u8,
+        /// Transfer function of the samples.
+        transfer: TransferFunction,
+    },
+
+    /// High bit depth RGBA raw slice (straight alpha).
+    ///
+    /// Samples range from 0 to `(1 << bit_depth) - 1`.
+    Rgba16 {
+        /// Pixel data in row-major order, 4 samples per pixel.
+        data: Vec<u16>,
+        /// Image width.
+        width: usize,
+        /// Image height.
+        height: usize,
+        /// Significant bits per sample (1-16).
+        bit_depth: u8,
+        /// Transfer function of the samples.
+        transfer: TransferFunction,
+    },
+
+    /// Floating-point RGB raw slice.
+    ///
+    /// Samples are normalized code values: `1.0` is the transfer function's
+    /// nominal peak. With [`TransferFunction::Linear`], `1.0` is SDR
+    /// reference white and larger values are allowed.
+    RgbF32 {
+        /// Pixel data in row-major order, 3 samples per pixel.
+        data: Vec<f32>,
+        /// Image width.
+        width: usize,
+        /// Image height.
+        height: usize,
+        /// Transfer function of the samples.
+        transfer: TransferFunction,
+    },
 }
 
 impl ImageData {
@@ -74,7 +146,10 @@ impl ImageData {
             Self::Rgba8(img) => img.width(),
             Self::RgbSlice { width, .. }
             | Self::RgbaSlice { width, .. }
-            | Self::RgbSliceWithIcc { width, .. } => *width,
+            | Self::RgbSliceWithIcc { width, .. }
+            | Self::Rgb16 { width, .. }
+            | Self::Rgba16 { width, .. }
+            | Self::RgbF32 { width, .. } => *width,
         }
     }
 
@@ -86,14 +161,91 @@ impl ImageData {
             Self::Rgba8(img) => img.height(),
             Self::RgbSlice { height, .. }
             | Self::RgbaSlice { height, .. }
-            | Self::RgbSliceWithIcc { height, .. } => *height,
+            | Self::RgbSliceWithIcc { height, .. }
+            | Self::Rgb16 { height, .. }
+            | Self::Rgba16 { height, .. }
+            | Self::RgbF32 { height, .. } => *height,
+        }
+    }
+
+    /// Transfer function of the pixel values. 8-bit variants are sRGB.
+    #[must_use]
+    pub fn transfer_function(&self) -> TransferFunction {
+        match self {
+            Self::Rgb16 { transfer, .. }
+            | Self::Rgba16 { transfer, .. }
+            | Self::RgbF32 { transfer, .. } => *transfer,
+            _ => TransferFunction::Srgb,
+        }
+    }
+
+    /// Whether this image holds more than 8 bits per sample.
+    ///
+    /// High-precision images are scored without quantizing to 8 bits.
+    #[must_use]
+    pub fn is_high_precision(&self) -> bool {
+        matches!(
+            self,
+            Self::Rgb16 { .. } | Self::Rgba16 { .. } | Self::RgbF32 { .. }
+        )
+    }
+
+    /// Convert to normalized (0.0-1.0) RGB code values in the image's own
+    /// transfer function.
+    ///
+    /// Note: This does NOT apply ICC profile transformation. Alpha is dropped.
+    #[must_use]
+    pub fn to_rgb_f32_signal(&self) -> Vec<f32> {
+        match self {
+            Self::Rgb16 {
+                data, bit_depth, ..
+            } => {
+                let scale = 1.0 / max_sample(*bit_depth);
+                data.iter().map(|&v| f32::from(v) * scale).collect()
+            }
+            Self::Rgba16 {
+                data, bit_depth, ..
+            } => {
+                let scale = 1.0 / max_sample(*bit_depth);
+                data.chunks_exact(4)
+                    .flat_map(|p| [p[0], p[1], p[2]])
+                    .map(|v| f32::from(v) * scale)
+                    .collect()
+            }
+            Self::RgbF32 { data, .. } => data.clone(),
+            _ => self
+                .to_rgb8_vec()
+                .into_iter()
+                .map(|v| f32::from(v) / 255.0)
+                .collect(),
+        }
+    }
+
+    /// Alpha plane as normalized (0.0-1.0) values, if the image has one.
+    fn alpha_f32(&self) -> Option<Vec<f32>> {
+        match self {
+            Self::Rgba16 {
+                data, bit_depth, ..
+            } => {
+                let scale = 1.0 / max_sample(*bit_depth);
+                Some(
+                    data.chunks_exact(4)
+                        .map(|p| f32::from(p[3]) * scale)
+                        .collect(),
+                )
+            }
+            _ => self
+                .alpha_vec()
+                .map(|alpha| alpha.into_iter().map(|v| f32::from(v) / 255.0).collect()),
         }
     }
 
     /// Convert to RGB8 slice representation.
     ///
     /// Note: This does NOT apply ICC profile transformation. For ICC-aware
-    /// conversion, use [`ImageData::to_rgb8_srgb()`] instead.
+    /// conversion, use [`ImageData::to_rgb8_srgb()`] instead. Alpha is dropped;
+    /// use [`ImageData::alpha_vec()`] to get it separately. High-precision
+    /// variants are quantized to 8 bits without changing transfer function.
     #[must_use]
     pub fn to_rgb8_vec(&self) -> Vec<u8> {
         match self {
@@ -113,6 +265,33 @@ impl ImageData {
                 }
                 rgb
             }
+            Self::Rgb16 { .. } | Self::Rgba16 { .. } | Self::RgbF32 { .. } => self
+                .to_rgb_f32_signal()
+                .into_iter()
+                .map(quantize_u8)
+                .collect(),
+        }
+    }
+
+    /// Whether this image carries an alpha channel.
+    #[must_use]
+    pub fn has_alpha(&self) -> bool {
+        matches!(
+            self,
+            Self::Rgba8(_) | Self::RgbaSlice { .. } | Self::Rgba16 { .. }
+        )
+    }
+
+    /// Extract the alpha plane (one byte per pixel), if the image has one.
+    #[must_use]
+    pub fn alpha_vec(&self) -> Option<Vec<u8>> {
+        match self {
+            Self::Rgba8(img) => Some(img.pixels().map(|p| p.a).collect()),
+            Self::RgbaSlice { data, .. } => Some(data.chunks_exact(4).map(|p| p[3]).collect()),
+            Self::Rgba16 { .. } => self
+                .alpha_f32()
+                .map(|alpha| alpha.into_iter().map(quantize_u8).collect()),
+            _ => None,
         }
     }
 
@@ -136,6 +315,57 @@ impl ImageData {
         }
     }
 
+    /// Checksum of the pixel content, dimensions, layout and ICC profile.
+    ///
+    /// Two `ImageData` values with the same checksum feed identical input to an
+    /// encoder. Used as the source key of the encode cache.
+    #[must_use]
+    pub fn content_checksum(&self) -> String {
+        let mut hasher = Fnv1a::new();
+        let (tag, pixels): (String, std::borrow::Cow<'_, [u8]>) = match self {
+            Self::Rgb8(img) => (
+                "rgb8".to_string(),
+                img.pixels().flat_map(|p| [p.r, p.g, p.b]).collect(),
+            ),
+            Self::Rgba8(img) => (
+                "rgba8".to_string(),
+                img.pixels().flat_map(|p| [p.r, p.g, p.b, p.a]).collect(),
+            ),
+            Self::RgbSlice { data, .. } | Self::RgbSliceWithIcc { data, .. } => {
+                ("rgb8".to_string(), data.into())
+            }
+            Self::RgbaSlice { data, .. } => ("rgba8".to_string(), data.into()),
+            Self::Rgb16 {
+                data,
+                bit_depth,
+                transfer,
+                ..
+            } => (
+                format!("rgb16/{bit_depth}/{}", transfer.name()),
+                data.iter().flat_map(|v| v.to_le_bytes()).collect(),
+            ),
+            Self::Rgba16 {
+                data,
+                bit_depth,
+                transfer,
+                ..
+            } => (
+                format!("rgba16/{bit_depth}/{}", transfer.name()),
+                data.iter().flat_map(|v| v.to_le_bytes()).collect(),
+            ),
+            Self::RgbF32 { data, transfer, .. } => (
+                format!("rgbf32/{}", transfer.name()),
+                data.iter().flat_map(|v| v.to_le_bytes()).collect(),
+            ),
+        };
+        hasher.update_field(tag.as_bytes());
+        hasher.update(&(self.width() as u64).to_le_bytes());
+        hasher.update(&(self.height() as u64).to_le_bytes());
+        hasher.update_field(&pixels);
+        hasher.update_field(self.icc_profile().unwrap_or_default());
+        hasher.finish_hex()
+    }
+
     /// Convert to sRGB RGB8 slice, applying ICC profile transformation if needed.
     ///
     /// This is the ICC-aware version of [`ImageData::to_rgb8_vec()`]. Use this when you need
@@ -147,6 +377,16 @@ impl ImageData {
     }
 }
 
+/// Largest sample value at a bit depth, as `f32`.
+fn max_sample(bit_depth: u8) -> f32 {
+    ((1u32 << bit_depth.clamp(1, 16)) - 1) as f32
+}
+
+/// Quantize a normalized sample to 8 bits.
+fn quantize_u8(v: f32) -> u8 {
+    (v.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
 /// Request for a single encode operation.
 #[derive(Debug, Clone)]
 pub struct EncodeRequest {
@@ -175,6 +415,62 @@ impl EncodeRequest {
     }
 }
 
+/// Named encoder parameters to cross with every quality level or target.
+///
+/// Each parameter lists the values to try; the session evaluates the
+/// cartesian product of all of them, passing each combination in
+/// [`EncodeRequest::params`].
+///
+/// ```rust,ignore
+/// let grid = ParamGrid::new()
+///     .with_param("subsampling", &["420", "444"])
+///     .with_param("speed", &["4", "6", "8"]);
+/// // 6 combinations per quality level.
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParamGrid {
+    /// Values to try for each parameter.
+    pub params: BTreeMap<String, Vec<String>>,
+}
+
+impl ParamGrid {
+    /// Create an empty grid (a single combination with no parameters).
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a parameter and the values to try for it.
+    #[must_use]
+    pub fn with_param(mut self, name: &str, values: &[&str]) -> Self {
+        self.params.insert(
+            name.to_string(),
+            values.iter().map(ToString::to_string).collect(),
+        );
+        self
+    }
+
+    /// Every combination of parameter values, varying the last parameter
+    /// (by name) fastest. A parameter with no values is ignored.
+    #[must_use]
+    pub fn combinations(&self) -> Vec<BTreeMap<String, String>> {
+        let mut combinations = vec![BTreeMap::new()];
+        for (name, values) in self.params.iter().filter(|(_, v)| !v.is_empty()) {
+            combinations = combinations
+                .into_iter()
+                .flat_map(|base| {
+                    values.iter().map(move |value| {
+                        let mut combination = base.clone();
+                        combination.insert(name.clone(), value.clone());
+                        combination
+                    })
+                })
+                .collect();
+        }
+        combinations
+    }
+}
+
 /// Encode callback type.
 ///
 /// Takes image data and encode request, returns encoded bytes.
@@ -192,16 +488,81 @@ pub struct EvalConfig {
     pub report_dir: PathBuf,
 
     /// Directory for caching encoded files.
+    ///
+    /// When set, bitstreams are stored content-addressed by source checksum,
+    /// codec id, codec version, quality and encode parameters, and reused on
+    /// later runs instead of re-encoding.
     pub cache_dir: Option<PathBuf>,
 
     /// Viewing condition for perceptual metrics.
+    ///
+    /// Reference and decoded images are resampled according to
+    /// [`ViewingCondition::simulation_params`] before any metric runs.
     pub viewing: ViewingCondition,
 
+    /// How the viewing condition is simulated (resampling policy).
+    pub simulation_mode: SimulationMode,
+
     /// Which metrics to calculate.
     pub metrics: MetricConfig,
 
     /// Quality levels to sweep.
     pub quality_levels: Vec<f64>,
+
+    /// How encoder settings are chosen. In the target modes,
+    /// `quality_levels` is ignored.
+    pub mode: EvalMode,
+
+    /// Encoder parameter grids by codec id. Every combination is crossed
+    /// with each quality level (or target) and recorded in
+    /// [`CodecResult::codec_params`]. Codecs without a grid are encoded with
+    /// empty params.
+    pub param_grids: BTreeMap<String, ParamGrid>,
+
+    /// Regions of interest by image name, scored separately from the whole
+    /// image and reported in [`MetricResult::tiles`].
+    pub regions: BTreeMap<String, Vec<Region>>,
+
+    /// Number of worker threads for encode/decode/metric jobs.
+    ///
+    /// `None` uses rayon's global pool (one thread per core unless
+    /// `RAYON_NUM_THREADS` says otherwise).
+    pub threads: Option<usize>,
+
+    /// Write false-colour distortion heatmaps for every decoded result.
+    ///
+    /// DSSIM, Butteraugli and custom metrics that provide
+    /// [`Metric::distortion_map`] are rendered to
+    /// `<report_dir>/heatmaps/<image>/<codec>-q<quality>-<metric>.png` and
+    /// recorded in [`CodecResult::heatmaps`]. Requires the `heatmap` feature.
+    pub heatmaps: bool,
+
+    /// Abort on the first failed job instead of recording it in
+    /// [`ImageReport::failures`] and carrying on.
+    pub fail_fast: bool,
+
+    /// Append every result to `<report_dir>/journal.jsonl` as it is produced
+    /// and skip jobs already recorded there, so interrupted runs can resume.
+    ///
+    /// Jobs are identified by image, codec, version, quality and parameters
+    /// (or search target). A journal written with different metric or
+    /// viewing settings is rejected.
+    pub journal: bool,
+
+    /// How encodes and decodes are timed: warmup runs, repetitions and the
+    /// statistic reported as [`CodecResult::encode_time`] / `decode_time`.
+    ///
+    /// Timings are only comparable between jobs that did not run
+    /// concurrently; set `threads(1)` when speed matters.
+    pub timing: TimingPolicy,
+
+    /// Record the peak heap growth of every encode and decode in
+    /// [`CodecResult::encode_peak_bytes`] / `decode_peak_bytes`.
+    ///
+    /// Requires [`TrackingAllocator`] as the global allocator, and runs jobs
+    /// one at a time regardless of `threads` so their allocations can be told
+    /// apart.
+    pub measure_memory: bool,
 }
 
 impl EvalConfig {
@@ -218,8 +579,18 @@ pub struct EvalConfigBuilder {
     report_dir: Option<PathBuf>,
     cache_dir: Option<PathBuf>,
     viewing: Option<ViewingCondition>,
+    simulation_mode: Option<SimulationMode>,
     metrics: Option<MetricConfig>,
     quality_levels: Option<Vec<f64>>,
+    mode: EvalMode,
+    param_grids: BTreeMap<String, ParamGrid>,
+    regions: BTreeMap<String, Vec<Region>>,
+    threads: Option<usize>,
+    heatmaps: bool,
+    fail_fast: bool,
+    journal: bool,
+    timing: TimingPolicy,
+    measure_memory: bool,
 }
 
 impl EvalConfigBuilder {
@@ -244,6 +615,13 @@ impl EvalConfigBuilder {
         self
     }
 
+    /// Set how the viewing condition is simulated.
+    #[must_use]
+    pub fn simulation_mode(mut self, mode: SimulationMode) -> Self {
+        self.simulation_mode = Some(mode);
+        self
+    }
+
     /// Set which metrics to calculate.
     #[must_use]
     pub fn metrics(mut self, metrics: MetricConfig) -> Self {
@@ -258,6 +636,95 @@ impl EvalConfigBuilder {
         self
     }
 
+    /// Set how encoder settings are chosen.
+    #[must_use]
+    pub fn mode(mut self, mode: EvalMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Search the quality setting of every codec for each target instead of
+    /// sweeping `quality_levels`.
+    #[must_use]
+    pub fn target_quality(self, targets: Vec<QualityTarget>) -> Self {
+        self.mode(EvalMode::TargetQuality(targets))
+    }
+
+    /// Search the highest quality setting of every codec that fits each
+    /// size budget instead of sweeping `quality_levels`.
+    #[must_use]
+    pub fn target_size(self, targets: Vec<SizeTarget>) -> Self {
+        self.mode(EvalMode::TargetSize(targets))
+    }
+
+    /// Sweep `quality_levels`, re-encoding every decoded output for the
+    /// generations of `loss`.
+    #[must_use]
+    pub fn generation_loss(self, loss: GenerationLoss) -> Self {
+        self.mode(EvalMode::GenerationLoss(loss))
+    }
+
+    /// Set the encoder parameter grid for `codec_id`.
+    #[must_use]
+    pub fn param_grid(mut self, codec_id: &str, grid: ParamGrid) -> Self {
+        self.param_grids.insert(codec_id.to_string(), grid);
+        self
+    }
+
+    /// Score `regions` of the image named `image` separately, e.g. faces
+    /// or text that a whole-image average would hide.
+    #[must_use]
+    pub fn regions(mut self, image: &str, regions: Vec<Region>) -> Self {
+        self.regions.insert(image.to_string(), regions);
+        self
+    }
+
+    /// Limit evaluation to `threads` worker threads.
+    ///
+    /// Each (codec, quality) encode/decode/metric job runs as a separate task,
+    /// so this bounds total concurrency across images as well.
+    #[must_use]
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads.max(1));
+        self
+    }
+
+    /// Write distortion heatmaps next to each result.
+    #[must_use]
+    pub fn heatmaps(mut self, enabled: bool) -> Self {
+        self.heatmaps = enabled;
+        self
+    }
+
+    /// Abort on the first failed job instead of recording failures.
+    #[must_use]
+    pub fn fail_fast(mut self, enabled: bool) -> Self {
+        self.fail_fast = enabled;
+        self
+    }
+
+    /// Journal results in the report directory and resume from it.
+    #[must_use]
+    pub fn journal(mut self, enabled: bool) -> Self {
+        self.journal = enabled;
+        self
+    }
+
+    /// Set how encodes and decodes are timed.
+    #[must_use]
+    pub fn timing(mut self, policy: TimingPolicy) -> Self {
+        self.timing = policy;
+        self
+    }
+
+    /// Record peak heap growth per encode and decode. Requires
+    /// [`TrackingAllocator`] as the global allocator.
+    #[must_use]
+    pub fn measure_memory(mut self, enabled: bool) -> Self {
+        self.measure_memory = enabled;
+        self
+    }
+
     /// Build the configuration.
     ///
     /// # Panics
@@ -269,10 +736,20 @@ impl EvalConfigBuilder {
             report_dir: self.report_dir.expect("report_dir is required"),
             cache_dir: self.cache_dir,
             viewing: self.viewing.unwrap_or_default(),
+            simulation_mode: self.simulation_mode.unwrap_or_default(),
             metrics: self.metrics.unwrap_or_else(MetricConfig::all),
             quality_levels: self
                 .quality_levels
                 .unwrap_or_else(|| vec![50.0, 60.0, 70.0, 80.0, 85.0, 90.0, 95.0]),
+            mode: self.mode,
+            param_grids: self.param_grids,
+            regions: self.regions,
+            threads: self.threads,
+            heatmaps: self.heatmaps,
+            fail_fast: self.fail_fast,
+            journal: self.journal,
+            timing: self.timing,
+            measure_memory: self.measure_memory,
         }
     }
 }
@@ -285,6 +762,112 @@ struct CodecEntry {
     decode: Option<DecodeFn>,
 }
 
+impl CodecEntry {
+    /// The decoder, or an error naming the `purpose` that needs one.
+    fn decoder(&self, purpose: &str) -> Result<&DecodeFn> {
+        self.decode.as_ref().ok_or_else(|| Error::Codec {
+            codec: self.id.clone(),
+            message: format!("{purpose} requires a decoder"),
+        })
+    }
+}
+
+/// A bitstream and how producing it went.
+struct Encoded {
+    data: Vec<u8>,
+    timing: Timing,
+    peak_bytes: Option<u64>,
+    cached_path: Option<PathBuf>,
+}
+
+/// One unit of work for an image: a single encode or a search, with one
+/// combination of the codec's parameter grid.
+struct Job<'a> {
+    codec: &'a CodecEntry,
+    params: BTreeMap<String, String>,
+    spec: JobSpec<'a>,
+}
+
+/// How a job picks its quality setting.
+#[derive(Clone, Copy)]
+enum JobSpec<'a> {
+    Sweep(f64),
+    TargetQuality(&'a QualityTarget),
+    TargetSize(&'a SizeTarget),
+    Generations(f64, &'a GenerationLoss),
+}
+
+impl Job<'_> {
+    /// Identity of the job in the results journal: codec, version and every
+    /// setting that determines the result.
+    fn key(&self) -> String {
+        let settings = match self.spec {
+            JobSpec::Sweep(quality) => format!("q={quality}"),
+            JobSpec::Generations(quality, loss) => format!("q={quality}:{}", loss.summary()),
+            JobSpec::TargetQuality(t) => format!(
+                "target_quality={}:{}:tol={}:range={}-{}:step={}:max={}",
+                t.metric,
+                t.target,
+                t.tolerance,
+                t.quality_range.0,
+                t.quality_range.1,
+                t.precision,
+                t.max_encodes
+            ),
+            JobSpec::TargetSize(t) => format!(
+                "target_size={}{}:range={}-{}:step={}:max={}",
+                t.budget.value(),
+                t.budget.unit(),
+                t.quality_range.0,
+                t.quality_range.1,
+                t.precision,
+                t.max_encodes
+            ),
+        };
+        let mut key = format!("{}\t{}\t{settings}", self.codec.id, self.codec.version);
+        if !self.params.is_empty() {
+            key.push('\t');
+            key.push_str(&format_params(&self.params));
+        }
+        key
+    }
+
+    /// Failure record for `error` in this job.
+    fn failure(&self, error: &Error) -> FailureRecord {
+        let mut failure =
+            FailureRecord::new(&self.codec.id, &self.codec.version, self.quality(), error);
+        failure.params.clone_from(&self.params);
+        failure
+    }
+
+    /// Quality setting, if the job encodes at a fixed one.
+    fn quality(&self) -> Option<f64> {
+        match self.spec {
+            JobSpec::Sweep(quality) | JobSpec::Generations(quality, _) => Some(quality),
+            JobSpec::TargetQuality(_) | JobSpec::TargetSize(_) => None,
+        }
+    }
+
+    /// Encode request for `quality` with this job's parameters.
+    fn request(&self, quality: f64) -> EncodeRequest {
+        EncodeRequest {
+            quality,
+            params: self.params.clone().into_iter().collect(),
+        }
+    }
+}
+
+/// Parameters as `name=value` pairs, comma-separated in name order.
+pub(super) fn format_params<'a>(
+    params: impl IntoIterator<Item = (&'a String, &'a String)>,
+) -> String {
+    params
+        .into_iter()
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
 /// Evaluation session for codec comparison.
 ///
 /// # Example
@@ -309,6 +892,77 @@ struct CodecEntry {
 pub struct EvalSession {
     config: EvalConfig,
     codecs: Vec<CodecEntry>,
+    metrics: Vec<Box<dyn Metric>>,
+    cache: Option<EncodeCache>,
+    /// Results journal, opened on first use when `EvalConfig::journal` is set.
+    journal: OnceLock<Journal>,
+    observers: Vec<Box<dyn EvalObserver>>,
+    cancel: CancellationToken,
+}
+
+/// Layout version of [`EvalSession::settings_fingerprint`]; bump it whenever
+/// the hashed fields change, so older journals are rejected rather than
+/// silently matched.
+const SETTINGS_FINGERPRINT_VERSION: u32 = 1;
+
+/// Per-image state shared by every (codec, quality) job for that image.
+pub(super) struct PreparedImage<'a> {
+    name: &'a str,
+    image: &'a ImageData,
+    /// Reference pixels as the metrics see them, with alpha compositing, XYB
+    /// roundtrip (if enabled) and viewing simulation already applied and
+    /// each metric's reference state precomputed. One entry per alpha
+    /// background for translucent references, otherwise one; empty for
+    /// high-precision references, which [`EvalSession::score_precise`] scores.
+    references: Vec<PreparedReference>,
+    /// Alpha plane of the reference, if it has one.
+    reference_alpha: Option<Vec<u8>>,
+    /// Viewing simulation applied to reference and decoded images.
+    simulation: SimulationParams,
+    /// Source checksum, computed only when the encode cache is enabled.
+    checksum: Option<String>,
+    /// Tiles, then regions, to score on their own; empty unless configured.
+    areas: Vec<LocalArea>,
+    /// Tile columns and rows.
+    tile_layout: (u32, u32),
+    /// High-precision counterpart of `references`, built on first use by
+    /// [`EvalSession::score_precise`].
+    precise: OnceLock<Vec<PreciseImage>>,
+}
+
+/// One composited reference, whole and cropped to each local area.
+struct PreparedReference {
+    image: ReferenceContext,
+    /// One per entry of [`PreparedImage::areas`]; `None` for areas too
+    /// small to score.
+    areas: Vec<Option<ReferenceContext>>,
+}
+
+/// A tile or region of interest, in source and simulated pixels.
+struct LocalArea {
+    /// Region name; `None` for grid tiles.
+    name: Option<String>,
+    rect: Rect,
+    /// `rect` mapped onto the viewing-simulated image.
+    scaled: Rect,
+    /// Whether `scaled` is at least [`MIN_AREA_SIDE`] on each side. Smaller
+    /// areas, including regions outside the image, are reported unscored.
+    scorable: bool,
+}
+
+/// Smallest tile or region side, after viewing simulation, that every
+/// metric can score.
+const MIN_AREA_SIDE: u32 = 8;
+
+/// Distortion maps by metric name, collected when `EvalConfig::heatmaps` is set.
+type DistortionMaps = BTreeMap<String, DistortionMap>;
+
+/// An image prepared for the high-precision metric path.
+struct PreciseImage {
+    /// Normalized code values after viewing simulation (for PSNR).
+    signal: Vec<f32>,
+    /// Linear light relative to SDR reference white after viewing simulation.
+    linear: Vec<f32>,
 }
 
 impl EvalSession {
@@ -316,8 +970,13 @@ impl EvalSession {
     #[must_use]
     pub fn new(config: EvalConfig) -> Self {
         Self {
+            cache: config.cache_dir.as_ref().map(EncodeCache::new),
             config,
             codecs: Vec::new(),
+            metrics: Vec::new(),
+            journal: OnceLock::new(),
+            observers: Vec::new(),
+            cancel: CancellationToken::new(),
         }
     }
 
@@ -349,6 +1008,51 @@ impl EvalSession {
         self
     }
 
+    /// Register a custom metric, computed for every decoded image after the
+    /// built-in metrics.
+    ///
+    /// Scores are stored in [`MetricResult::custom`] under [`Metric::name`];
+    /// registering a second metric with the same name replaces the first.
+    pub fn add_metric(&mut self, metric: impl Metric + 'static) -> &mut Self {
+        self.metrics.retain(|m| m.name() != metric.name());
+        self.metrics.push(Box::new(metric));
+        self
+    }
+
+    /// Register an observer that is told about progress, results and
+    /// failures of every evaluation run by this session.
+    pub fn add_observer(&mut self, observer: impl EvalObserver + 'static) -> &mut Self {
+        self.observers.push(Box::new(observer));
+        self
+    }
+
+    /// Use `token` to cancel this session's evaluations, e.g. one shared
+    /// with a Ctrl-C handler or with other sessions.
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) -> &mut Self {
+        self.cancel = token;
+        self
+    }
+
+    /// A handle to the token that cancels this session's evaluations.
+    ///
+    /// After [`CancellationToken::cancel`], running evaluations start no new
+    /// jobs and return [`Error::Cancelled`]; so does every later one.
+    #[must_use]
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// Direction of a built-in or registered metric.
+    #[must_use]
+    pub fn metric_direction(&self, name: &str) -> Option<QualityDirection> {
+        builtin_direction(name).or_else(|| {
+            self.metrics
+                .iter()
+                .find(|m| m.name() == name)
+                .map(|m| m.direction())
+        })
+    }
+
     /// Get the number of registered codecs.
     #[must_use]
     pub fn codec_count(&self) -> usize {
@@ -357,6 +1061,10 @@ impl EvalSession {
 
     /// Evaluate a single image across all registered codecs.
     ///
+    /// Every (codec, quality) combination runs as an independent job on the
+    /// session's thread pool. Results are ordered by codec registration order,
+    /// then by quality level, regardless of which job finishes first.
+    ///
     /// # Arguments
     ///
     /// * `name` - Image name or identifier.
@@ -365,243 +1073,2655 @@ impl EvalSession {
     /// # Returns
     ///
     /// An [`ImageReport`] containing results for all codec/quality combinations.
+    /// Jobs that fail are listed in [`ImageReport::failures`] instead, unless
+    /// [`EvalConfig::fail_fast`] is set. Returns [`Error::Cancelled`] if the
+    /// [`cancellation_token`](Self::cancellation_token) is cancelled before
+    /// all jobs have run.
     pub fn evaluate_image(&self, name: &str, image: ImageData) -> Result<ImageReport> {
-        let width = image.width() as u32;
-        let height = image.height() as u32;
-        let mut report = ImageReport::new(name.to_string(), width, height);
-
-        let reference_rgb = image.to_rgb8_vec();
-
-        for codec in &self.codecs {
-            for &quality in &self.config.quality_levels {
-                let request = EncodeRequest::new(quality);
-
-                // Encode
-                let start = Instant::now();
-                let encoded = (codec.encode)(&image, &request)?;
-                let encode_time = start.elapsed();
-
-                // Calculate metrics
-                let metrics = if let Some(ref decode) = codec.decode {
-                    // Decode and compare
-                    let start = Instant::now();
-                    let decoded = decode(&encoded)?;
-                    let decode_time = start.elapsed();
-
-                    // Convert decoded pixels to sRGB, applying ICC profile if present.
-                    // This ensures accurate metric calculation for XYB JPEGs and other
-                    // images with embedded ICC profiles.
-                    let decoded_rgb = decoded.to_rgb8_srgb()?;
-                    let metrics =
-                        self.calculate_metrics(&reference_rgb, &decoded_rgb, width, height)?;
-
-                    report.results.push(CodecResult {
-                        codec_id: codec.id.clone(),
-                        codec_version: codec.version.clone(),
-                        quality,
-                        file_size: encoded.len(),
-                        bits_per_pixel: (encoded.len() * 8) as f64 / (width as f64 * height as f64),
-                        encode_time,
-                        decode_time: Some(decode_time),
-                        metrics: metrics.clone(),
-                        perception: metrics.perception_level(),
-                        cached_path: None,
-                        codec_params: request.params,
-                    });
-                    continue;
-                } else {
-                    // No decoder, just record file size
-                    MetricResult::default()
-                };
+        let progress = RunProgress::new(1, self.jobs().len());
+        self.install(|| self.evaluate_observed(name, &image, &progress))?
+    }
 
-                report.results.push(CodecResult {
-                    codec_id: codec.id.clone(),
-                    codec_version: codec.version.clone(),
-                    quality,
-                    file_size: encoded.len(),
-                    bits_per_pixel: (encoded.len() * 8) as f64 / (width as f64 * height as f64),
-                    encode_time,
-                    decode_time: None,
-                    metrics,
-                    perception: None,
-                    cached_path: None,
-                    codec_params: request.params,
-                });
-            }
-        }
+    /// Evaluate every image in a corpus across all registered codecs.
+    ///
+    /// The harness does not decode files itself: `load` is called with the full
+    /// path of each [`CorpusImage`](crate::corpus::CorpusImage) and must return
+    /// the decoded pixels. Images and their (codec, quality) jobs are scheduled
+    /// together on the session's thread pool (see [`EvalConfigBuilder::threads`]),
+    /// so a few slow images do not leave cores idle.
+    ///
+    /// Image reports appear in corpus order and results within each image in
+    /// codec registration order, then quality order, so repeated runs produce
+    /// identical reports apart from timings.
+    ///
+    /// Unless [`EvalConfig::fail_fast`] is set, an image that fails to load
+    /// is reported with zero dimensions and a single failure record.
+    ///
+    /// Observers see each image and job as it finishes, not in corpus order.
+    /// Cancelling the session returns [`Error::Cancelled`]; with the journal
+    /// enabled, a later run picks up where the cancelled one stopped.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let corpus = Corpus::discover("./images")?;
+    /// let report = session.evaluate_corpus(&corpus, |path| load_png(path))?;
+    /// session.write_corpus_report(&report)?;
+    /// ```
+    pub fn evaluate_corpus<F>(&self, corpus: &Corpus, load: F) -> Result<CorpusReport>
+    where
+        F: Fn(&Path) -> Result<ImageData> + Sync,
+    {
+        let journal = self.journal()?;
+        let jobs_per_image = self.jobs().len();
+        let progress = RunProgress::new(corpus.images.len(), jobs_per_image);
+        let images = self.install(|| {
+            corpus
+                .images
+                .par_iter()
+                .map(|entry| {
+                    if self.cancel.is_cancelled() {
+                        return Err(Error::Cancelled);
+                    }
+                    let path = entry.full_path(&corpus.root_path);
+                    // Fully journaled images are not loaded again.
+                    if let Some(mut report) =
+                        journal.and_then(|j| self.journaled_report(j, entry.name()))
+                    {
+                        report.source_path = Some(path);
+                        progress.jobs_finished(jobs_per_image, jobs_per_image);
+                        self.finish_image(&report, &progress);
+                        return Ok(report);
+                    }
+                    let mut report = match load(&path) {
+                        Ok(image) => self.evaluate_observed(entry.name(), &image, &progress)?,
+                        Err(error) => {
+                            let failure = FailureRecord::new("", "", None, &error);
+                            self.notify(|o| o.failure(entry.name(), &failure));
+                            if self.config.fail_fast {
+                                return Err(error);
+                            }
+                            // None of the image's jobs run; count them as done.
+                            progress.jobs_finished(jobs_per_image, jobs_per_image);
+                            let mut report = ImageReport::new(entry.name().to_string(), 0, 0);
+                            report.failures.push(failure);
+                            self.finish_image(&report, &progress);
+                            report
+                        }
+                    };
+                    report.source_path = Some(path);
+                    Ok(report)
+                })
+                .collect::<Result<Vec<_>>>()
+        })??;
 
+        let mut report = CorpusReport::new(corpus.name.clone());
+        report.images = images;
+        report.config_summary = self.config_summary();
         Ok(report)
     }
 
-    /// Calculate metrics between reference and test images.
-    fn calculate_metrics(
+    /// Evaluate one image, telling observers when it starts and finishes.
+    fn evaluate_observed(
         &self,
-        reference: &[u8],
-        test: &[u8],
-        width: u32,
-        height: u32,
-    ) -> Result<MetricResult> {
-        let mut result = MetricResult::default();
+        name: &str,
+        image: &ImageData,
+        progress: &RunProgress,
+    ) -> Result<ImageReport> {
+        self.notify(|o| o.image_started(name));
+        let report = self.evaluate_image_jobs(name, image, progress)?;
+        self.finish_image(&report, progress);
+        Ok(report)
+    }
 
-        // Apply XYB roundtrip to reference if enabled
-        let reference_for_metrics: std::borrow::Cow<'_, [u8]> = if self.config.metrics.xyb_roundtrip
-        {
-            std::borrow::Cow::Owned(crate::metrics::xyb_roundtrip(
-                reference,
-                width as usize,
-                height as usize,
-            ))
-        } else {
-            std::borrow::Cow::Borrowed(reference)
-        };
+    /// Count `report` as done and tell observers.
+    fn finish_image(&self, report: &ImageReport, progress: &RunProgress) {
+        progress.image_finished();
+        self.notify(|o| o.image_finished(report));
+        let snapshot = progress.snapshot();
+        self.notify(|o| o.progress(&snapshot));
+    }
 
-        if self.config.metrics.psnr {
-            result.psnr = Some(calculate_psnr(
-                &reference_for_metrics,
-                test,
-                width as usize,
-                height as usize,
-            ));
+    /// Call `event` on every observer.
+    fn notify(&self, event: impl Fn(&dyn EvalObserver)) {
+        for observer in &self.observers {
+            event(observer.as_ref());
         }
+    }
 
-        if self.config.metrics.dssim {
-            let ref_img =
-                rgb8_to_dssim_image(&reference_for_metrics, width as usize, height as usize);
-            let test_img = rgb8_to_dssim_image(test, width as usize, height as usize);
-            result.dssim = Some(crate::metrics::dssim::calculate_dssim(
-                &ref_img,
-                &test_img,
-                &self.config.viewing,
-            )?);
+    /// Run `op` on the configured thread pool.
+    ///
+    /// Without an explicit thread budget the caller's current rayon pool is
+    /// used, so nested calls from inside another pool stay on that pool.
+    ///
+    /// Memory measurement runs everything on a single thread.
+    pub(super) fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> Result<R> {
+        let threads = if self.config.measure_memory {
+            if !TrackingAllocator::is_installed() {
+                return Err(Error::Io(std::io::Error::other(
+                    "measure_memory requires TrackingAllocator as the #[global_allocator]",
+                )));
+            }
+            Some(1)
+        } else {
+            self.config.threads
+        };
+        match threads {
+            None => Ok(op()),
+            Some(threads) => {
+                let pool = rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .map_err(|e| Error::Io(std::io::Error::other(e)))?;
+                Ok(pool.install(op))
+            }
         }
+    }
 
-        if self.config.metrics.ssimulacra2 {
-            result.ssimulacra2 = Some(crate::metrics::ssimulacra2::calculate_ssimulacra2(
-                &reference_for_metrics,
-                test,
-                width as usize,
-                height as usize,
-            )?);
+    /// One-line description of the configuration, stored in corpus reports.
+    fn config_summary(&self) -> String {
+        let codecs: Vec<String> = self
+            .codecs
+            .iter()
+            .map(|c| format!("{}@{}", c.id, c.version))
+            .collect();
+        let settings = match &self.config.mode {
+            EvalMode::Sweep => format!("quality_levels={:?}", self.config.quality_levels),
+            EvalMode::TargetQuality(targets) => {
+                let targets: Vec<String> = targets
+                    .iter()
+                    .map(|t| format!("{}={}", t.metric, t.target))
+                    .collect();
+                format!("target_quality=[{}]", targets.join(", "))
+            }
+            EvalMode::TargetSize(targets) => {
+                let targets: Vec<String> = targets
+                    .iter()
+                    .map(|t| format!("{}{}", t.budget.value(), t.budget.unit()))
+                    .collect();
+                format!("target_size=[{}]", targets.join(", "))
+            }
+            EvalMode::GenerationLoss(loss) => format!(
+                "quality_levels={:?} {}",
+                self.config.quality_levels,
+                loss.summary()
+            ),
+        };
+        let mut params = String::new();
+        for (codec, grid) in &self.config.param_grids {
+            let _ = write!(params, " params[{codec}]={:?}", grid.params);
         }
+        format!(
+            "codecs=[{}] {settings} ppd={:.1}{params}",
+            codecs.join(", "),
+            self.config.viewing.effective_ppd(),
+        )
+    }
 
-        if self.config.metrics.butteraugli {
-            result.butteraugli = Some(crate::metrics::butteraugli::calculate_butteraugli(
-                &reference_for_metrics,
-                test,
-                width as usize,
-                height as usize,
+    /// Every job to run per image, in report order: codec registration
+    /// order, then parameter combination, then quality level or target.
+    fn jobs(&self) -> Vec<Job<'_>> {
+        let specs: Vec<JobSpec<'_>> = match &self.config.mode {
+            EvalMode::Sweep => self
+                .config
+                .quality_levels
+                .iter()
+                .map(|&quality| JobSpec::Sweep(quality))
+                .collect(),
+            EvalMode::TargetQuality(targets) => {
+                targets.iter().map(JobSpec::TargetQuality).collect()
+            }
+            EvalMode::TargetSize(targets) => targets.iter().map(JobSpec::TargetSize).collect(),
+            EvalMode::GenerationLoss(loss) => self
+                .config
+                .quality_levels
+                .iter()
+                .map(|&quality| JobSpec::Generations(quality, loss))
+                .collect(),
+        };
+        let mut jobs = Vec::new();
+        for codec in &self.codecs {
+            let combinations = self
+                .config
+                .param_grids
+                .get(&codec.id)
+                .map_or_else(|| vec![BTreeMap::new()], ParamGrid::combinations);
+            for params in combinations {
+                for &spec in &specs {
+                    jobs.push(Job {
+                        codec,
+                        params: params.clone(),
+                        spec,
+                    });
+                }
+            }
+        }
+        jobs
+    }
+
+    /// The results journal, if enabled.
+    fn journal(&self) -> Result<Option<&Journal>> {
+        if !self.config.journal {
+            return Ok(None);
+        }
+        if let Some(journal) = self.journal.get() {
+            return Ok(Some(journal));
+        }
+        let path = self.config.report_dir.join(JOURNAL_FILE);
+        let journal = Journal::open(&path, &self.settings_fingerprint())?;
+        // If another thread got there first, its handle wins; ours is dropped.
+        let _ = self.journal.set(journal);
+        Ok(self.journal.get())
+    }
+
+    /// Hash of the settings that affect results but not job identity, so a
+    /// journal is only reused under the same metrics, viewing conditions,
+    /// timing and memory measurement.
+    ///
+    /// Only the fields listed here are hashed, so new configuration fields
+    /// leave existing journals valid until they are added to the list, at
+    /// which point [`SETTINGS_FINGERPRINT_VERSION`] is bumped.
+    fn settings_fingerprint(&self) -> String {
+        let config = &self.config;
+        let metrics = &config.metrics;
+        let mut names: Vec<&str> = [
+            ("dssim", metrics.dssim),
+            ("ssimulacra2", metrics.ssimulacra2),
+            ("butteraugli", metrics.butteraugli),
+            ("psnr", metrics.psnr),
+            ("ssim", metrics.ssim),
+            ("ms_ssim", metrics.ms_ssim),
+            ("ciede2000", metrics.ciede2000),
+        ]
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect();
+        let mut custom: Vec<&str> = self.metrics.iter().map(|m| m.name()).collect();
+        custom.sort_unstable();
+        names.extend(custom);
+
+        let timing = &config.timing;
+        let mut hasher = Fnv1a::new();
+        for field in [
+            format!("v{SETTINGS_FINGERPRINT_VERSION}"),
+            names.join(","),
+            metrics.butteraugli_p.to_string(),
+            metrics.xyb_roundtrip.to_string(),
+            serde_json::to_string(metrics.effective_alpha_backgrounds()).unwrap_or_default(),
+            serde_json::to_string(&metrics.tiles).unwrap_or_default(),
+            serde_json::to_string(&config.regions).unwrap_or_default(),
+            serde_json::to_string(&config.viewing).unwrap_or_default(),
+            serde_json::to_string(&config.simulation_mode).unwrap_or_default(),
+            config.heatmaps.to_string(),
+            format!(
+                "{} {} {:?} {}",
+                timing.warmup, timing.repetitions, timing.statistic, timing.cpu_time
+            ),
+            config.measure_memory.to_string(),
+        ] {
+            hasher.update_field(field.as_bytes());
+        }
+        hasher.finish_hex()
+    }
+
+    /// Rebuild a report from the journal if every job of `name` is in it.
+    fn journaled_report(&self, journal: &Journal, name: &str) -> Option<ImageReport> {
+        let (width, height) = journal.dimensions(name)?;
+        let mut report = ImageReport::new(name.to_string(), width, height);
+        for job in self.jobs() {
+            report.results.push(journal.result(name, &job.key())?);
+        }
+        Some(report)
+    }
+
+    /// Evaluate one image in parallel on the current pool.
+    ///
+    /// Failed jobs are recorded in [`ImageReport::failures`] unless
+    /// `fail_fast` is set, in which case the first error is returned. With
+    /// the journal enabled, journaled jobs are reused and new results are
+    /// appended as they finish. Observers hear about each job as it finishes.
+    fn evaluate_image_jobs(
+        &self,
+        name: &str,
+        image: &ImageData,
+        progress: &RunProgress,
+    ) -> Result<ImageReport> {
+        let width = image.width() as u32;
+        let height = image.height() as u32;
+        let mut report = ImageReport::new(name.to_string(), width, height);
+
+        let journal = self.journal()?;
+        if let Some(journal) = journal {
+            journal.record_image(name, width, height)?;
+        }
+
+        let prepared = self.prepare(name, image);
+        let jobs = self.jobs();
+
+        let compute = |job: &Job<'_>, key: Option<&String>| {
+            let result = match job.spec {
+                JobSpec::Sweep(quality) => self.run_job(job.codec, job.request(quality), &prepared),
+                JobSpec::TargetQuality(target) => self.search_job(job, target, &prepared),
+                JobSpec::TargetSize(target) => self.size_search_job(job, target, &prepared),
+                JobSpec::Generations(quality, loss) => {
+                    self.generation_job(job, quality, loss, &prepared)
+                }
+            }?;
+            if let (Some(journal), Some(key)) = (journal, key) {
+                journal.record_result(name, key, &result)?;
+            }
+            Ok(result)
+        };
+        let run = |job: &Job<'_>| {
+            if self.cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            let key = journal.map(|_| job.key());
+            if let (Some(journal), Some(key)) = (journal, &key)
+                && let Some(result) = journal.result(name, key)
+            {
+                let snapshot = progress.jobs_finished(1, 1);
+                self.notify(|o| o.progress(&snapshot));
+                return Ok(result);
+            }
+            let outcome = compute(job, key.as_ref());
+            match &outcome {
+                Ok(result) => self.notify(|o| o.result(name, result)),
+                Err(error) => {
+                    let failure = job.failure(error);
+                    self.notify(|o| o.failure(name, &failure));
+                }
+            }
+            let snapshot = progress.jobs_finished(1, 0);
+            self.notify(|o| o.progress(&snapshot));
+            outcome
+        };
+
+        // Indexed parallel collect keeps job order, so the report is deterministic.
+        let outcomes: Vec<Result<CodecResult>> = if self.config.fail_fast {
+            jobs.par_iter()
+                .map(run)
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .map(Ok)
+                .collect()
+        } else {
+            jobs.par_iter().map(run).collect()
+        };
+        if outcomes
+            .iter()
+            .any(|outcome| matches!(outcome, Err(Error::Cancelled)))
+        {
+            return Err(Error::Cancelled);
+        }
+
+        for (job, outcome) in jobs.iter().zip(outcomes) {
+            match outcome {
+                Ok(result) => report.results.push(result),
+                Err(error) => report.failures.push(job.failure(&error)),
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Encode, decode and score a single (codec, request) combination.
+    fn run_job(
+        &self,
+        codec: &CodecEntry,
+        request: EncodeRequest,
+        prepared: &PreparedImage<'_>,
+    ) -> Result<CodecResult> {
+        let (mut result, maps) = self.evaluate_request(codec, request, prepared)?;
+        if self.config.heatmaps {
+            result.heatmaps = self.write_heatmaps(prepared.name, &result, &maps)?;
+        }
+        Ok(result)
+    }
+
+    /// Search the quality setting of the job's codec for `target`.
+    ///
+    /// Every probe goes through the encode cache; only the reported result
+    /// gets heatmaps.
+    fn search_job(
+        &self,
+        job: &Job<'_>,
+        target: &QualityTarget,
+        prepared: &PreparedImage<'_>,
+    ) -> Result<CodecResult> {
+        let codec = job.codec;
+        codec.decoder("target-quality search")?;
+        let direction =
+            self.metric_direction(&target.metric)
+                .ok_or_else(|| Error::MetricCalculation {
+                    metric: target.metric.clone(),
+                    reason: "unknown metric".to_string(),
+                })?;
+
+        let search = bisect(
+            target.quality_range,
+            target.precision,
+            target.max_encodes,
+            Ordering::Greater,
+            |quality| {
+                let scored = self.evaluate_request(codec, job.request(quality), prepared)?;
+                let score = scored.0.metrics.value(&target.metric).ok_or_else(|| {
+                    Error::MetricCalculation {
+                        metric: target.metric.clone(),
+                        reason: "not computed; enable it in MetricConfig".to_string(),
+                    }
+                })?;
+                let ordering = if (score - target.target).abs() <= target.tolerance {
+                    Ordering::Equal
+                } else {
+                    direction
+                        .higher_is_better(score)
+                        .total_cmp(&direction.higher_is_better(target.target))
+                };
+                Ok((ordering, (scored, score)))
+            },
+        )?;
+
+        let ((mut result, maps), achieved) = search.value;
+        result.search = Some(SearchInfo {
+            metric: target.metric.clone(),
+            target: target.target,
+            achieved,
+            encodes: search.encodes,
+            converged: search.converged,
+        });
+        if self.config.heatmaps {
+            result.heatmaps = self.write_heatmaps(prepared.name, &result, &maps)?;
+        }
+        Ok(result)
+    }
+
+    /// Search the highest quality setting of the job's codec that fits `target`.
+    ///
+    /// Probes are only encoded; the reported encode is decoded and scored
+    /// once the search is done.
+    fn size_search_job(
+        &self,
+        job: &Job<'_>,
+        target: &SizeTarget,
+        prepared: &PreparedImage<'_>,
+    ) -> Result<CodecResult> {
+        let codec = job.codec;
+        let max_bytes = target
+            .budget
+            .max_bytes(prepared.image.width(), prepared.image.height());
+
+        let search = bisect(
+            target.quality_range,
+            target.precision,
+            target.max_encodes,
+            Ordering::Less,
+            |quality| {
+                let encoded = self.encode_request(codec, job.request(quality), prepared)?;
+                Ok((encoded.0.file_size.cmp(&max_bytes), encoded))
+            },
+        )?;
+
+        let (mut result, encoded) = search.value;
+        let maps = self.decode_and_score(codec, &mut result, &encoded, prepared)?;
+        result.search = Some(SearchInfo {
+            metric: target.budget.unit().to_string(),
+            target: target.budget.value(),
+            achieved: match target.budget {
+                SizeBudget::Bytes(_) => result.file_size as f64,
+                SizeBudget::Bpp(_) => result.bits_per_pixel,
+            },
+            encodes: search.encodes,
+            converged: search.converged,
+        });
+        if self.config.heatmaps {
+            result.heatmaps = self.write_heatmaps(prepared.name, &result, &maps)?;
+        }
+        Ok(result)
+    }
+
+    /// Encode at `quality`, then re-encode the decoded output for the
+    /// remaining generations of `loss`.
+    ///
+    /// The first generation is the reported result and goes through the
+    /// encode cache; later generations are encoded from decoded pixels and
+    /// are not cached. Every generation is scored against the original.
+    fn generation_job(
+        &self,
+        job: &Job<'_>,
+        quality: f64,
+        loss: &GenerationLoss,
+        prepared: &PreparedImage<'_>,
+    ) -> Result<CodecResult> {
+        let codec = job.codec;
+        const PURPOSE: &str = "generation-loss evaluation";
+        codec.decoder(PURPOSE)?;
+
+        // Resolve the whole chain before encoding anything.
+        let mut steps = Vec::with_capacity(loss.generations.saturating_sub(1));
+        for generation in 2..=loss.generations {
+            let (entry, request) = match loss.step(generation) {
+                Some(step) => {
+                    let chained = self
+                        .codecs
+                        .iter()
+                        .find(|entry| entry.id == step.codec_id)
+                        .ok_or_else(|| Error::Codec {
+                            codec: step.codec_id.clone(),
+                            message: "generation-loss chain codec is not registered".to_string(),
+                        })?;
+                    (chained, EncodeRequest::new(step.quality))
+                }
+                None => (codec, job.request(quality)),
+            };
+            steps.push((entry, request, entry.decoder(PURPOSE)?));
+        }
+
+        let pixels = (prepared.image.width() * prepared.image.height()) as f64;
+        let (mut result, encoded) = self.encode_request(codec, job.request(quality), prepared)?;
+        let Some(mut decoded) = self.decode(codec, &mut result, &encoded)? else {
+            unreachable!("decoder checked above");
+        };
+        let maps;
+        (result.metrics, maps) = self.score_decoded(prepared, &decoded)?;
+        result.perception = result.metrics.perception_level();
+
+        let mut generations = vec![GenerationResult {
+            generation: 1,
+            codec_id: codec.id.clone(),
+            quality,
+            file_size: result.file_size,
+            bits_per_pixel: result.bits_per_pixel,
+            metrics: result.metrics.clone(),
+        }];
+        for (generation, (entry, request, decode)) in (2..).zip(&steps) {
+            let encoded = (entry.encode)(&decoded, request)?;
+            decoded = decode(&encoded)?;
+            let (metrics, _) = self.score_decoded(prepared, &decoded)?;
+            generations.push(GenerationResult {
+                generation,
+                codec_id: entry.id.clone(),
+                quality: request.quality,
+                file_size: encoded.len(),
+                bits_per_pixel: (encoded.len() * 8) as f64 / pixels,
+                metrics,
+            });
+        }
+        result.generations = generations;
+
+        if self.config.heatmaps {
+            result.heatmaps = self.write_heatmaps(prepared.name, &result, &maps)?;
+        }
+        Ok(result)
+    }
+
+    /// Encode, decode and score a request, returning any distortion maps.
+    fn evaluate_request(
+        &self,
+        codec: &CodecEntry,
+        request: EncodeRequest,
+        prepared: &PreparedImage<'_>,
+    ) -> Result<(CodecResult, DistortionMaps)> {
+        let (mut result, encoded) = self.encode_request(codec, request, prepared)?;
+        let maps = self.decode_and_score(codec, &mut result, &encoded, prepared)?;
+        Ok((result, maps))
+    }
+
+    /// Encode a request into an unscored result plus the bitstream.
+    fn encode_request(
+        &self,
+        codec: &CodecEntry,
+        request: EncodeRequest,
+        prepared: &PreparedImage<'_>,
+    ) -> Result<(CodecResult, Vec<u8>)> {
+        let width = prepared.image.width() as u32;
+        let height = prepared.image.height() as u32;
+
+        let Encoded {
+            data: encoded,
+            timing: encode_timing,
+            peak_bytes,
+            cached_path,
+        } = self.encode(codec, &request, prepared)?;
+
+        let result = CodecResult {
+            codec_id: codec.id.clone(),
+            codec_version: codec.version.clone(),
+            quality: request.quality,
+            file_size: encoded.len(),
+            bits_per_pixel: (encoded.len() * 8) as f64 / (f64::from(width) * f64::from(height)),
+            encode_time: encode_timing.wall_time(self.config.timing.statistic),
+            decode_time: None,
+            encode_timing: Some(encode_timing),
+            decode_timing: None,
+            encode_peak_bytes: peak_bytes,
+            decode_peak_bytes: None,
+            metrics: MetricResult::default(),
+            perception: None,
+            cached_path,
+            codec_params: request.params,
+            heatmaps: BTreeMap::new(),
+            search: None,
+            generations: Vec::new(),
+        };
+        Ok((result, encoded))
+    }
+
+    /// Decode `encoded` and fill in the metrics of `result`.
+    ///
+    /// Without a decoder, only file size is recorded.
+    fn decode_and_score(
+        &self,
+        codec: &CodecEntry,
+        result: &mut CodecResult,
+        encoded: &[u8],
+        prepared: &PreparedImage<'_>,
+    ) -> Result<DistortionMaps> {
+        let Some(decoded) = self.decode(codec, result, encoded)? else {
+            return Ok(DistortionMaps::new());
+        };
+        let (metrics, maps) = self.score_decoded(prepared, &decoded)?;
+        result.metrics = metrics;
+        result.perception = result.metrics.perception_level();
+        Ok(maps)
+    }
+
+    /// Decode `encoded`, recording decode time and memory in `result`.
+    ///
+    /// Returns `None` when the codec has no decoder.
+    fn decode(
+        &self,
+        codec: &CodecEntry,
+        result: &mut CodecResult,
+        encoded: &[u8],
+    ) -> Result<Option<ImageData>> {
+        let Some(ref decode) = codec.decode else {
+            return Ok(None);
+        };
+        let (decoded, timing, peak_bytes) = self.run_timed(|| decode(encoded))?;
+        result.decode_time = Some(timing.wall_time(self.config.timing.statistic));
+        result.decode_timing = Some(timing);
+        result.decode_peak_bytes = peak_bytes;
+        Ok(Some(decoded))
+    }
+
+    /// Score a decoded image against the prepared reference.
+    pub(super) fn score_decoded(
+        &self,
+        prepared: &PreparedImage<'_>,
+        decoded: &ImageData,
+    ) -> Result<(MetricResult, DistortionMaps)> {
+        let expected = (prepared.image.width(), prepared.image.height());
+        let actual = (decoded.width(), decoded.height());
+        if actual != expected {
+            return Err(Error::DimensionMismatch { expected, actual });
+        }
+
+        if prepared.image.is_high_precision() || decoded.is_high_precision() {
+            self.score_precise(prepared, decoded)
+        } else {
+            // Convert decoded pixels to sRGB, applying ICC profile if present.
+            // This ensures accurate metric calculation for XYB JPEGs and other
+            // images with embedded ICC profiles.
+            let decoded_rgb = decoded.to_rgb8_srgb()?;
+            let decoded_alpha = decoded.alpha_vec();
+            self.score(prepared, &decoded_rgb, decoded_alpha.as_deref())
+        }
+    }
+
+    /// Encode through the cache when enabled, timed per `EvalConfig::timing`.
+    ///
+    /// On a cache hit the timing and peak memory recorded when the entry was
+    /// written are reported, so they stay comparable across cached and fresh
+    /// runs. An entry timed under a different policy (number of runs, CPU
+    /// time), or without a peak when `EvalConfig::measure_memory` is set, is
+    /// encoded afresh and replaced.
+    fn encode(
+        &self,
+        codec: &CodecEntry,
+        request: &EncodeRequest,
+        prepared: &PreparedImage<'_>,
+    ) -> Result<Encoded> {
+        let slot = match (&self.cache, &prepared.checksum) {
+            (Some(cache), Some(checksum)) => {
+                Some(cache.slot(checksum, &codec.id, &codec.version, request))
+            }
+            _ => None,
+        };
+
+        if let Some(slot) = &slot
+            && let Some(hit) = slot.load()?
+        {
+            // Entries from before repeated timing only have the one time.
+            let timing = hit.encode_timing.unwrap_or_else(|| Timing {
+                samples: 1,
+                wall: TimingSummary::from_samples(&[hit.encode_time]),
+                cpu: None,
+            });
+            let measured = hit.encode_peak_bytes.is_some() || !self.config.measure_memory;
+            if self.config.timing.produced(&timing) && measured {
+                return Ok(Encoded {
+                    data: hit.data,
+                    timing,
+                    peak_bytes: hit.encode_peak_bytes.filter(|_| self.config.measure_memory),
+                    cached_path: Some(slot.path().to_path_buf()),
+                });
+            }
+        }
+
+        let (data, timing, peak_bytes) =
+            self.run_timed(|| (codec.encode)(prepared.image, request))?;
+
+        let cached_path = match slot {
+            Some(slot) => {
+                let encode_time = timing.wall_time(self.config.timing.statistic);
+                slot.store(&data, encode_time, &timing, peak_bytes)?;
+                Some(slot.path().to_path_buf())
+            }
+            None => None,
+        };
+        Ok(Encoded {
+            data,
+            timing,
+            peak_bytes,
+            cached_path,
+        })
+    }
+
+    /// Run a codec callback per `EvalConfig::timing`, also recording its peak
+    /// heap growth (the largest over the timed runs) when
+    /// `EvalConfig::measure_memory` is set.
+    fn run_timed<T>(&self, mut f: impl FnMut() -> Result<T>) -> Result<(T, Timing, Option<u64>)> {
+        if !self.config.measure_memory {
+            let (output, timing) = self.config.timing.measure(f)?;
+            return Ok((output, timing, None));
+        }
+        let mut peak_bytes = 0;
+        let (output, timing) = self.config.timing.measure(|| {
+            let (output, bytes) = memory::measure_peak(&mut f);
+            peak_bytes = peak_bytes.max(bytes);
+            output
+        })?;
+        Ok((output, timing, Some(peak_bytes)))
+    }
+
+    /// Prepare the per-image state shared by every job.
+    ///
+    /// Composites translucent references over each alpha background, applies
+    /// the optional XYB roundtrip at native resolution, then the viewing
+    /// simulation. Done once per image.
+    pub(super) fn prepare<'a>(&self, name: &'a str, image: &'a ImageData) -> PreparedImage<'a> {
+        let (width, height) = (image.width(), image.height());
+        let simulation = self.config.viewing.simulation_params(
+            width as u32,
+            height as u32,
+            self.config.simulation_mode,
+        );
+
+        let reference_alpha = image.alpha_vec();
+        let (tile_layout, areas) = self.local_areas(name, width as u32, height as u32, &simulation);
+        let references = if image.is_high_precision() {
+            Vec::new()
+        } else {
+            self.prepare_references(image, reference_alpha.as_deref(), &simulation, &areas)
+        };
+
+        PreparedImage {
+            name,
+            image,
+            references,
+            reference_alpha,
+            simulation,
+            checksum: self.cache.as_ref().map(|_| image.content_checksum()),
+            areas,
+            tile_layout,
+            precise: OnceLock::new(),
+        }
+    }
+
+    /// Composite, XYB-roundtrip and simulate an 8-bit reference per alpha
+    /// background, then precompute its metric state for the whole image and
+    /// each local area.
+    fn prepare_references(
+        &self,
+        image: &ImageData,
+        alpha: Option<&[u8]>,
+        simulation: &SimulationParams,
+        areas: &[LocalArea],
+    ) -> Vec<PreparedReference> {
+        let (width, height) = (image.width(), image.height());
+        let rgb = image.to_rgb8_vec();
+        let composited: Vec<Vec<u8>> = match alpha {
+            Some(alpha) => self
+                .config
+                .metrics
+                .effective_alpha_backgrounds()
+                .iter()
+                .map(|&bg| composite_over(&rgb, Some(alpha), width, bg))
+                .collect(),
+            None => vec![rgb],
+        };
+
+        let config = &self.config.metrics;
+        let (target_width, target_height) = (
+            simulation.target_width as usize,
+            simulation.target_height as usize,
+        );
+        composited
+            .into_iter()
+            .map(|mut reference| {
+                if config.xyb_roundtrip {
+                    reference = crate::metrics::xyb_roundtrip(&reference, width, height);
+                }
+                if simulation.requires_scaling() {
+                    reference = simulation.apply_rgb8(&reference, width, height);
+                }
+                let areas = areas
+                    .iter()
+                    .map(|area| {
+                        let rect = area.scaled;
+                        area.scorable.then(|| {
+                            ReferenceContext::new(
+                                rect.crop(&reference, target_width, 3),
+                                rect.width as usize,
+                                rect.height as usize,
+                                config,
+                            )
+                        })
+                    })
+                    .collect();
+                PreparedReference {
+                    image: ReferenceContext::new(reference, target_width, target_height, config),
+                    areas,
+                }
+            })
+            .collect()
+    }
+
+    /// Grid tiles and the image's regions of interest, clipped to the image.
+    fn local_areas(
+        &self,
+        name: &str,
+        width: u32,
+        height: u32,
+        simulation: &SimulationParams,
+    ) -> ((u32, u32), Vec<LocalArea>) {
+        let target = (simulation.target_width, simulation.target_height);
+        let area = |name: Option<String>, rect: Rect| {
+            let rect = rect.clamp(width, height);
+            let scaled = rect.scale((width, height), target);
+            LocalArea {
+                name,
+                rect,
+                scaled,
+                scorable: scaled.width >= MIN_AREA_SIDE && scaled.height >= MIN_AREA_SIDE,
+            }
+        };
+        let mut areas = Vec::new();
+        let mut layout = (0, 0);
+        if let Some(grid) = self.config.metrics.tiles {
+            layout = grid.layout(width, height);
+            areas.extend(grid.tiles(width, height).into_iter().map(|r| area(None, r)));
+        }
+        for region in self.config.regions.get(name).into_iter().flatten() {
+            areas.push(area(Some(region.name.clone()), region.rect));
+        }
+        (layout, areas)
+    }
+
+    /// Score every tile and region of `prepared` with `score_area`, given
+    /// each area's index and simulated rectangle, and attach them to
+    /// `metrics`. Areas too small to score keep empty metrics.
+    fn score_areas(
+        &self,
+        prepared: &PreparedImage<'_>,
+        metrics: &mut MetricResult,
+        score_area: impl Fn(usize, Rect) -> Result<MetricResult>,
+    ) -> Result<()> {
+        if prepared.areas.is_empty() {
+            return Ok(());
+        }
+        let mut tiles = Vec::new();
+        let mut regions = Vec::new();
+        for (i, area) in prepared.areas.iter().enumerate() {
+            let score = TileScore {
+                name: area.name.clone(),
+                rect: area.rect,
+                metrics: if area.scorable {
+                    score_area(i, area.scaled)?
+                } else {
+                    MetricResult::default()
+                },
+            };
+            if score.name.is_some() {
+                regions.push(score);
+            } else {
+                tiles.push(score);
+            }
+        }
+        metrics.tiles = Some(Box::new(TileScores::new(
+            prepared.tile_layout,
+            tiles,
+            regions,
+            |name| self.metric_direction(name),
+        )));
+        Ok(())
+    }
+
+    /// Score decoded pixels against the prepared reference.
+    ///
+    /// If either side has an alpha channel, both are composited over every
+    /// configured background and each metric keeps its worst score (and
+    /// distortion maps their per-pixel maximum); the alpha planes are compared
+    /// separately.
+    fn score(
+        &self,
+        prepared: &PreparedImage<'_>,
+        decoded_rgb: &[u8],
+        decoded_alpha: Option<&[u8]>,
+    ) -> Result<(MetricResult, DistortionMaps)> {
+        let (width, height) = (prepared.image.width(), prepared.image.height());
+        let sim = &prepared.simulation;
+
+        let score_all = |reference: &PreparedReference, test: &[u8]| {
+            let (mut metrics, maps) = self.calculate_metrics(&reference.image, test)?;
+            let stride = sim.target_width as usize;
+            self.score_areas(prepared, &mut metrics, |i, rect| {
+                let test = rect.crop(test, stride, 3);
+                let context = reference.areas[i]
+                    .as_ref()
+                    .expect("only scorable areas are scored");
+                let (metrics, _) = self.calculate_metrics(context, &test)?;
+                Ok(metrics)
+            })?;
+            Ok((metrics, maps))
+        };
+
+        if prepared.reference_alpha.is_none() && decoded_alpha.is_none() {
+            let test = sim.apply_rgb8(decoded_rgb, width, height);
+            return score_all(&prepared.references[0], &test);
+        }
+
+        let mut result: Option<(MetricResult, DistortionMaps)> = None;
+        let backgrounds = self.config.metrics.effective_alpha_backgrounds();
+        for (i, &background) in backgrounds.iter().enumerate() {
+            // An opaque reference looks the same over every background.
+            let reference = prepared
+                .references
+                .get(i)
+                .unwrap_or(&prepared.references[0]);
+            let test = composite_over(decoded_rgb, decoded_alpha, width, background);
+            let test = sim.apply_rgb8(&test, width, height);
+            let scored = score_all(reference, &test)?;
+            self.merge_worst(&mut result, scored);
+        }
+
+        let (mut result, maps) = result.unwrap_or_default();
+        result.alpha_rmse = Some(alpha_rmse(
+            prepared.reference_alpha.as_deref(),
+            decoded_alpha,
+            width * height,
+        ));
+        Ok((result, maps))
+    }
+
+    /// Score a decoded image without quantizing either side to 8 bits.
+    ///
+    /// Used when the reference or the decoded image is high bit depth or HDR.
+    /// Compositing happens on code values, then both images are converted to
+    /// linear light per their [`TransferFunction`] before viewing simulation.
+    /// The XYB roundtrip option only applies to the 8-bit path. PSNR is
+    /// computed on code values and only when both transfer functions match.
+    fn score_precise(
+        &self,
+        prepared: &PreparedImage<'_>,
+        decoded: &ImageData,
+    ) -> Result<(MetricResult, DistortionMaps)> {
+        let (width, height) = (prepared.image.width(), prepared.image.height());
+        let reference_transfer = prepared.image.transfer_function();
+        let references = prepared.precise.get_or_init(|| {
+            let signal = prepared.image.to_rgb_f32_signal();
+            let alpha = prepared.image.alpha_f32();
+            let backgrounds = match alpha {
+                Some(_) => self.config.metrics.effective_alpha_backgrounds(),
+                None => &[crate::metrics::AlphaBackground::Black],
+            };
+            backgrounds
+                .iter()
+                .map(|&bg| {
+                    let composited = composite_over_f32(
+                        &signal,
+                        alpha.as_deref(),
+                        width,
+                        bg,
+                        reference_transfer,
+                    );
+                    self.simulate_precise(composited, reference_transfer, prepared)
+                })
+                .collect()
+        });
+
+        // ICC-tagged decodes are converted to sRGB first, as in the 8-bit path.
+        let (test_signal, test_transfer) = if decoded.icc_profile().is_some() {
+            let rgb = decoded.to_rgb8_srgb()?;
+            let signal = rgb.into_iter().map(|v| f32::from(v) / 255.0).collect();
+            (signal, TransferFunction::Srgb)
+        } else {
+            (decoded.to_rgb_f32_signal(), decoded.transfer_function())
+        };
+        let test_alpha = decoded.alpha_f32();
+
+        let mut result: Option<(MetricResult, DistortionMaps)> = None;
+        let backgrounds = if prepared.reference_alpha.is_none() && test_alpha.is_none() {
+            &[crate::metrics::AlphaBackground::Black]
+        } else {
+            self.config.metrics.effective_alpha_backgrounds()
+        };
+        for (i, &background) in backgrounds.iter().enumerate() {
+            // An opaque reference looks the same over every background.
+            let reference = references.get(i).unwrap_or(&references[0]);
+            let test = composite_over_f32(
+                &test_signal,
+                test_alpha.as_deref(),
+                width,
+                background,
+                test_transfer,
+            );
+            let test = self.simulate_precise(test, test_transfer, prepared);
+            let sim = &prepared.simulation;
+            let (mut metrics, maps) = self.calculate_metrics_precise(
+                (reference, reference_transfer),
+                (&test, test_transfer),
+                sim.target_width as usize,
+                sim.target_height as usize,
+            )?;
+            let stride = sim.target_width as usize;
+            self.score_areas(prepared, &mut metrics, |_, rect| {
+                let crop = |image: &PreciseImage| PreciseImage {
+                    signal: rect.crop(&image.signal, stride, 3),
+                    linear: rect.crop(&image.linear, stride, 3),
+                };
+                let (metrics, _) = self.calculate_metrics_precise(
+                    (&crop(reference), reference_transfer),
+                    (&crop(&test), test_transfer),
+                    rect.width as usize,
+                    rect.height as usize,
+                )?;
+                Ok(metrics)
+            })?;
+            self.merge_worst(&mut result, (metrics, maps));
+        }
+
+        let (mut result, maps) = result.unwrap_or_default();
+        if prepared.reference_alpha.is_some() || test_alpha.is_some() {
+            result.alpha_rmse = Some(alpha_rmse(
+                prepared.reference_alpha.as_deref(),
+                decoded.alpha_vec().as_deref(),
+                width * height,
+            ));
+        }
+        Ok((result, maps))
+    }
+
+    /// Fold one background's scores into the worst-so-far.
+    fn merge_worst(
+        &self,
+        worst: &mut Option<(MetricResult, DistortionMaps)>,
+        (metrics, maps): (MetricResult, DistortionMaps),
+    ) {
+        let Some((worst_metrics, worst_maps)) = worst else {
+            *worst = Some((metrics, maps));
+            return;
+        };
+        worst_metrics.merge_worst(&metrics, |name| self.metric_direction(name));
+        for (name, map) in maps {
+            match worst_maps.get_mut(&name) {
+                Some(existing) => existing.merge_max(&map),
+                None => {
+                    worst_maps.insert(name, map);
+                }
+            }
+        }
+    }
+
+    /// Write distortion maps as false-colour PNGs and return their paths.
+    #[cfg(feature = "heatmap")]
+    fn write_heatmaps(
+        &self,
+        image_name: &str,
+        result: &CodecResult,
+        maps: &DistortionMaps,
+    ) -> Result<BTreeMap<String, PathBuf>> {
+        if maps.is_empty() {
+            return Ok(BTreeMap::new());
+        }
+        let dir = self
+            .config
+            .report_dir
+            .join("heatmaps")
+            .join(sanitize(image_name));
+        std::fs::create_dir_all(&dir)?;
+
+        let mut paths = BTreeMap::new();
+        for (metric, map) in maps {
+            let mut codec = result.codec_id.clone();
+            if !result.codec_params.is_empty() {
+                let params: BTreeMap<&String, &String> = result.codec_params.iter().collect();
+                codec = format!("{codec}-{}", format_params(params));
+            }
+            let path = dir.join(format!(
+                "{}-q{}-{}.png",
+                sanitize(&codec),
+                result.quality,
+                sanitize(metric)
+            ));
+            let max = heatmap_scale(metric).unwrap_or_else(|| map.max_value());
+            map.write_png(&path, max)?;
+            paths.insert(metric.clone(), path);
+        }
+        Ok(paths)
+    }
+
+    #[cfg(not(feature = "heatmap"))]
+    #[allow(clippy::unused_self)]
+    fn write_heatmaps(
+        &self,
+        _image_name: &str,
+        _result: &CodecResult,
+        _maps: &DistortionMaps,
+    ) -> Result<BTreeMap<String, PathBuf>> {
+        Err(Error::UnsupportedFormat(
+            "EvalConfig::heatmaps requires the `heatmap` feature".to_string(),
+        ))
+    }
+
+    /// Linearize normalized code values and apply the viewing simulation.
+    fn simulate_precise(
+        &self,
+        signal: Vec<f32>,
+        transfer: TransferFunction,
+        prepared: &PreparedImage<'_>,
+    ) -> PreciseImage {
+        let sim = &prepared.simulation;
+        let linear = linearize(&signal, transfer);
+        if !sim.requires_scaling() {
+            return PreciseImage { signal, linear };
+        }
+
+        let linear = resample_linear(
+            &linear,
+            3,
+            prepared.image.width(),
+            prepared.image.height(),
+            sim.target_width as usize,
+            sim.target_height as usize,
+        );
+        let signal = linear.iter().map(|&v| transfer.from_linear(v)).collect();
+        PreciseImage { signal, linear }
+    }
+
+    /// Calculate metrics between high-precision reference and test images.
+    ///
+    /// Both images must already have the viewing simulation applied.
+    fn calculate_metrics_precise(
+        &self,
+        (reference, reference_transfer): (&PreciseImage, TransferFunction),
+        (test, test_transfer): (&PreciseImage, TransferFunction),
+        width: usize,
+        height: usize,
+    ) -> Result<(MetricResult, DistortionMaps)> {
+        let mut result = MetricResult::default();
+        let mut maps = DistortionMaps::new();
+
+        if self.config.metrics.psnr && reference_transfer == test_transfer {
+            result.psnr = Some(calculate_psnr_f32(
+                &reference.signal,
+                &test.signal,
+                width,
+                height,
+            ));
+        }
+
+        // Like PSNR, SSIM compares code values, so only within one transfer
+        // function.
+        if reference_transfer == test_transfer {
+            if self.config.metrics.ssim {
+                result.ssim = Some(ssim::calculate_ssim_f32(
+                    &reference.signal,
+                    &test.signal,
+                    width,
+                    height,
+                )?);
+            }
+            if self.config.metrics.ms_ssim {
+                result.ms_ssim = Some(ssim::calculate_ms_ssim_f32(
+                    &reference.signal,
+                    &test.signal,
+                    width,
+                    height,
+                )?);
+            }
+        }
+
+        if self.config.metrics.ciede2000 {
+            let (scores, map) = ciede2000::calculate_ciede2000_linear(
+                &reference.linear,
+                &test.linear,
+                width,
+                height,
+            )?;
+            self.record_ciede2000(&mut result, &mut maps, scores, map);
+        }
+
+        if self.config.metrics.dssim {
+            // DSSIM expects display-relative light in 0.0-1.0, so HDR content
+            // is scaled by the brighter transfer function's peak.
+            let scale = 1.0
+                / reference_transfer
+                    .peak_linear()
+                    .max(test_transfer.peak_linear());
+            let to_dssim = |linear: &[f32]| {
+                let pixels = linear
+                    .chunks_exact(3)
+                    .map(|p| {
+                        let [r, g, b] = [p[0], p[1], p[2]].map(|v| (v * scale).min(1.0));
+                        RGBA::new(r, g, b, 1.0)
+                    })
+                    .collect();
+                ImgVec::new(pixels, width, height)
+            };
+            result.dssim = Some(self.compare_dssim(
+                &to_dssim(&reference.linear),
+                &to_dssim(&test.linear),
+                &mut maps,
+            )?);
+        }
+
+        if self.config.metrics.ssimulacra2 {
+            result.ssimulacra2 = Some(crate::metrics::ssimulacra2::calculate_ssimulacra2_linear(
+                &reference.linear,
+                &test.linear,
+                width,
+                height,
             )?);
         }
 
-        Ok(result)
+        if self.config.metrics.butteraugli {
+            // Linear 1.0 is SDR white: 203 cd/m² alongside HDR content,
+            // otherwise butteraugli's default SDR display.
+            let intensity_target = if reference_transfer.is_hdr() || test_transfer.is_hdr() {
+                SDR_WHITE_NITS
+            } else {
+                80.0
+            };
+            let (scores, map) = butteraugli::calculate_butteraugli_linear_scores(
+                &reference.linear,
+                &test.linear,
+                width,
+                height,
+                intensity_target,
+                self.config.metrics.butteraugli_p,
+            )?;
+            self.record_butteraugli(&mut result, &mut maps, scores, map);
+        }
+
+        // Custom metrics take 8-bit sRGB, so they see quantized code values.
+        if !self.metrics.is_empty() {
+            let quantize = |image: &PreciseImage| -> Vec<u8> {
+                image.signal.iter().map(|&v| quantize_u8(v)).collect()
+            };
+            self.calculate_custom_metrics(
+                &mut result,
+                &mut maps,
+                &quantize(reference),
+                &quantize(test),
+                width,
+                height,
+            )?;
+        }
+
+        Ok((result, maps))
+    }
+
+    /// Calculate metrics between a prepared reference and a test image.
+    ///
+    /// The test image must already have the viewing simulation applied.
+    fn calculate_metrics(
+        &self,
+        reference: &ReferenceContext,
+        test: &[u8],
+    ) -> Result<(MetricResult, DistortionMaps)> {
+        let (mut result, mut maps) = if self.config.heatmaps {
+            reference.compare_with_maps(test)?
+        } else {
+            (reference.compare(test)?, DistortionMaps::new())
+        };
+
+        self.calculate_custom_metrics(
+            &mut result,
+            &mut maps,
+            reference.reference(),
+            test,
+            reference.width(),
+            reference.height(),
+        )?;
+
+        Ok((result, maps))
+    }
+
+    /// Run registered custom metrics on 8-bit sRGB images.
+    fn calculate_custom_metrics(
+        &self,
+        result: &mut MetricResult,
+        maps: &mut DistortionMaps,
+        reference: &[u8],
+        test: &[u8],
+        width: usize,
+        height: usize,
+    ) -> Result<()> {
+        if self.metrics.is_empty() {
+            return Ok(());
+        }
+        let reference = ImgRef::new(reference.as_rgb(), width, height);
+        let test = ImgRef::new(test.as_rgb(), width, height);
+        for metric in &self.metrics {
+            let value = metric.compute(reference, test)?;
+            result.custom.insert(metric.name().to_string(), value);
+            if self.config.heatmaps
+                && let Some(map) = metric.distortion_map(reference, test)?
+            {
+                maps.insert(metric.name().to_string(), map);
+            }
+        }
+        Ok(())
+    }
+
+    /// Store both Butteraugli norms, keeping the diffmap when heatmaps are
+    /// enabled.
+    fn record_butteraugli(
+        &self,
+        result: &mut MetricResult,
+        maps: &mut DistortionMaps,
+        scores: butteraugli::ButteraugliScores,
+        map: DistortionMap,
+    ) {
+        result.butteraugli = Some(scores.max);
+        result.butteraugli_pnorm = Some(scores.pnorm);
+        if self.config.heatmaps {
+            maps.insert("butteraugli".to_string(), map);
+        }
+    }
+
+    /// Store the CIEDE2000 summary, keeping the per-pixel map when heatmaps
+    /// are enabled.
+    fn record_ciede2000(
+        &self,
+        result: &mut MetricResult,
+        maps: &mut DistortionMaps,
+        scores: ciede2000::DeltaEScores,
+        map: DistortionMap,
+    ) {
+        result.ciede2000 = Some(scores.mean);
+        result.ciede2000_p95 = Some(scores.p95);
+        result.ciede2000_max = Some(scores.max);
+        if self.config.heatmaps {
+            maps.insert("ciede2000".to_string(), map);
+        }
+    }
+
+    /// Run DSSIM, keeping its distortion map when heatmaps are enabled.
+    fn compare_dssim(
+        &self,
+        reference: &ImgVec<RGBA<f32>>,
+        test: &ImgVec<RGBA<f32>>,
+        maps: &mut DistortionMaps,
+    ) -> Result<f64> {
+        if !self.config.heatmaps {
+            return dssim::compare_dssim(reference, test);
+        }
+        let (score, map) = dssim::compare_dssim_with_map(reference, test)?;
+        maps.insert("dssim".to_string(), map);
+        Ok(score)
+    }
+
+    /// Write an image report to the configured report directory.
+    pub fn write_image_report(&self, report: &ImageReport) -> Result<()> {
+        std::fs::create_dir_all(&self.config.report_dir)?;
+
+        let json_path = self.config.report_dir.join(format!("{}.json", report.name));
+        let json = serde_json::to_string_pretty(report)?;
+        std::fs::write(json_path, json)?;
+
+        Ok(())
+    }
+
+    /// Write a corpus report to the configured report directory.
+    pub fn write_corpus_report(&self, report: &CorpusReport) -> Result<()> {
+        std::fs::create_dir_all(&self.config.report_dir)?;
+
+        let json_path = self.config.report_dir.join(format!("{}.json", report.name));
+        let json = serde_json::to_string_pretty(report)?;
+        std::fs::write(json_path, json)?;
+
+        // Also write CSV summary
+        let csv_path = self.config.report_dir.join(format!("{}.csv", report.name));
+        self.write_csv_summary(report, &csv_path)?;
+
+        Ok(())
+    }
+
+    /// Write a CSV summary of the corpus report.
+    fn write_csv_summary(&self, report: &CorpusReport, path: &Path) -> Result<()> {
+        let mut wtr = csv::Writer::from_path(path)?;
+
+        // Custom metrics become extra columns, sorted by name.
+        let custom: BTreeSet<&str> = report
+            .images
+            .iter()
+            .flat_map(|img| &img.results)
+            .flat_map(|r| r.metrics.custom.keys().map(String::as_str))
+            .collect();
+
+        // Header
+        let mut header = vec![
+            "image",
+            "codec",
+            "version",
+            "quality",
+            "file_size",
+            "bpp",
+            "encode_ms",
+            "decode_ms",
+        ];
+        // Peak memory columns appear when memory was measured.
+        let measured = report
+            .images
+            .iter()
+            .flat_map(|img| &img.results)
+            .any(|r| r.encode_peak_bytes.is_some() || r.decode_peak_bytes.is_some());
+        header.extend(["dssim", "ssimulacra2", "butteraugli", "psnr", "perception"]);
+        // Timing detail columns appear when a timing policy repeated runs or
+        // measured CPU time.
+        let timed = report
+            .images
+            .iter()
+            .flat_map(|img| &img.results)
+            .flat_map(|r| [&r.encode_timing, &r.decode_timing])
+            .flatten()
+            .any(|t| t.samples > 1 || t.cpu.is_some());
+        if timed {
+            header.extend([
+                "encode_min_ms",
+                "encode_mad_ms",
+                "encode_cpu_ms",
+                "decode_min_ms",
+                "decode_mad_ms",
+                "decode_cpu_ms",
+            ]);
+        }
+        if measured {
+            header.extend(["encode_peak_bytes", "decode_peak_bytes"]);
+        }
+        // SSIM columns appear when either index was computed.
+        let ssim_scored = report
+            .images
+            .iter()
+            .flat_map(|img| &img.results)
+            .any(|r| r.metrics.ssim.is_some() || r.metrics.ms_ssim.is_some());
+        if ssim_scored {
+            header.extend(["ssim", "ms_ssim"]);
+        }
+        let pnorm_scored = report
+            .images
+            .iter()
+            .flat_map(|img| &img.results)
+            .any(|r| r.metrics.butteraugli_pnorm.is_some());
+        if pnorm_scored {
+            header.push("butteraugli_pnorm");
+        }
+        let delta_e_scored = report
+            .images
+            .iter()
+            .flat_map(|img| &img.results)
+            .any(|r| r.metrics.ciede2000.is_some());
+        if delta_e_scored {
+            header.extend(["ciede2000", "ciede2000_p95", "ciede2000_max"]);
+        }
+        let alpha_scored = report
+            .images
+            .iter()
+            .flat_map(|img| &img.results)
+            .any(|r| r.metrics.alpha_rmse.is_some());
+        if alpha_scored {
+            header.push("alpha_rmse");
+        }
+        header.extend(&custom);
+        // Encoder parameters get a `param_<name>` column each.
+        let params: BTreeSet<&str> = report
+            .images
+            .iter()
+            .flat_map(|img| {
+                let results = img.results.iter().flat_map(|r| r.codec_params.keys());
+                let failures = img.failures.iter().flat_map(|f| f.params.keys());
+                results.chain(failures)
+            })
+            .map(String::as_str)
+            .collect();
+        let param_headers: Vec<String> = params.iter().map(|p| format!("param_{p}")).collect();
+        header.extend(param_headers.iter().map(String::as_str));
+        // Target-quality runs also record how each result was found.
+        let searched = report
+            .images
+            .iter()
+            .flat_map(|img| &img.results)
+            .any(|r| r.search.is_some());
+        let searched_columns = if searched { 4 } else { 0 };
+        if searched {
+            header.extend([
+                "search_metric",
+                "search_target",
+                "search_encodes",
+                "search_converged",
+            ]);
+        }
+        // Failed jobs get rows of their own, with the error kind and message
+        // in trailing `error_kind` and `error` columns.
+        let failed = report.images.iter().any(|img| !img.failures.is_empty());
+        let error_columns = if failed { 2 } else { 0 };
+        if failed {
+            header.extend(["error_kind", "error"]);
+        }
+        let error_values = vec![String::new(); error_columns];
+        wtr.write_record(&header)?;
+
+        for img in &report.images {
+            for result in &img.results {
+                let custom_values: Vec<String> = custom
+                    .iter()
+                    .map(|name| {
+                        result
+                            .metrics
+                            .custom
+                            .get(*name)
+                            .map_or(String::new(), ToString::to_string)
+                    })
+                    .collect();
+                let param_values: Vec<String> = params
+                    .iter()
+                    .map(|p| result.codec_params.get(*p).cloned().unwrap_or_default())
+                    .collect();
+                let search_values: Vec<String> = match (&result.search, searched) {
+                    (Some(search), _) => vec![
+                        search.metric.clone(),
+                        search.target.to_string(),
+                        search.encodes.to_string(),
+                        search.converged.to_string(),
+                    ],
+                    (None, true) => vec![String::new(); 4],
+                    (None, false) => Vec::new(),
+                };
+                // Min, MAD and median CPU time of the encode, then the decode.
+                let timing_values: Vec<String> = [&result.encode_timing, &result.decode_timing]
+                    .into_iter()
+                    .filter(|_| timed)
+                    .flat_map(|timing| {
+                        let ms = |v: f64| format!("{v:.3}");
+                        match timing {
+                            Some(t) => [
+                                ms(t.wall.min_ms),
+                                ms(t.wall.mad_ms),
+                                t.cpu.map_or(String::new(), |cpu| ms(cpu.median_ms)),
+                            ],
+                            None => Default::default(),
+                        }
+                    })
+                    .collect();
+                let ssim_values: Vec<String> = if ssim_scored {
+                    [result.metrics.ssim, result.metrics.ms_ssim]
+                        .iter()
+                        .map(|s| s.map_or(String::new(), |s| format!("{s:.6}")))
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                let pnorm_value = pnorm_scored.then(|| {
+                    result
+                        .metrics
+                        .butteraugli_pnorm
+                        .map_or(String::new(), |b| format!("{b:.4}"))
+                });
+                let alpha_value = alpha_scored.then(|| {
+                    result
+                        .metrics
+                        .alpha_rmse
+                        .map_or(String::new(), |a| format!("{a:.4}"))
+                });
+                let delta_e_values: Vec<String> = if delta_e_scored {
+                    [
+                        result.metrics.ciede2000,
+                        result.metrics.ciede2000_p95,
+                        result.metrics.ciede2000_max,
+                    ]
+                    .iter()
+                    .map(|d| d.map_or(String::new(), |d| format!("{d:.4}")))
+                    .collect()
+                } else {
+                    Vec::new()
+                };
+                let memory_values: Vec<String> = if measured {
+                    [result.encode_peak_bytes, result.decode_peak_bytes]
+                        .iter()
+                        .map(|b| b.map_or(String::new(), |b| b.to_string()))
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                wtr.write_record(
+                    [
+                        &img.name,
+                        &result.codec_id,
+                        &result.codec_version,
+                        &result.quality.to_string(),
+                        &result.file_size.to_string(),
+                        &format!("{:.4}", result.bits_per_pixel),
+                        &result.encode_time.as_millis().to_string(),
+                        &result
+                            .decode_time
+                            .map_or(String::new(), |d| d.as_millis().to_string()),
+                    ]
+                    .into_iter()
+                    .chain([
+                        &result
+                            .metrics
+                            .dssim
+                            .map_or(String::new(), |d| format!("{:.6}", d)),
+                        &result
+                            .metrics
+                            .ssimulacra2
+                            .map_or(String::new(), |s| format!("{:.2}", s)),
+                        &result
+                            .metrics
+                            .butteraugli
+                            .map_or(String::new(), |b| format!("{:.4}", b)),
+                        &result
+                            .metrics
+                            .psnr
+                            .map_or(String::new(), |p| format!("{:.2}", p)),
+                        &result
+                            .perception
+                            .map_or(String::new(), |p| p.code().to_string()),
+                    ])
+                    .chain(&timing_values)
+                    .chain(&memory_values)
+                    .chain(&ssim_values)
+                    .chain(&pnorm_value)
+                    .chain(&delta_e_values)
+                    .chain(&alpha_value)
+                    .chain(&custom_values)
+                    .chain(&param_values)
+                    .chain(&search_values)
+                    .chain(&error_values),
+                )?;
+            }
+            for failure in &img.failures {
+                let mut record = vec![
+                    img.name.clone(),
+                    failure.codec_id.clone(),
+                    failure.codec_version.clone(),
+                    failure.quality.map_or(String::new(), |q| q.to_string()),
+                ];
+                record.resize(header.len() - error_columns, String::new());
+                let first_param = header.len() - error_columns - searched_columns - params.len();
+                for (i, p) in params.iter().enumerate() {
+                    record[first_param + i] = failure.params.get(*p).cloned().unwrap_or_default();
+                }
+                record.extend([failure.kind.clone(), failure.message.clone()]);
+                wtr.write_record(&record)?;
+            }
+        }
+
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::timing::TimingStatistic;
+
+    fn create_test_image(width: usize, height: usize) -> ImageData {
+        let data: Vec<u8> = (0..width * height * 3).map(|i| (i % 256) as u8).collect();
+        ImageData::RgbSlice {
+            data,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn test_image_data_dimensions() {
+        let img = create_test_image(100, 50);
+        assert_eq!(img.width(), 100);
+        assert_eq!(img.height(), 50);
+    }
+
+    /// Mean absolute difference of the red channel.
+    struct RedMae;
+
+    impl Metric for RedMae {
+        fn name(&self) -> &'static str {
+            "red_mae"
+        }
+
+        fn direction(&self) -> QualityDirection {
+            QualityDirection::LowerIsBetter
+        }
+
+        fn compute(&self, reference: ImgRef<'_, RGB8>, test: ImgRef<'_, RGB8>) -> Result<f64> {
+            let sum: f64 = reference
+                .pixels()
+                .zip(test.pixels())
+                .map(|(a, b)| f64::from(a.r.abs_diff(b.r)))
+                .sum();
+            Ok(sum / (reference.width() * reference.height()) as f64)
+        }
+    }
+
+    #[test]
+    fn test_custom_metric_flows_to_results_and_csv() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = EvalConfig::builder()
+            .report_dir(dir.path())
+            .metrics(MetricConfig::fast())
+            .quality_levels(vec![90.0])
+            .build();
+        let mut session = EvalSession::new(config);
+        session.add_metric(RedMae);
+        session.add_codec_with_decode(
+            "brighten",
+            "1.0",
+            Box::new(|image, _| Ok(image.to_rgb8_vec())),
+            Box::new(|bytes| {
+                Ok(ImageData::RgbSlice {
+                    data: bytes.iter().map(|v| v.saturating_add(4)).collect(),
+                    width: 16,
+                    height: 16,
+                })
+            }),
+        );
+        assert_eq!(
+            session.metric_direction("red_mae"),
+            Some(QualityDirection::LowerIsBetter)
+        );
+
+        let image = ImageData::RgbSlice {
+            data: vec![100; 16 * 16 * 3],
+            width: 16,
+            height: 16,
+        };
+        let mut report = CorpusReport::new("custom".to_string());
+        report
+            .images
+            .push(session.evaluate_image("flat", image).unwrap());
+        assert_eq!(
+            report.images[0].results[0].metrics.value("red_mae"),
+            Some(4.0)
+        );
+
+        session.write_corpus_report(&report).unwrap();
+        let csv = std::fs::read_to_string(dir.path().join("custom.csv")).unwrap();
+        let mut lines = csv.lines();
+        let header = lines.next().unwrap();
+        assert!(header.ends_with(",red_mae"));
+        assert!(!header.contains("alpha_rmse"));
+        assert!(lines.next().unwrap().ends_with(",4"));
+
+        let imported = crate::import::CsvImporter::auto_detect()
+            .import(dir.path().join("custom.csv"))
+            .unwrap();
+        assert_eq!(imported[0].metric("red_mae"), Some(4.0));
+    }
+
+    #[test]
+    fn test_ciede2000_scores_and_csv_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = EvalConfig::builder()
+            .report_dir(dir.path())
+            .metrics(MetricConfig::fast().with_ciede2000())
+            .quality_levels(vec![90.0])
+            .build();
+        let mut session = EvalSession::new(config);
+        // Swaps green and blue in the left half: a hue shift with little
+        // change in luma.
+        session.add_codec_with_decode(
+            "hue-shift",
+            "1.0",
+            Box::new(|image, _| Ok(image.to_rgb8_vec())),
+            Box::new(|bytes| {
+                let mut data = bytes.to_vec();
+                for (i, p) in data.chunks_exact_mut(3).enumerate() {
+                    if i % 16 < 8 {
+                        p.swap(1, 2);
+                    }
+                }
+                Ok(ImageData::RgbSlice {
+                    data,
+                    width: 16,
+                    height: 16,
+                })
+            }),
+        );
+
+        let image = ImageData::RgbSlice {
+            data: [200, 60, 20].repeat(16 * 16),
+            width: 16,
+            height: 16,
+        };
+        let mut report = CorpusReport::new("delta-e".to_string());
+        report
+            .images
+            .push(session.evaluate_image("orange", image).unwrap());
+        let metrics = &report.images[0].results[0].metrics;
+        let (mean, max) = (metrics.ciede2000.unwrap(), metrics.ciede2000_max.unwrap());
+        assert!(max > 10.0, "max {max}");
+        assert!((mean - max / 2.0).abs() < 1e-3, "mean {mean}");
+        assert_eq!(metrics.ciede2000_p95, Some(max));
+
+        session.write_corpus_report(&report).unwrap();
+        let csv = std::fs::read_to_string(dir.path().join("delta-e.csv")).unwrap();
+        assert!(
+            csv.lines()
+                .next()
+                .unwrap()
+                .ends_with(",ciede2000,ciede2000_p95,ciede2000_max")
+        );
+    }
+
+    #[cfg(feature = "heatmap")]
+    #[test]
+    fn test_heatmaps_written_and_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = EvalConfig::builder()
+            .report_dir(dir.path())
+            .metrics(MetricConfig::perceptual())
+            .quality_levels(vec![50.0])
+            .heatmaps(true)
+            .build();
+        let mut session = EvalSession::new(config);
+        session.add_codec_with_decode(
+            "dark/codec",
+            "1.0",
+            Box::new(|image, _| Ok(image.to_rgb8_vec())),
+            Box::new(|bytes| {
+                Ok(ImageData::RgbSlice {
+                    data: bytes.iter().map(|v| v.saturating_sub(20)).collect(),
+                    width: 32,
+                    height: 32,
+                })
+            }),
+        );
+
+        let image = ImageData::RgbSlice {
+            data: (0..32 * 32 * 3).map(|i| (i % 200) as u8).collect(),
+            width: 32,
+            height: 32,
+        };
+        let report = session.evaluate_image("img", image).unwrap();
+        let heatmaps = &report.results[0].heatmaps;
+        assert_eq!(
+            heatmaps.keys().collect::<Vec<_>>(),
+            vec!["butteraugli", "dssim"]
+        );
+        for path in heatmaps.values() {
+            assert!(path.starts_with(dir.path().join("heatmaps").join("img")));
+            assert!(path.exists(), "{}", path.display());
+        }
+        assert!(heatmaps["dssim"].ends_with("dark_codec-q50-dssim.png"));
+    }
+
+    /// Session with one codec whose encoder fails at quality 90.
+    fn flaky_session(dir: &Path, fail_fast: bool) -> EvalSession {
+        let config = EvalConfig::builder()
+            .report_dir(dir)
+            .metrics(MetricConfig::fast())
+            .quality_levels(vec![80.0, 90.0, 95.0])
+            .fail_fast(fail_fast)
+            .build();
+        let mut session = EvalSession::new(config);
+        session.add_codec_with_decode(
+            "flaky",
+            "1.0",
+            Box::new(|image, request| {
+                if request.quality == 90.0 {
+                    return Err(Error::Codec {
+                        codec: "flaky".to_string(),
+                        message: "unsupported setting".to_string(),
+                    });
+                }
+                Ok(image.to_rgb8_vec())
+            }),
+            Box::new(|bytes| {
+                Ok(ImageData::RgbSlice {
+                    data: bytes.to_vec(),
+                    width: 8,
+                    height: 8,
+                })
+            }),
+        );
+        session
+    }
+
+    #[test]
+    fn test_failures_recorded_and_run_continues() {
+        let dir = tempfile::tempdir().unwrap();
+        let session = flaky_session(dir.path(), false);
+        let mut report = CorpusReport::new("flaky".to_string());
+        report.images.push(
+            session
+                .evaluate_image("img", create_test_image(8, 8))
+                .unwrap(),
+        );
+
+        let image = &report.images[0];
+        let qualities: Vec<f64> = image.results.iter().map(|r| r.quality).collect();
+        assert_eq!(qualities, vec![80.0, 95.0]);
+        assert_eq!(
+            image.failures,
+            vec![FailureRecord {
+                codec_id: "flaky".to_string(),
+                codec_version: "1.0".to_string(),
+                quality: Some(90.0),
+                params: BTreeMap::new(),
+                kind: "codec".to_string(),
+                message: "Codec error (flaky): unsupported setting".to_string(),
+            }]
+        );
+        assert_eq!(report.failure_summary().by_codec["flaky"], 1);
+
+        session.write_corpus_report(&report).unwrap();
+        let csv = std::fs::read_to_string(dir.path().join("flaky.csv")).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].ends_with(",error_kind,error"));
+        assert!(lines[1].ends_with(",,"));
+        assert!(lines[3].starts_with("img,flaky,1.0,90,,"));
+        assert!(lines[3].ends_with(",codec,Codec error (flaky): unsupported setting"));
+        assert_eq!(
+            lines[3].split(',').count(),
+            lines[0].split(',').count(),
+            "{}",
+            lines[3]
+        );
+    }
+
+    #[test]
+    fn test_fail_fast() {
+        let dir = tempfile::tempdir().unwrap();
+        let session = flaky_session(dir.path(), true);
+        let err = session
+            .evaluate_image("img", create_test_image(8, 8))
+            .unwrap_err();
+        assert_eq!(err.kind(), "codec");
+    }
+
+    #[test]
+    fn test_tile_and_region_scores() {
+        use crate::metrics::tiles::{Rect, Region, TileGrid};
+
+        let config = EvalConfig::builder()
+            .report_dir("/tmp/test")
+            .metrics(MetricConfig::all().with_tiles(TileGrid::new(16)))
+            .regions("img", vec![Region::new("corner", 0, 0, 8, 8)])
+            .quality_levels(vec![50.0])
+            .build();
+        let mut session = EvalSession::new(config);
+        // Damage only the top-left 8x8 block.
+        session.add_codec_with_decode(
+            "blotchy",
+            "1.0",
+            Box::new(|image, _| Ok(image.to_rgb8_vec())),
+            Box::new(|bytes| {
+                let mut data = bytes.to_vec();
+                for y in 0..8 {
+                    for x in 0..8 {
+                        let i = (y * 32 + x) * 3;
+                        data[i..i + 3].copy_from_slice(&[255, 0, 255]);
+                    }
+                }
+                Ok(ImageData::RgbSlice {
+                    data,
+                    width: 32,
+                    height: 32,
+                })
+            }),
+        );
+
+        let report = session
+            .evaluate_image("img", create_test_image(32, 32))
+            .unwrap();
+        let metrics = &report.results[0].metrics;
+        let tiles = metrics.tiles.as_ref().unwrap();
+        assert_eq!((tiles.columns, tiles.rows), (2, 2));
+        assert_eq!(tiles.tiles.len(), 4);
+        assert_eq!(tiles.tiles[3].rect, Rect::new(16, 16, 16, 16));
+
+        // Only the top-left tile is damaged, and it is worse than the whole.
+        assert_eq!(tiles.tiles[1].metrics.psnr, Some(f64::INFINITY));
+        let worst = tiles
+            .worst_tile("psnr", QualityDirection::HigherIsBetter)
+            .unwrap();
+        assert_eq!(worst.rect, Rect::new(0, 0, 16, 16));
+        assert_eq!(tiles.worst.psnr, worst.metrics.psnr);
+        assert!(tiles.worst.psnr.unwrap() < metrics.psnr.unwrap());
+        assert!(tiles.worst.butteraugli.unwrap() >= tiles.p90.butteraugli.unwrap());
+
+        let corner = &tiles.regions[0];
+        assert_eq!(corner.name.as_deref(), Some("corner"));
+        assert!(corner.metrics.psnr.unwrap() < tiles.worst.psnr.unwrap());
+        assert!(corner.metrics.ssimulacra2.is_some());
+
+        // Tile scores survive the JSON report.
+        let json = serde_json::to_string(&report).unwrap();
+        let parsed: ImageReport = serde_json::from_str(&json).unwrap();
+        let parsed = parsed.results[0].metrics.tiles.as_ref().unwrap();
+        assert_eq!(parsed.regions[0].rect, corner.rect);
+    }
+
+    #[test]
+    fn test_undersized_areas_are_left_unscored() {
+        use crate::metrics::tiles::{Region, TileGrid};
+
+        // Shown on a phone, 16 px tiles shrink to about 7 px.
+        let config = EvalConfig::builder()
+            .report_dir("/tmp/test")
+            .viewing(crate::viewing::presets::native_phone())
+            .metrics(MetricConfig::fast().with_tiles(TileGrid::new(16)))
+            .regions(
+                "img",
+                vec![
+                    Region::new("outside", 100, 100, 8, 8),
+                    Region::new("whole", 0, 0, 64, 64),
+                ],
+            )
+            .quality_levels(vec![50.0])
+            .build();
+        let mut session = EvalSession::new(config);
+        session.add_codec_with_decode(
+            "identity",
+            "1.0",
+            Box::new(|image, _| Ok(image.to_rgb8_vec())),
+            Box::new(|bytes| {
+                Ok(ImageData::RgbSlice {
+                    data: bytes.to_vec(),
+                    width: 64,
+                    height: 64,
+                })
+            }),
+        );
+
+        let report = session
+            .evaluate_image("img", create_test_image(64, 64))
+            .unwrap();
+        assert!(report.failures.is_empty());
+        let metrics = &report.results[0].metrics;
+        assert!(metrics.psnr.is_some());
+        let tiles = metrics.tiles.as_ref().unwrap();
+        assert_eq!(tiles.tiles.len(), 16);
+        // The top-left tile maps to 7x7 pixels and is left unscored; the
+        // worst tile is taken over the scored ones.
+        assert_eq!(tiles.tiles[0].metrics.psnr, None);
+        assert!(tiles.worst.psnr.is_some());
+        assert_eq!(tiles.regions[0].name.as_deref(), Some("outside"));
+        assert_eq!(tiles.regions[0].metrics.psnr, None);
+        assert!(tiles.regions[1].metrics.psnr.is_some());
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        events: std::sync::Mutex<Vec<String>>,
+        last: std::sync::Mutex<Option<crate::eval::Progress>>,
+    }
+
+    impl EvalObserver for Recorder {
+        fn image_started(&self, name: &str) {
+            self.events.lock().unwrap().push(format!("start {name}"));
+        }
+
+        fn image_finished(&self, report: &ImageReport) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("finish {}", report.name));
+        }
+
+        fn result(&self, image: &str, result: &CodecResult) {
+            let event = format!("result {image} q{}", result.quality);
+            self.events.lock().unwrap().push(event);
+        }
+
+        fn failure(&self, image: &str, failure: &FailureRecord) {
+            let event = format!("failure {image} {}", failure.kind);
+            self.events.lock().unwrap().push(event);
+        }
+
+        fn progress(&self, progress: &crate::eval::Progress) {
+            *self.last.lock().unwrap() = Some(*progress);
+        }
     }
 
-    /// Write an image report to the configured report directory.
-    pub fn write_image_report(&self, report: &ImageReport) -> Result<()> {
-        std::fs::create_dir_all(&self.config.report_dir)?;
+    #[test]
+    fn test_observer_sees_results_failures_and_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut session = flaky_session(dir.path(), false);
+        session.config.threads = Some(1);
+        let recorder = std::sync::Arc::new(Recorder::default());
+        session.add_observer(recorder.clone());
 
-        let json_path = self.config.report_dir.join(format!("{}.json", report.name));
-        let json = serde_json::to_string_pretty(report)?;
-        std::fs::write(json_path, json)?;
+        session
+            .evaluate_image("img", create_test_image(8, 8))
+            .unwrap();
+        assert_eq!(
+            *recorder.events.lock().unwrap(),
+            vec![
+                "start img",
+                "result img q80",
+                "failure img codec",
+                "result img q95",
+                "finish img",
+            ]
+        );
+        let last = recorder.last.lock().unwrap().unwrap();
+        assert_eq!((last.jobs_done, last.jobs_total), (3, 3));
+        assert_eq!((last.images_done, last.images_total), (1, 1));
+        assert!(last.is_done());
+        assert_eq!(last.eta, Some(std::time::Duration::ZERO));
+    }
 
-        Ok(())
+    #[test]
+    fn test_cancellation_stops_run() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let config = EvalConfig::builder()
+            .report_dir("/tmp/test")
+            .metrics(MetricConfig::fast())
+            .quality_levels(vec![50.0, 70.0, 90.0])
+            .threads(1)
+            .build();
+        let mut session = EvalSession::new(config);
+        let token = session.cancellation_token();
+        let encodes = std::sync::Arc::new(AtomicUsize::new(0));
+        let counter = encodes.clone();
+        session.add_codec(
+            "cancelling",
+            "1.0",
+            Box::new(move |image, _| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                // Cancel from inside the first encode; it still finishes.
+                token.cancel();
+                Ok(image.to_rgb8_vec())
+            }),
+        );
+
+        let err = session
+            .evaluate_image("img", create_test_image(8, 8))
+            .unwrap_err();
+        assert!(matches!(err, Error::Cancelled));
+        assert_eq!(err.kind(), "cancelled");
+        assert_eq!(encodes.load(Ordering::SeqCst), 1);
+
+        // With a fresh token the codec only cancels its stale one.
+        session.set_cancellation_token(CancellationToken::new());
+        let report = session
+            .evaluate_image("img", create_test_image(8, 8))
+            .unwrap();
+        assert_eq!(report.results.len(), 3);
     }
 
-    /// Write a corpus report to the configured report directory.
-    pub fn write_corpus_report(&self, report: &CorpusReport) -> Result<()> {
-        std::fs::create_dir_all(&self.config.report_dir)?;
+    #[test]
+    fn test_param_grid_combinations() {
+        assert_eq!(ParamGrid::new().combinations(), vec![BTreeMap::new()]);
 
-        let json_path = self.config.report_dir.join(format!("{}.json", report.name));
-        let json = serde_json::to_string_pretty(report)?;
-        std::fs::write(json_path, json)?;
+        let grid = ParamGrid::new()
+            .with_param("speed", &["4", "8"])
+            .with_param("subsampling", &["420", "444"])
+            .with_param("unused", &[]);
+        let combinations: Vec<String> = grid.combinations().iter().map(format_params).collect();
+        assert_eq!(
+            combinations,
+            vec![
+                "speed=4,subsampling=420",
+                "speed=4,subsampling=444",
+                "speed=8,subsampling=420",
+                "speed=8,subsampling=444",
+            ]
+        );
+    }
 
-        // Also write CSV summary
-        let csv_path = self.config.report_dir.join(format!("{}.csv", report.name));
-        self.write_csv_summary(report, &csv_path)?;
+    #[test]
+    fn test_param_grid_jobs_and_csv_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = EvalConfig::builder()
+            .report_dir(dir.path())
+            .metrics(MetricConfig::fast())
+            .quality_levels(vec![50.0, 90.0])
+            .param_grid(
+                "tunable",
+                ParamGrid::new()
+                    .with_param("effort", &["1", "2"])
+                    .with_param("tune", &["psnr"]),
+            )
+            .heatmaps(false)
+            .build();
+        let mut session = EvalSession::new(config);
+        // Pads the payload by `effort` bytes so each combination differs.
+        session.add_codec_with_decode(
+            "tunable",
+            "1.0",
+            Box::new(|image, request| {
+                let effort: usize = request.params["effort"].parse().unwrap();
+                let mut bytes = image.to_rgb8_vec();
+                bytes.resize(bytes.len() + effort, 0);
+                Ok(bytes)
+            }),
+            Box::new(|bytes| {
+                Ok(ImageData::RgbSlice {
+                    data: bytes[..8 * 8 * 3].to_vec(),
+                    width: 8,
+                    height: 8,
+                })
+            }),
+        );
+        session.add_codec("plain", "1.0", Box::new(|image, _| Ok(image.to_rgb8_vec())));
 
-        Ok(())
+        let mut report = CorpusReport::new("grid".to_string());
+        report.images.push(
+            session
+                .evaluate_image("img", create_test_image(8, 8))
+                .unwrap(),
+        );
+        let jobs: Vec<(String, f64, usize)> = report.images[0]
+            .results
+            .iter()
+            .map(|r| {
+                let effort = r.codec_params.get("effort").cloned().unwrap_or_default();
+                (format!("{}{effort}", r.codec_id), r.quality, r.file_size)
+            })
+            .collect();
+        assert_eq!(
+            jobs,
+            vec![
+                ("tunable1".to_string(), 50.0, 193),
+                ("tunable1".to_string(), 90.0, 193),
+                ("tunable2".to_string(), 50.0, 194),
+                ("tunable2".to_string(), 90.0, 194),
+                ("plain".to_string(), 50.0, 192),
+                ("plain".to_string(), 90.0, 192),
+            ]
+        );
+        assert_eq!(report.images[0].results[0].codec_params["tune"], "psnr");
+
+        session.write_corpus_report(&report).unwrap();
+        let csv = std::fs::read_to_string(dir.path().join("grid.csv")).unwrap();
+        let mut lines = csv.lines();
+        assert!(lines.next().unwrap().ends_with(",param_effort,param_tune"));
+        assert!(lines.next().unwrap().ends_with(",1,psnr"));
+        assert!(lines.nth(3).unwrap().ends_with(",,"));
     }
 
-    /// Write a CSV summary of the corpus report.
-    fn write_csv_summary(&self, report: &CorpusReport, path: &Path) -> Result<()> {
-        let mut wtr = csv::Writer::from_path(path)?;
+    #[test]
+    fn test_target_quality_search() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = EvalConfig::builder()
+            .report_dir(dir.path())
+            .metrics(MetricConfig::fast())
+            .target_quality(vec![QualityTarget::new("psnr", 30.0)])
+            .build();
+        let mut session = EvalSession::new(config);
+        // Darkens by (100 - quality) / 5 levels and pads the payload by
+        // `quality` bytes, so PSNR >= 30 dB first holds at quality 56.
+        session.add_codec_with_decode(
+            "lossy",
+            "1.0",
+            Box::new(|image, request| {
+                let shift = ((100.0 - request.quality) / 5.0).floor() as u8;
+                let mut bytes: Vec<u8> = image
+                    .to_rgb8_vec()
+                    .iter()
+                    .map(|v| v.saturating_sub(shift))
+                    .collect();
+                bytes.resize(bytes.len() + request.quality as usize, 0);
+                Ok(bytes)
+            }),
+            Box::new(|bytes| {
+                Ok(ImageData::RgbSlice {
+                    data: bytes[..16 * 16 * 3].to_vec(),
+                    width: 16,
+                    height: 16,
+                })
+            }),
+        );
 
-        // Header
-        wtr.write_record([
-            "image",
-            "codec",
-            "version",
-            "quality",
-            "file_size",
-            "bpp",
-            "encode_ms",
-            "decode_ms",
-            "dssim",
-            "ssimulacra2",
-            "butteraugli",
-            "psnr",
-            "perception",
-        ])?;
+        let image = ImageData::RgbSlice {
+            data: vec![100; 16 * 16 * 3],
+            width: 16,
+            height: 16,
+        };
+        let mut report = CorpusReport::new("target".to_string());
+        report
+            .images
+            .push(session.evaluate_image("flat", image).unwrap());
+        let result = &report.images[0].results[0];
+        assert_eq!(report.images[0].results.len(), 1);
+        assert_eq!(result.quality, 56.0);
+        assert_eq!(result.file_size, 16 * 16 * 3 + 56);
+        let search = result.search.as_ref().unwrap();
+        assert!(search.converged);
+        assert!(search.achieved >= 30.0);
+        assert!(search.encodes <= 8, "{} encodes", search.encodes);
 
-        for img in &report.images {
-            for result in &img.results {
-                wtr.write_record([
-                    &img.name,
-                    &result.codec_id,
-                    &result.codec_version,
-                    &result.quality.to_string(),
-                    &result.file_size.to_string(),
-                    &format!("{:.4}", result.bits_per_pixel),
-                    &result.encode_time.as_millis().to_string(),
-                    &result
-                        .decode_time
-                        .map_or(String::new(), |d| d.as_millis().to_string()),
-                    &result
-                        .metrics
-                        .dssim
-                        .map_or(String::new(), |d| format!("{:.6}", d)),
-                    &result
-                        .metrics
-                        .ssimulacra2
-                        .map_or(String::new(), |s| format!("{:.2}", s)),
-                    &result
-                        .metrics
-                        .butteraugli
-                        .map_or(String::new(), |b| format!("{:.4}", b)),
-                    &result
-                        .metrics
-                        .psnr
-                        .map_or(String::new(), |p| format!("{:.2}", p)),
-                    &result
-                        .perception
-                        .map_or(String::new(), |p| p.code().to_string()),
-                ])?;
-            }
-        }
+        session.write_corpus_report(&report).unwrap();
+        let csv = std::fs::read_to_string(dir.path().join("target.csv")).unwrap();
+        let mut lines = csv.lines();
+        assert!(
+            lines
+                .next()
+                .unwrap()
+                .ends_with(",search_metric,search_target,search_encodes,search_converged")
+        );
+        assert!(lines.next().unwrap().ends_with(",true"));
+    }
 
-        wtr.flush()?;
-        Ok(())
+    #[test]
+    fn test_target_size_search() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = EvalConfig::builder()
+            .report_dir(dir.path())
+            .metrics(MetricConfig::fast())
+            .target_size(vec![SizeTarget::bytes(502), SizeTarget::bpp(50.0)])
+            .build();
+        let mut session = EvalSession::new(config);
+        // 192 bytes of pixels plus 4 bytes per quality step.
+        session.add_codec_with_decode(
+            "padded",
+            "1.0",
+            Box::new(|image, request| {
+                let shift = ((100.0 - request.quality) / 10.0).floor() as u8;
+                let mut bytes: Vec<u8> = image
+                    .to_rgb8_vec()
+                    .iter()
+                    .map(|v| v.saturating_sub(shift))
+                    .collect();
+                bytes.resize(bytes.len() + 4 * request.quality as usize, 0);
+                Ok(bytes)
+            }),
+            Box::new(|bytes| {
+                Ok(ImageData::RgbSlice {
+                    data: bytes[..8 * 8 * 3].to_vec(),
+                    width: 8,
+                    height: 8,
+                })
+            }),
+        );
+
+        let report = session
+            .evaluate_image("img", create_test_image(8, 8))
+            .unwrap();
+        let [bytes, bpp] = &report.results[..] else {
+            panic!("expected one result per target");
+        };
+
+        assert_eq!(bytes.quality, 77.0);
+        assert_eq!(bytes.file_size, 500);
+        assert!(bytes.metrics.psnr.is_some());
+        let search = bytes.search.as_ref().unwrap();
+        assert_eq!((search.metric.as_str(), search.target), ("bytes", 502.0));
+        assert_eq!(search.achieved, 500.0);
+        assert!(search.converged);
+
+        // 50 bpp of an 8x8 image is 400 bytes.
+        assert_eq!(bpp.quality, 52.0);
+        assert_eq!(bpp.search.as_ref().unwrap().achieved, 50.0);
+        assert!(bpp.search.as_ref().unwrap().converged);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_generation_loss_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = EvalConfig::builder()
+            .report_dir(dir.path())
+            .metrics(MetricConfig::fast())
+            .quality_levels(vec![80.0])
+            .generation_loss(
+                GenerationLoss::new(3)
+                    .then("copy", 90.0)
+                    .then("darken", 50.0),
+            )
+            .build();
+        let mut session = EvalSession::new(config);
+        // Pixels, darkened by one level per 10 quality below 100, then one
+        // byte of padding per quality step.
+        let codec = |darken: bool| -> EncodeFn {
+            Box::new(move |image, request| {
+                let shift = if darken {
+                    ((100.0 - request.quality) / 10.0) as u8
+                } else {
+                    0
+                };
+                let mut bytes: Vec<u8> = image
+                    .to_rgb8_vec()
+                    .iter()
+                    .map(|v| v.saturating_sub(shift))
+                    .collect();
+                bytes.resize(bytes.len() + request.quality as usize, 0);
+                Ok(bytes)
+            })
+        };
+        let decode = || -> DecodeFn {
+            Box::new(|bytes| {
+                Ok(ImageData::RgbSlice {
+                    data: bytes[..8 * 8 * 3].to_vec(),
+                    width: 8,
+                    height: 8,
+                })
+            })
+        };
+        session.add_codec_with_decode("darken", "1.0", codec(true), decode());
+        session.add_codec_with_decode("copy", "1.0", codec(false), decode());
 
-    fn create_test_image(width: usize, height: usize) -> ImageData {
-        let data: Vec<u8> = (0..width * height * 3).map(|i| (i % 256) as u8).collect();
-        ImageData::RgbSlice {
+        let image = session
+            .evaluate_image("img", create_test_image(8, 8))
+            .unwrap();
+        let darken = image
+            .results
+            .iter()
+            .find(|r| r.codec_id == "darken")
+            .unwrap();
+        let steps: Vec<(&str, f64, usize)> = darken
+            .generations
+            .iter()
+            .map(|g| (g.codec_id.as_str(), g.quality, g.file_size))
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                ("darken", 80.0, 272),
+                ("copy", 90.0, 282),
+                ("darken", 50.0, 242)
+            ]
+        );
+        assert_eq!(darken.generations[0].metrics.psnr, darken.metrics.psnr);
+
+        // The lossless copy keeps the loss, the second darken adds to it.
+        let psnr: Vec<f64> = darken
+            .generations
+            .iter()
+            .map(|g| g.metrics.psnr.unwrap())
+            .collect();
+        assert_eq!(psnr[0], psnr[1]);
+        assert!(psnr[2] < psnr[1]);
+
+        let mut report = CorpusReport::new("corpus".to_string());
+        report.images.push(image);
+        let curves = report.generation_curves("psnr");
+        assert_eq!(curves.len(), 2);
+        assert_eq!(curves[1].codec_id, "darken");
+        assert_eq!(curves[1].mean_score, psnr);
+        assert_eq!(curves[1].mean_bpp[0], 272.0 * 8.0 / 64.0);
+    }
+
+    #[test]
+    fn test_generation_loss_unknown_chain_codec() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = EvalConfig::builder()
+            .report_dir(dir.path())
+            .quality_levels(vec![80.0])
+            .generation_loss(GenerationLoss::new(2).then("missing", 90.0))
+            .build();
+        let mut session = EvalSession::new(config);
+        session.add_codec_with_decode(
+            "copy",
+            "1.0",
+            Box::new(|image, _| Ok(image.to_rgb8_vec())),
+            Box::new(|bytes| {
+                Ok(ImageData::RgbSlice {
+                    data: bytes.to_vec(),
+                    width: 8,
+                    height: 8,
+                })
+            }),
+        );
+
+        let report = session
+            .evaluate_image("img", create_test_image(8, 8))
+            .unwrap();
+        assert!(report.results.is_empty());
+        assert!(report.failures[0].message.contains("missing"));
+    }
+
+    #[test]
+    fn test_target_quality_requires_computed_metric() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = EvalConfig::builder()
+            .report_dir(dir.path())
+            .metrics(MetricConfig::fast())
+            .target_quality(vec![QualityTarget::new("butteraugli", 1.5)])
+            .build();
+        let mut session = EvalSession::new(config);
+        session.add_codec_with_decode(
+            "identity",
+            "1.0",
+            Box::new(|image, _| Ok(image.to_rgb8_vec())),
+            Box::new(|bytes| {
+                Ok(ImageData::RgbSlice {
+                    data: bytes.to_vec(),
+                    width: 8,
+                    height: 8,
+                })
+            }),
+        );
+        let report = session
+            .evaluate_image("img", create_test_image(8, 8))
+            .unwrap();
+        assert!(report.results.is_empty());
+        assert_eq!(report.failures[0].kind, "metric_calculation");
+        assert_eq!(report.failures[0].quality, None);
+    }
+
+    #[test]
+    fn test_image_data_high_precision() {
+        let image = ImageData::Rgba16 {
+            data: vec![1023, 512, 0, 1023, 0, 0, 0, 0],
+            width: 2,
+            height: 1,
+            bit_depth: 10,
+            transfer: TransferFunction::Pq,
+        };
+        assert!(image.is_high_precision());
+        assert!(image.has_alpha());
+        assert_eq!(image.transfer_function(), TransferFunction::Pq);
+        assert_eq!(image.to_rgb8_vec(), vec![255, 128, 0, 0, 0, 0]);
+        assert_eq!(image.alpha_vec(), Some(vec![255, 0]));
+        assert_eq!(image.to_rgb_f32_signal()[0], 1.0);
+
+        let srgb = ImageData::Rgba16 {
+            data: vec![1023, 512, 0, 1023, 0, 0, 0, 0],
+            width: 2,
+            height: 1,
+            bit_depth: 10,
+            transfer: TransferFunction::Srgb,
+        };
+        assert_ne!(image.content_checksum(), srgb.content_checksum());
+        assert!(!create_test_image(2, 1).is_high_precision());
+    }
+
+    #[test]
+    fn test_high_precision_metrics_skip_8bit_quantization() {
+        let (width, height) = (32, 32);
+        let data: Vec<u16> = (0..width * height * 3)
+            .map(|i| ((i * 37) % 4096) as u16)
+            .collect();
+        let image = ImageData::Rgb16 {
             data,
             width,
             height,
+            bit_depth: 12,
+            transfer: TransferFunction::Pq,
+        };
+
+        let config = EvalConfig::builder()
+            .report_dir("/tmp/test")
+            .metrics(MetricConfig::all())
+            .quality_levels(vec![90.0])
+            .build();
+        let mut session = EvalSession::new(config);
+        // Flipping the lowest of 12 bits is a difference that disappears when
+        // quantized to 8 bits.
+        for (id, offset) in [("lossless", 0u16), ("one-lsb", 1)] {
+            session.add_codec_with_decode(
+                id,
+                "1.0",
+                Box::new(move |image, _| {
+                    let ImageData::Rgb16 { data, .. } = image else {
+                        unreachable!()
+                    };
+                    Ok(data
+                        .iter()
+                        .flat_map(|&v| (v ^ offset).to_le_bytes())
+                        .collect())
+                }),
+                Box::new(move |bytes| {
+                    Ok(ImageData::Rgb16 {
+                        data: bytes
+                            .chunks_exact(2)
+                            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                            .collect(),
+                        width,
+                        height,
+                        bit_depth: 12,
+                        transfer: TransferFunction::Pq,
+                    })
+                }),
+            );
         }
-    }
 
-    #[test]
-    fn test_image_data_dimensions() {
-        let img = create_test_image(100, 50);
-        assert_eq!(img.width(), 100);
-        assert_eq!(img.height(), 50);
+        let report = session.evaluate_image("hdr", image).unwrap();
+        let lossless = &report.results[0].metrics;
+        assert_eq!(lossless.psnr, Some(f64::INFINITY));
+        assert!(lossless.ssimulacra2.unwrap() > 99.0);
+        assert!(lossless.butteraugli.unwrap() < 0.01);
+        assert!(lossless.alpha_rmse.is_none());
+
+        let one_lsb = &report.results[1].metrics;
+        let psnr = one_lsb.psnr.unwrap();
+        assert!(psnr.is_finite() && psnr > 70.0, "psnr {psnr}");
+        assert!(one_lsb.dssim.unwrap() > 0.0);
+        // SSIM also sees the 12-bit difference.
+        assert_eq!(lossless.ssim, Some(1.0));
+        assert!(one_lsb.ssim.unwrap() < 1.0);
+        assert!(one_lsb.ms_ssim.unwrap() < 1.0);
+        assert_eq!(lossless.ciede2000_max, Some(0.0));
+        assert!(one_lsb.ciede2000.unwrap() > 0.0);
     }
 
     #[test]
@@ -635,4 +3755,691 @@ mod tests {
 
         assert_eq!(session.codec_count(), 1);
     }
+
+    /// Session with two lossless "codecs" whose encoded size depends on quality.
+    fn create_roundtrip_session(threads: usize) -> EvalSession {
+        let config = EvalConfig::builder()
+            .report_dir("/tmp/test")
+            .metrics(MetricConfig::fast())
+            .quality_levels(vec![90.0, 50.0, 70.0])
+            .threads(threads)
+            .build();
+
+        let mut session = EvalSession::new(config);
+        for id in ["b-codec", "a-codec"] {
+            session.add_codec_with_decode(
+                id,
+                "1.0",
+                Box::new(|image, request| {
+                    let mut bytes = vec![image.width() as u8, image.height() as u8];
+                    bytes.extend(image.to_rgb8_vec());
+                    bytes.resize(bytes.len() + request.quality as usize, 0);
+                    Ok(bytes)
+                }),
+                Box::new(|bytes| {
+                    let (width, height) = (usize::from(bytes[0]), usize::from(bytes[1]));
+                    Ok(ImageData::RgbSlice {
+                        data: bytes[2..2 + width * height * 3].to_vec(),
+                        width,
+                        height,
+                    })
+                }),
+            );
+        }
+        session
+    }
+
+    #[test]
+    fn test_evaluate_image_parallel_order() {
+        let session = create_roundtrip_session(4);
+        let report = session
+            .evaluate_image("test", create_test_image(16, 16))
+            .unwrap();
+
+        let order: Vec<(&str, f64)> = report
+            .results
+            .iter()
+            .map(|r| (r.codec_id.as_str(), r.quality))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                ("b-codec", 90.0),
+                ("b-codec", 50.0),
+                ("b-codec", 70.0),
+                ("a-codec", 90.0),
+                ("a-codec", 50.0),
+                ("a-codec", 70.0),
+            ]
+        );
+        assert!(report.results.iter().all(|r| r.decode_time.is_some()));
+        assert!(
+            report
+                .results
+                .iter()
+                .all(|r| r.metrics.psnr == Some(f64::INFINITY))
+        );
+    }
+
+    #[test]
+    fn test_evaluate_corpus_deterministic() {
+        let mut corpus = Corpus::new("synthetic", "/nonexistent");
+        for (name, size) in [("c.png", 24), ("a.png", 8), ("b.png", 16)] {
+            corpus.images.push(crate::corpus::CorpusImage {
+                relative_path: PathBuf::from(name),
+                category: None,
+                width: size,
+                height: size,
+                file_size: 0,
+                checksum: None,
+                format: "png".to_string(),
+            });
+        }
+        let load = |path: &Path| {
+            let size = match path.file_name().and_then(|n| n.to_str()) {
+                Some("c.png") => 24,
+                Some("a.png") => 8,
+                _ => 16,
+            };
+            Ok(create_test_image(size, size))
+        };
+
+        let serial = create_roundtrip_session(1)
+            .evaluate_corpus(&corpus, load)
+            .unwrap();
+        let parallel = create_roundtrip_session(8)
+            .evaluate_corpus(&corpus, load)
+            .unwrap();
+
+        assert_eq!(serial.name, "synthetic");
+        let names: Vec<&str> = parallel.images.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["c.png", "a.png", "b.png"]);
+        assert_eq!(
+            parallel.images[0].source_path,
+            Some(PathBuf::from("/nonexistent/c.png"))
+        );
+        for (s, p) in serial.images.iter().zip(&parallel.images) {
+            let key = |r: &CodecResult| (r.codec_id.clone(), r.quality.to_bits(), r.file_size);
+            assert_eq!(
+                s.results.iter().map(key).collect::<Vec<_>>(),
+                p.results.iter().map(key).collect::<Vec<_>>()
+            );
+        }
+        assert_eq!(parallel.total_results(), 18);
+    }
+
+    #[test]
+    fn test_viewing_simulation_changes_scores() {
+        let evaluate = |viewing: ViewingCondition, mode: SimulationMode| {
+            let config = EvalConfig::builder()
+                .report_dir("/tmp/test")
+                .viewing(viewing)
+                .simulation_mode(mode)
+                .metrics(MetricConfig::fast())
+                .quality_levels(vec![80.0])
+                .build();
+            let mut session = EvalSession::new(config);
+            // Adds pixel-level noise that averages away when downscaled.
+            session.add_codec_with_decode(
+                "noisy",
+                "1.0",
+                Box::new(|image, _| {
+                    Ok(image
+                        .to_rgb8_vec()
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &v)| {
+                            if (i / 3) % 2 == 0 {
+                                v.saturating_add(20)
+                            } else {
+                                v.saturating_sub(20)
+                            }
+                        })
+                        .collect())
+                }),
+                Box::new(|bytes| {
+                    Ok(ImageData::RgbSlice {
+                        data: bytes.to_vec(),
+                        width: 32,
+                        height: 32,
+                    })
+                }),
+            );
+            let image = ImageData::RgbSlice {
+                data: vec![128; 32 * 32 * 3],
+                width: 32,
+                height: 32,
+            };
+            session.evaluate_image("img", image).unwrap().results[0]
+                .metrics
+                .psnr
+                .unwrap()
+        };
+
+        let desktop = evaluate(ViewingCondition::desktop(), SimulationMode::Accurate);
+        let oversized = ViewingCondition::desktop()
+            .with_browser_dppx(1.0)
+            .with_image_intrinsic_dppx(2.0);
+        let downscaled = evaluate(oversized.clone(), SimulationMode::Accurate);
+        assert!(downscaled > desktop, "{downscaled} vs {desktop}");
+        assert!((evaluate(oversized, SimulationMode::DownsampleOnly) - downscaled).abs() < 1e-9);
+
+        // Undersized images are upscaled only in Accurate mode.
+        let undersized = ViewingCondition::desktop()
+            .with_browser_dppx(2.0)
+            .with_image_intrinsic_dppx(1.0);
+        let native = evaluate(undersized, SimulationMode::DownsampleOnly);
+        assert!((native - desktop).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_alpha_aware_metrics() {
+        let (width, height) = (16, 16);
+        // Opaque red on the left half, fully transparent on the right.
+        let rgba: Vec<u8> = (0..width * height)
+            .flat_map(|i| {
+                let alpha = if i % width < width / 2 { 255 } else { 0 };
+                [200, 30, 30, alpha]
+            })
+            .collect();
+        let image = ImageData::RgbaSlice {
+            data: rgba,
+            width,
+            height,
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = EvalConfig::builder()
+            .report_dir(dir.path())
+            .metrics(MetricConfig::fast())
+            .quality_levels(vec![90.0])
+            .build();
+        let mut session = EvalSession::new(config);
+        session.add_codec_with_decode(
+            "keeps-alpha",
+            "1.0",
+            Box::new(|image, _| {
+                let ImageData::RgbaSlice { data, .. } = image else {
+                    unreachable!()
+                };
+                Ok(data.clone())
+            }),
+            Box::new(move |bytes| {
+                Ok(ImageData::RgbaSlice {
+                    data: bytes.to_vec(),
+                    width,
+                    height,
+                })
+            }),
+        );
+        session.add_codec_with_decode(
+            "drops-alpha",
+            "1.0",
+            Box::new(|image, _| Ok(image.to_rgb8_vec())),
+            Box::new(move |bytes| {
+                Ok(ImageData::RgbSlice {
+                    data: bytes.to_vec(),
+                    width,
+                    height,
+                })
+            }),
+        );
+
+        let report = session.evaluate_image("alpha", image).unwrap();
+        let keeps = &report.results[0].metrics;
+        assert_eq!(keeps.alpha_rmse, Some(0.0));
+        assert_eq!(keeps.psnr, Some(f64::INFINITY));
+
+        // Dropping alpha reveals hidden pixels and is penalized on both counts.
+        let drops = &report.results[1].metrics;
+        assert!(drops.alpha_rmse.unwrap() > 100.0);
+        assert!(drops.psnr.unwrap() < 20.0);
+
+        let mut corpus = CorpusReport::new("alpha".to_string());
+        corpus.images.push(report);
+        session.write_corpus_report(&corpus).unwrap();
+        let csv = std::fs::read_to_string(dir.path().join("alpha.csv")).unwrap();
+        let mut lines = csv.lines();
+        assert!(lines.next().unwrap().ends_with(",alpha_rmse"));
+        assert!(lines.next().unwrap().ends_with(",0.0000"));
+    }
+
+    #[test]
+    fn test_content_checksum() {
+        let a = create_test_image(8, 8);
+        let b = ImageData::Rgb8(ImgVec::new(
+            a.to_rgb8_vec()
+                .chunks_exact(3)
+                .map(|c| RGB8::new(c[0], c[1], c[2]))
+                .collect(),
+            8,
+            8,
+        ));
+        // Same pixels in a different container hash the same.
+        assert_eq!(a.content_checksum(), b.content_checksum());
+        assert_ne!(
+            a.content_checksum(),
+            create_test_image(16, 4).content_checksum()
+        );
+        let with_icc = ImageData::RgbSliceWithIcc {
+            data: a.to_rgb8_vec(),
+            width: 8,
+            height: 8,
+            icc_profile: vec![1, 2, 3],
+        };
+        assert_ne!(a.content_checksum(), with_icc.content_checksum());
+    }
+
+    #[test]
+    fn test_timing_policy_repeats_encode_and_decode() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = EvalConfig::builder()
+            .report_dir(dir.path())
+            .metrics(MetricConfig::fast())
+            .quality_levels(vec![80.0])
+            .timing(TimingPolicy::new(1, 3).with_statistic(TimingStatistic::Min))
+            .build();
+        let mut session = EvalSession::new(config);
+        let encodes = Arc::new(AtomicUsize::new(0));
+        let decodes = Arc::new(AtomicUsize::new(0));
+        let (encode_counter, decode_counter) = (Arc::clone(&encodes), Arc::clone(&decodes));
+        session.add_codec_with_decode(
+            "xor",
+            "1.0",
+            Box::new(move |image, _| {
+                encode_counter.fetch_add(1, Ordering::SeqCst);
+                Ok(image.to_rgb8_vec().iter().map(|v| v ^ 1).collect())
+            }),
+            Box::new(move |bytes| {
+                decode_counter.fetch_add(1, Ordering::SeqCst);
+                Ok(ImageData::RgbSlice {
+                    data: bytes.iter().map(|v| v ^ 1).collect(),
+                    width: 8,
+                    height: 8,
+                })
+            }),
+        );
+
+        let mut report = CorpusReport::new("timing".to_string());
+        report.images.push(
+            session
+                .evaluate_image("img", create_test_image(8, 8))
+                .unwrap(),
+        );
+        // One warmup plus three timed runs of each.
+        assert_eq!(encodes.load(Ordering::SeqCst), 4);
+        assert_eq!(decodes.load(Ordering::SeqCst), 4);
+
+        let result = &report.images[0].results[0];
+        let encode = result.encode_timing.as_ref().unwrap();
+        assert_eq!(encode.samples, 3);
+        assert_eq!(result.encode_time, encode.wall_time(TimingStatistic::Min));
+        assert_eq!(result.decode_timing.as_ref().unwrap().samples, 3);
+
+        session.write_corpus_report(&report).unwrap();
+        let csv = std::fs::read_to_string(dir.path().join("timing.csv")).unwrap();
+        assert!(csv.lines().next().unwrap().contains(
+            ",perception,encode_min_ms,encode_mad_ms,encode_cpu_ms,\
+             decode_min_ms,decode_mad_ms,decode_cpu_ms"
+        ));
+    }
+
+    #[test]
+    fn test_measure_memory_records_peaks() {
+        let _lock = memory::MEASURE_LOCK
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        let config = EvalConfig::builder()
+            .report_dir(dir.path())
+            .metrics(MetricConfig::fast())
+            .quality_levels(vec![50.0, 80.0])
+            .measure_memory(true)
+            .build();
+        let mut session = EvalSession::new(config);
+        // Encoding needs a 4 MiB scratch buffer.
+        session.add_codec_with_decode(
+            "hungry",
+            "1.0",
+            Box::new(|image, _| {
+                let scratch = vec![1u8; 4 << 20];
+                std::hint::black_box(&scratch);
+                Ok(image.to_rgb8_vec().iter().map(|v| v ^ 1).collect())
+            }),
+            Box::new(|bytes| {
+                Ok(ImageData::RgbSlice {
+                    data: bytes.iter().map(|v| v ^ 1).collect(),
+                    width: 8,
+                    height: 8,
+                })
+            }),
+        );
+
+        let mut report = CorpusReport::new("memory".to_string());
+        report.images.push(
+            session
+                .evaluate_image("img", create_test_image(8, 8))
+                .unwrap(),
+        );
+        for result in &report.images[0].results {
+            assert!(result.encode_peak_bytes.unwrap() >= 4 << 20);
+            assert!(result.decode_peak_bytes.unwrap() >= 8 * 8 * 3);
+        }
+
+        let summary = &report.memory_summary()["hungry"];
+        assert_eq!(summary.encode.unwrap().samples, 2);
+        assert!(summary.encode.unwrap().max_bytes >= 4 << 20);
+        assert!(summary.decode.is_some());
+
+        session.write_corpus_report(&report).unwrap();
+        let csv = std::fs::read_to_string(dir.path().join("memory.csv")).unwrap();
+        assert!(
+            csv.lines()
+                .next()
+                .unwrap()
+                .contains(",perception,encode_peak_bytes,decode_peak_bytes")
+        );
+    }
+
+    #[test]
+    fn test_encode_cache_reuses_bitstreams() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let cache_dir = tempfile::tempdir().unwrap();
+        let encodes = Arc::new(AtomicUsize::new(0));
+        let make_session = |metrics: MetricConfig| {
+            let config = EvalConfig::builder()
+                .report_dir("/tmp/test")
+                .cache_dir(cache_dir.path())
+                .metrics(metrics)
+                .quality_levels(vec![50.0, 80.0])
+                .build();
+            let mut session = EvalSession::new(config);
+            let counter = Arc::clone(&encodes);
+            session.add_codec(
+                "counting",
+                "1.0",
+                Box::new(move |image, request| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    let mut bytes: Vec<u8> = image.to_rgb8_vec().iter().map(|v| v ^ 1).collect();
+                    bytes.truncate(request.quality as usize);
+                    Ok(bytes)
+                }),
+            );
+            session
+        };
+
+        let image = create_test_image(8, 8);
+        let first = make_session(MetricConfig::fast())
+            .evaluate_image("img", image.clone())
+            .unwrap();
+        assert_eq!(encodes.load(Ordering::SeqCst), 2);
+
+        // Different metric config, same encodes: nothing is re-encoded.
+        let second = make_session(MetricConfig::perceptual())
+            .evaluate_image("img", image)
+            .unwrap();
+        assert_eq!(encodes.load(Ordering::SeqCst), 2);
+
+        for (a, b) in first.results.iter().zip(&second.results) {
+            let path = a.cached_path.as_ref().unwrap();
+            assert_eq!(Some(path), b.cached_path.as_ref());
+            assert_eq!(std::fs::read(path).unwrap().len(), b.file_size);
+            assert_eq!(a.encode_time.as_millis(), b.encode_time.as_millis());
+            let (a, b) = (a.encode_timing.as_ref(), b.encode_timing.as_ref());
+            assert!((a.unwrap().wall.median_ms - b.unwrap().wall.median_ms).abs() < 1e-9);
+        }
+
+        // A different source image misses the cache.
+        make_session(MetricConfig::fast())
+            .evaluate_image("other", create_test_image(8, 9))
+            .unwrap();
+        assert_eq!(encodes.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn test_encode_cache_retimes_under_another_policy() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let cache_dir = tempfile::tempdir().unwrap();
+        let encodes = Arc::new(AtomicUsize::new(0));
+        let make_session = |timing: TimingPolicy| {
+            let config = EvalConfig::builder()
+                .report_dir("/tmp/test")
+                .cache_dir(cache_dir.path())
+                .metrics(MetricConfig::fast())
+                .quality_levels(vec![50.0])
+                .timing(timing)
+                .build();
+            let mut session = EvalSession::new(config);
+            let counter = Arc::clone(&encodes);
+            session.add_codec(
+                "counting",
+                "1.0",
+                Box::new(move |image, _| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    Ok(image.to_rgb8_vec())
+                }),
+            );
+            session
+        };
+        let image = create_test_image(8, 8);
+        let samples =
+            |report: &ImageReport| report.results[0].encode_timing.as_ref().unwrap().samples;
+
+        let first = make_session(TimingPolicy::default())
+            .evaluate_image("img", image.clone())
+            .unwrap();
+        assert_eq!(samples(&first), 1);
+        assert_eq!(encodes.load(Ordering::SeqCst), 1);
+
+        // The cached single run does not stand in for ten repetitions.
+        let repeated = make_session(TimingPolicy::new(2, 10))
+            .evaluate_image("img", image.clone())
+            .unwrap();
+        assert_eq!(samples(&repeated), 10);
+        assert_eq!(encodes.load(Ordering::SeqCst), 13);
+
+        // The entry now holds the repeated timing and is reused as such.
+        let cached = make_session(TimingPolicy::new(2, 10))
+            .evaluate_image("img", image)
+            .unwrap();
+        assert_eq!(samples(&cached), 10);
+        assert_eq!(encodes.load(Ordering::SeqCst), 13);
+    }
+
+    #[test]
+    fn test_encode_cache_remeasures_memory() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let _lock = memory::MEASURE_LOCK
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let cache_dir = tempfile::tempdir().unwrap();
+        let encodes = Arc::new(AtomicUsize::new(0));
+        let run = |measure_memory: bool| {
+            let config = EvalConfig::builder()
+                .report_dir("/tmp/test")
+                .cache_dir(cache_dir.path())
+                .metrics(MetricConfig::fast())
+                .quality_levels(vec![50.0])
+                .measure_memory(measure_memory)
+                .build();
+            let mut session = EvalSession::new(config);
+            let counter = Arc::clone(&encodes);
+            session.add_codec(
+                "counting",
+                "1.0",
+                Box::new(move |image, _| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    Ok(image.to_rgb8_vec())
+                }),
+            );
+            let report = session
+                .evaluate_image("img", create_test_image(8, 8))
+                .unwrap();
+            report.results[0].encode_peak_bytes
+        };
+
+        assert_eq!(run(false), None);
+        assert_eq!(encodes.load(Ordering::SeqCst), 1);
+
+        // The entry has no peak, so the encode is measured again.
+        assert!(run(true).is_some());
+        assert_eq!(encodes.load(Ordering::SeqCst), 2);
+        assert!(run(true).is_some());
+        assert_eq!(encodes.load(Ordering::SeqCst), 2);
+
+        // A stored peak is not reported when memory is not measured.
+        assert_eq!(run(false), None);
+        assert_eq!(encodes.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_settings_fingerprint_fields() {
+        let config = || EvalConfig::builder().report_dir("reports").build();
+        let fingerprint = |config: EvalConfig| EvalSession::new(config).settings_fingerprint();
+        let base = fingerprint(config());
+        assert_eq!(base, fingerprint(config()));
+
+        let mut timed = config();
+        timed.timing = TimingPolicy::new(1, 5);
+        assert_ne!(base, fingerprint(timed));
+
+        let mut measured = config();
+        measured.measure_memory = true;
+        assert_ne!(base, fingerprint(measured));
+
+        let mut ciede2000 = config();
+        ciede2000.metrics.ciede2000 = !ciede2000.metrics.ciede2000;
+        assert_ne!(base, fingerprint(ciede2000));
+    }
+
+    #[test]
+    fn test_journal_resumes_corpus_run() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut corpus = Corpus::new("resume", "/nonexistent");
+        for name in ["a.png", "b.png"] {
+            corpus.images.push(crate::corpus::CorpusImage {
+                relative_path: PathBuf::from(name),
+                category: None,
+                width: 8,
+                height: 8,
+                file_size: 0,
+                checksum: None,
+                format: "png".to_string(),
+            });
+        }
+        let encodes = Arc::new(AtomicUsize::new(0));
+        let loads = AtomicUsize::new(0);
+        let load = |_: &Path| {
+            loads.fetch_add(1, Ordering::SeqCst);
+            Ok(create_test_image(8, 8))
+        };
+        let session = |levels: Vec<f64>, metrics: MetricConfig| {
+            let config = EvalConfig::builder()
+                .report_dir(dir.path())
+                .metrics(metrics)
+                .quality_levels(levels)
+                .journal(true)
+                .build();
+            let mut session = EvalSession::new(config);
+            let counter = Arc::clone(&encodes);
+            session.add_codec_with_decode(
+                "counted",
+                "1.0",
+                Box::new(move |image, request| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    let mut bytes: Vec<u8> = image.to_rgb8_vec().iter().map(|v| v ^ 1).collect();
+                    bytes.resize(bytes.len() + request.quality as usize, 0);
+                    Ok(bytes)
+                }),
+                Box::new(|bytes| {
+                    Ok(ImageData::RgbSlice {
+                        data: bytes[..8 * 8 * 3].to_vec(),
+                        width: 8,
+                        height: 8,
+                    })
+                }),
+            );
+            session
+        };
+
+        // An interrupted run that only got through two quality levels.
+        session(vec![50.0, 70.0], MetricConfig::fast())
+            .evaluate_corpus(&corpus, load)
+            .unwrap();
+        assert_eq!(encodes.load(Ordering::SeqCst), 4);
+
+        // The restart only runs the missing level.
+        let resumed = session(vec![50.0, 70.0, 90.0], MetricConfig::fast())
+            .evaluate_corpus(&corpus, load)
+            .unwrap();
+        assert_eq!(encodes.load(Ordering::SeqCst), 6);
+        let qualities: Vec<f64> = resumed.images[1]
+            .results
+            .iter()
+            .map(|r| r.quality)
+            .collect();
+        assert_eq!(qualities, vec![50.0, 70.0, 90.0]);
+        assert_eq!(loads.load(Ordering::SeqCst), 4);
+
+        // A complete journal rebuilds the report without loading any image.
+        let rebuilt = session(vec![50.0, 70.0, 90.0], MetricConfig::fast())
+            .evaluate_corpus(&corpus, load)
+            .unwrap();
+        assert_eq!(encodes.load(Ordering::SeqCst), 6);
+        assert_eq!(loads.load(Ordering::SeqCst), 4);
+        assert_eq!(rebuilt.total_results(), 6);
+        assert_eq!(rebuilt.images[0].width, 8);
+        assert_eq!(
+            rebuilt.images[1].results[2].metrics.psnr,
+            resumed.images[1].results[2].metrics.psnr
+        );
+
+        // Different metric settings must not reuse the journal.
+        let err = session(vec![50.0], MetricConfig::perceptual())
+            .evaluate_corpus(&corpus, load)
+            .unwrap_err();
+        assert!(matches!(err, Error::Report(_)), "{err}");
+    }
+
+    #[test]
+    fn test_evaluate_corpus_load_error() {
+        let mut corpus = Corpus::new("broken", "/nonexistent");
+        corpus.images.push(crate::corpus::CorpusImage {
+            relative_path: PathBuf::from("missing.png"),
+            category: None,
+            width: 8,
+            height: 8,
+            file_size: 0,
+            checksum: None,
+            format: "png".to_string(),
+        });
+
+        let load = |path: &Path| {
+            Err(Error::ImageLoad {
+                path: path.to_path_buf(),
+                reason: "not found".to_string(),
+            })
+        };
+        let mut session = create_roundtrip_session(2);
+        let report = session.evaluate_corpus(&corpus, load).unwrap();
+        assert!(report.images[0].results.is_empty());
+        assert_eq!(report.images[0].failures[0].kind, "image_load");
+        assert_eq!(report.failure_summary().total, 1);
+
+        session.config.fail_fast = true;
+        let result = session.evaluate_corpus(&corpus, load);
+        assert!(matches!(result, Err(Error::ImageLoad { .. })));
+    }
 }
@@ -0,0 +1,238 @@
+//! Repeated encode/decode timing.
+//!
+//! A single wall-clock measurement is dominated by noise: page faults on the
+//! first run, frequency scaling, other processes. With a [`TimingPolicy`] the
+//! session runs each encode and decode a few times untimed (warmup), then
+//! times `repetitions` runs and records the median, minimum and median
+//! absolute deviation in a [`Timing`]. Process CPU time can be measured
+//! alongside wall time ([`TimingPolicy::with_cpu_time`]), so an encoder that
+//! spreads work over many threads can be compared with a single-threaded one
+//! on total work as well as latency.
+
+use std::time::{Duration, Instant};
+
+use cpu_time::ProcessTime;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::stats::median;
+
+/// Which statistic of the timed runs is reported as
+/// [`CodecResult::encode_time`](super::CodecResult::encode_time) and
+/// `decode_time`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimingStatistic {
+    /// Median of the timed runs.
+    #[default]
+    Median,
+    /// Fastest timed run.
+    Min,
+}
+
+/// How encodes and decodes are timed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimingPolicy {
+    /// Untimed runs before the timed ones.
+    pub warmup: usize,
+    /// Timed runs (at least 1).
+    pub repetitions: usize,
+    /// Statistic reported as the encode/decode time.
+    pub statistic: TimingStatistic,
+    /// Also measure process CPU time. It counts every thread in the
+    /// process, so only enable it when jobs run one at a time.
+    pub cpu_time: bool,
+}
+
+impl Default for TimingPolicy {
+    /// One timed run and no warmup, i.e. every job encodes and decodes once.
+    fn default() -> Self {
+        Self {
+            warmup: 0,
+            repetitions: 1,
+            statistic: TimingStatistic::Median,
+            cpu_time: false,
+        }
+    }
+}
+
+impl TimingPolicy {
+    /// `warmup` untimed runs followed by `repetitions` timed runs, reporting
+    /// the median.
+    #[must_use]
+    pub fn new(warmup: usize, repetitions: usize) -> Self {
+        Self {
+            warmup,
+            repetitions: repetitions.max(1),
+            statistic: TimingStatistic::Median,
+            cpu_time: false,
+        }
+    }
+
+    /// Set the reported statistic.
+    #[must_use]
+    pub fn with_statistic(mut self, statistic: TimingStatistic) -> Self {
+        self.statistic = statistic;
+        self
+    }
+
+    /// Also measure process CPU time.
+    #[must_use]
+    pub fn with_cpu_time(mut self) -> Self {
+        self.cpu_time = true;
+        self
+    }
+
+    /// Whether `timing` has the shape this policy produces: as many timed
+    /// runs, and CPU time exactly when it is enabled.
+    pub(crate) fn produced(&self, timing: &Timing) -> bool {
+        timing.samples == self.repetitions.max(1) && timing.cpu.is_some() == self.cpu_time
+    }
+
+    /// Run `f` per the policy and return the output of the last run together
+    /// with its timing. Stops at the first error.
+    pub(crate) fn measure<T>(&self, mut f: impl FnMut() -> Result<T>) -> Result<(T, Timing)> {
+        for _ in 0..self.warmup {
+            f()?;
+        }
+
+        let repetitions = self.repetitions.max(1);
+        let mut wall = Vec::with_capacity(repetitions);
+        let mut cpu = Vec::with_capacity(repetitions);
+        let mut output = None;
+        for _ in 0..repetitions {
+            let cpu_start = self
+                .cpu_time
+                .then(ProcessTime::try_now)
+                .and_then(std::result::Result::ok);
+            let start = Instant::now();
+            output = Some(f()?);
+            wall.push(start.elapsed());
+            if let Some(elapsed) = cpu_start.and_then(|t| t.try_elapsed().ok()) {
+                cpu.push(elapsed);
+            }
+        }
+
+        let timing = Timing {
+            samples: repetitions,
+            wall: TimingSummary::from_samples(&wall),
+            // CPU time is all-or-nothing, so the summaries cover the same runs.
+            cpu: (self.cpu_time && cpu.len() == repetitions)
+                .then(|| TimingSummary::from_samples(&cpu)),
+        };
+        Ok((output.expect("at least one repetition"), timing))
+    }
+}
+
+/// Timing of one encode or decode over [`TimingPolicy::repetitions`] runs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Timing {
+    /// Number of timed runs.
+    pub samples: usize,
+    /// Wall-clock time.
+    pub wall: TimingSummary,
+    /// Process CPU time, summed over all threads. `None` unless
+    /// [`TimingPolicy::cpu_time`] is set, or where the platform does not
+    /// report it.
+    ///
+    /// This counts every thread in the process, so it is only attributable
+    /// to a single job when jobs run one at a time (`threads(1)`).
+    pub cpu: Option<TimingSummary>,
+}
+
+impl Timing {
+    /// The wall-clock `statistic`.
+    #[must_use]
+    pub fn wall_time(&self, statistic: TimingStatistic) -> Duration {
+        Duration::from_secs_f64(self.wall.get(statistic).max(0.0) / 1000.0)
+    }
+}
+
+/// Summary statistics of repeated measurements, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TimingSummary {
+    /// Median run.
+    pub median_ms: f64,
+    /// Fastest run.
+    pub min_ms: f64,
+    /// Median absolute deviation from the median.
+    pub mad_ms: f64,
+}
+
+impl TimingSummary {
+    /// Summarise a non-empty set of samples.
+    #[must_use]
+    pub fn from_samples(samples: &[Duration]) -> Self {
+        let ms: Vec<f64> = samples.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+        let median_ms = median(&ms);
+        let deviations: Vec<f64> = ms.iter().map(|v| (v - median_ms).abs()).collect();
+        Self {
+            median_ms,
+            min_ms: ms.iter().copied().reduce(f64::min).unwrap_or(0.0),
+            mad_ms: median(&deviations),
+        }
+    }
+
+    /// The value of `statistic`.
+    #[must_use]
+    pub fn get(&self, statistic: TimingStatistic) -> f64 {
+        match statistic {
+            TimingStatistic::Median => self.median_ms,
+            TimingStatistic::Min => self.min_ms,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_statistics() {
+        let samples = [10, 12, 11, 30, 9].map(Duration::from_millis);
+        let summary = TimingSummary::from_samples(&samples);
+        assert_eq!(summary.median_ms, 11.0);
+        assert_eq!(summary.min_ms, 9.0);
+        // Deviations 1, 1, 0, 19, 2: the outlier does not move the MAD.
+        assert_eq!(summary.mad_ms, 1.0);
+
+        let even = TimingSummary::from_samples(&[2, 4].map(Duration::from_millis));
+        assert_eq!(even.median_ms, 3.0);
+        assert_eq!(even.get(TimingStatistic::Min), 2.0);
+    }
+
+    #[test]
+    fn test_measure_runs_warmup_and_repetitions() {
+        let mut runs = 0;
+        let (last, timing) = TimingPolicy::new(2, 3)
+            .measure(|| {
+                runs += 1;
+                Ok(runs)
+            })
+            .unwrap();
+        assert_eq!(runs, 5);
+        assert_eq!(last, 5);
+        assert_eq!(timing.samples, 3);
+        assert!(timing.wall.min_ms <= timing.wall.median_ms);
+        assert!(timing.cpu.is_none());
+
+        let ((), timing) = TimingPolicy::default()
+            .with_cpu_time()
+            .measure(|| Ok(()))
+            .unwrap();
+        assert_eq!(timing.cpu.is_some(), ProcessTime::try_now().is_ok());
+    }
+
+    #[test]
+    fn test_measure_stops_at_error() {
+        let mut runs = 0;
+        let result: Result<((), Timing)> = TimingPolicy::new(1, 5).measure(|| {
+            runs += 1;
+            Err(crate::error::Error::Codec {
+                codec: "c".to_string(),
+                message: "boom".to_string(),
+            })
+        });
+        assert!(result.is_err());
+        assert_eq!(runs, 1);
+    }
+}
@@ -0,0 +1,573 @@
+//! Metric validation against subjective scores.
+//!
+//! A subjective dataset pairs reference and distorted images with a score
+//! from human viewers: a mean opinion score (MOS), differential MOS or JND
+//! count. [`EvalSession::validate`] scores every pair with the session's
+//! metrics and viewing conditions, so each metric's agreement with the
+//! viewers can be measured with [`ValidationReport::correlations`] and the
+//! [`PerceptionLevel`](crate::metrics::PerceptionLevel) thresholds can be
+//! fitted to the data with [`ValidationReport::fit_thresholds`].
+//!
+//! ```rust,ignore
+//! let dataset = MosDataset::from_csv("kadid10k/dmos.csv")?;
+//! let report = session.validate(&dataset, |path| load_png(path))?;
+//! for m in report.correlations(&Bootstrap::default()) {
+//!     let s = m.correlation.srocc;
+//!     println!("{}: SROCC {:.3} [{:.3}, {:.3}]", m.metric, s.value, s.lower, s.upper);
+//! }
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::session::{EvalSession, ImageData};
+use crate::error::{Error, Result};
+use crate::metrics::custom::{BUILTIN_METRICS, builtin_direction};
+use crate::metrics::{MetricResult, PerceptionThresholds};
+use crate::stats::{Bootstrap, CorrelationSummary, QualityDirection, correlate};
+
+/// Column names recognized by [`MosDataset::from_csv`], case-insensitive.
+const REFERENCE_COLUMNS: &[&str] = &["reference", "ref", "ref_img", "original", "source"];
+const DISTORTED_COLUMNS: &[&str] = &["distorted", "dist", "dist_img", "image", "test"];
+/// Score columns where higher means better quality.
+const MOS_COLUMNS: &[&str] = &["mos", "score", "rating"];
+/// Score columns where higher means more visible distortion.
+const DISTORTION_COLUMNS: &[&str] = &["dmos", "jnd", "dscore"];
+
+/// A subjective quality dataset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MosDataset {
+    /// Dataset name, used in reports.
+    pub name: String,
+    /// Whether higher subjective scores mean better quality (MOS) or more
+    /// visible distortion (DMOS, JND).
+    pub direction: QualityDirection,
+    /// Rated image pairs.
+    pub pairs: Vec<RatedPair>,
+}
+
+/// A distorted image, its reference and its subjective score.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RatedPair {
+    /// Path of the reference image.
+    pub reference: PathBuf,
+    /// Path of the distorted image.
+    pub distorted: PathBuf,
+    /// Subjective score.
+    pub score: f64,
+}
+
+impl MosDataset {
+    /// Create an empty dataset.
+    #[must_use]
+    pub fn new(name: &str, direction: QualityDirection) -> Self {
+        Self {
+            name: name.to_string(),
+            direction,
+            pairs: Vec::new(),
+        }
+    }
+
+    /// Add a rated pair.
+    #[must_use]
+    pub fn with_pair(
+        mut self,
+        reference: impl Into<PathBuf>,
+        distorted: impl Into<PathBuf>,
+        score: f64,
+    ) -> Self {
+        self.pairs.push(RatedPair {
+            reference: reference.into(),
+            distorted: distorted.into(),
+            score,
+        });
+        self
+    }
+
+    /// Load a dataset from a CSV file with one row per distorted image.
+    ///
+    /// Columns are found by name: the reference image (`reference`, `ref`,
+    /// `ref_img`, `original` or `source`), the distorted image
+    /// (`distorted`, `dist`, `dist_img`, `image` or `test`) and the score.
+    /// A `mos`, `score` or `rating` column is read as higher-is-better; a
+    /// `dmos`, `jnd` or `dscore` column as lower-is-better. Relative image
+    /// paths are resolved against the CSV's directory. The dataset is named
+    /// after the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, a column is missing, or
+    /// a score is not a number.
+    pub fn from_csv(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut reader = csv::Reader::from_path(path)?;
+        let headers: Vec<String> = reader.headers()?.iter().map(str::to_lowercase).collect();
+        let find = |aliases: &[&str]| {
+            aliases
+                .iter()
+                .find_map(|alias| headers.iter().position(|h| h == alias))
+        };
+        let missing = |what: &str| Error::CsvImport {
+            line: 0,
+            reason: format!("Could not find {what} column"),
+        };
+        let reference_idx = find(REFERENCE_COLUMNS).ok_or_else(|| missing("reference image"))?;
+        let distorted_idx = find(DISTORTED_COLUMNS).ok_or_else(|| missing("distorted image"))?;
+        let (score_idx, direction) = match (find(MOS_COLUMNS), find(DISTORTION_COLUMNS)) {
+            (Some(i), _) => (i, QualityDirection::HigherIsBetter),
+            (None, Some(i)) => (i, QualityDirection::LowerIsBetter),
+            (None, None) => return Err(missing("mos/dmos/jnd score")),
+        };
+
+        let name = path
+            .file_stem()
+            .map_or_else(String::new, |s| s.to_string_lossy().into_owned());
+        let root = path.parent().unwrap_or(Path::new(""));
+        let mut dataset = Self::new(&name, direction);
+        for (line_num, record) in reader.records().enumerate() {
+            let line = line_num + 2; // +2 for 1-based and header
+            let record = record.map_err(|e| Error::CsvImport {
+                line,
+                reason: e.to_string(),
+            })?;
+            let field = |i: usize| record.get(i).unwrap_or("").trim();
+            let score = field(score_idx).parse().map_err(|_| Error::CsvImport {
+                line,
+                reason: format!("Invalid score: {:?}", field(score_idx)),
+            })?;
+            dataset.pairs.push(RatedPair {
+                reference: root.join(field(reference_idx)),
+                distorted: root.join(field(distorted_idx)),
+                score,
+            });
+        }
+        Ok(dataset)
+    }
+
+    /// Override the direction of the subjective scores.
+    #[must_use]
+    pub fn with_direction(mut self, direction: QualityDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+}
+
+/// Metric scores of every pair of a [`MosDataset`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationReport {
+    /// Dataset name.
+    pub dataset: String,
+    /// Direction of the subjective scores.
+    pub direction: QualityDirection,
+    /// One entry per rated pair, in dataset order.
+    pub samples: Vec<ValidationSample>,
+    /// Directions of the session's custom metrics.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub custom_directions: BTreeMap<String, QualityDirection>,
+}
+
+/// A rated pair with its metric scores.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationSample {
+    /// The pair and its subjective score.
+    #[serde(flatten)]
+    pub pair: RatedPair,
+    /// Scores of the distorted image against the reference.
+    pub metrics: MetricResult,
+}
+
+/// Agreement of one metric with the subjective scores.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricCorrelation {
+    /// Metric name, as used by [`MetricResult::value`].
+    pub metric: String,
+    /// Correlations, signed so that positive means the metric agrees with
+    /// the viewers whatever the direction of either scale.
+    pub correlation: CorrelationSummary,
+}
+
+/// [`PerceptionThresholds`] fitted to subjective scores.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FittedThresholds {
+    /// Metric name.
+    pub metric: String,
+    /// Fitted thresholds.
+    pub thresholds: PerceptionThresholds,
+    /// Per threshold, the share of samples it puts on the same side as the
+    /// corresponding subjective boundary.
+    pub accuracy: [f64; 4],
+    /// Number of samples the fit used.
+    pub samples: usize,
+}
+
+impl ValidationReport {
+    /// Direction of a built-in or custom metric.
+    #[must_use]
+    pub fn metric_direction(&self, metric: &str) -> Option<QualityDirection> {
+        builtin_direction(metric).or_else(|| self.custom_directions.get(metric).copied())
+    }
+
+    /// Names of the metrics scored on at least one sample: built-in ones
+    /// first, then custom ones alphabetically.
+    #[must_use]
+    pub fn metric_names(&self) -> Vec<String> {
+        let scored = |name: &str| self.samples.iter().any(|s| s.metrics.value(name).is_some());
+        let custom: BTreeSet<&String> = self
+            .samples
+            .iter()
+            .flat_map(|s| s.metrics.custom.keys())
+            .collect();
+        BUILTIN_METRICS
+            .iter()
+            .map(|(name, _)| *name)
+            .filter(|name| scored(name))
+            .map(String::from)
+            .chain(custom.into_iter().cloned())
+            .collect()
+    }
+
+    /// SROCC, PLCC and KROCC of `metric` against the subjective scores.
+    ///
+    /// Samples without a finite score (e.g. PSNR of identical images) are
+    /// left out. Returns `None` for unknown metrics or fewer than two
+    /// samples.
+    #[must_use]
+    pub fn correlation(&self, metric: &str, bootstrap: &Bootstrap) -> Option<CorrelationSummary> {
+        let (subjective, objective) = self.oriented(metric)?;
+        (subjective.len() >= 2).then(|| correlate(&objective, &subjective, bootstrap))
+    }
+
+    /// [`correlation`](Self::correlation) of every scored metric, best SROCC
+    /// first.
+    #[must_use]
+    pub fn correlations(&self, bootstrap: &Bootstrap) -> Vec<MetricCorrelation> {
+        let mut correlations: Vec<MetricCorrelation> = self
+            .metric_names()
+            .into_iter()
+            .filter_map(|metric| {
+                let correlation = self.correlation(&metric, bootstrap)?;
+                Some(MetricCorrelation {
+                    metric,
+                    correlation,
+                })
+            })
+            .collect();
+        // NaN (constant scores) sorts last.
+        let key = |c: &MetricCorrelation| {
+            let srocc = c.correlation.srocc.value;
+            if srocc.is_nan() {
+                f64::NEG_INFINITY
+            } else {
+                srocc
+            }
+        };
+        correlations.sort_by(|a, b| key(b).total_cmp(&key(a)));
+        correlations
+    }
+
+    /// Fit [`PerceptionThresholds`] for `metric` to the subjective scores.
+    ///
+    /// `boundaries` are the subjective scores separating Imperceptible from
+    /// Marginal, Marginal from Subtle, Subtle from Noticeable and Noticeable
+    /// from Degraded, on the dataset's own scale (e.g. `[0.5, 1.0, 2.0, 3.0]`
+    /// JND). Each metric threshold is placed where it agrees with the most
+    /// samples on which side of its boundary they fall; thresholds are then
+    /// made monotonic so every level stays reachable in order.
+    ///
+    /// Returns `None` for unknown metrics or fewer than two samples.
+    #[must_use]
+    pub fn fit_thresholds(&self, metric: &str, boundaries: [f64; 4]) -> Option<FittedThresholds> {
+        let (subjective, objective) = self.oriented(metric)?;
+        if subjective.len() < 2 {
+            return None;
+        }
+        let direction = self.metric_direction(metric)?;
+
+        let mut order: Vec<usize> = (0..objective.len()).collect();
+        order.sort_by(|&a, &b| objective[a].total_cmp(&objective[b]));
+        let mut bounds = [0.0; 4];
+        let mut accuracy = [0.0; 4];
+        for (k, &boundary) in boundaries.iter().enumerate() {
+            let boundary = self.direction.higher_is_better(boundary);
+            let better: Vec<bool> = order.iter().map(|&i| subjective[i] > boundary).collect();
+            let (threshold, correct) = best_split(&order, &objective, &better);
+            bounds[k] = threshold;
+            accuracy[k] = correct as f64 / order.len() as f64;
+        }
+        // Better levels need higher oriented scores.
+        for k in 1..4 {
+            bounds[k] = bounds[k].min(bounds[k - 1]);
+        }
+
+        Some(FittedThresholds {
+            metric: metric.to_string(),
+            thresholds: PerceptionThresholds {
+                direction,
+                bounds: bounds.map(|b| direction.higher_is_better(b)),
+            },
+            accuracy,
+            samples: order.len(),
+        })
+    }
+
+    /// Subjective and metric scores of the samples with a finite `metric`
+    /// score, both mapped so that higher is better.
+    fn oriented(&self, metric: &str) -> Option<(Vec<f64>, Vec<f64>)> {
+        let direction = self.metric_direction(metric)?;
+        Some(
+            self.samples
+                .iter()
+                .filter_map(|s| {
+                    let value = s.metrics.value(metric).filter(|v| v.is_finite())?;
+                    Some((
+                        self.direction.higher_is_better(s.pair.score),
+                        direction.higher_is_better(value),
+                    ))
+                })
+                .unzip(),
+        )
+    }
+}
+
+/// Threshold on `scores` (higher is better) that best predicts `better`,
+/// and how many samples it classifies correctly. `order` sorts `scores`
+/// ascending and `better` is indexed like `order`.
+fn best_split(order: &[usize], scores: &[f64], better: &[bool]) -> (f64, usize) {
+    // Start with everything above the threshold: all `better` are right.
+    let mut correct = better.iter().filter(|&&b| b).count();
+    let mut best = (scores[order[0]] - 1.0, correct);
+    for (pos, &i) in order.iter().enumerate() {
+        correct = if better[pos] {
+            correct - 1
+        } else {
+            correct + 1
+        };
+        // Only split between distinct scores.
+        let next = order.get(pos + 1).map(|&j| scores[j]);
+        if next == Some(scores[i]) {
+            continue;
+        }
+        if correct > best.1 {
+            let threshold = next.map_or(scores[i], |next| f64::midpoint(scores[i], next));
+            best = (threshold, correct);
+        }
+    }
+    best
+}
+
+impl EvalSession {
+    /// Score every pair of a subjective dataset with the session's metrics.
+    ///
+    /// No codec runs: each distorted image is scored against its reference
+    /// exactly as a decoded image would be, with the configured viewing
+    /// conditions, alpha backgrounds, ICC conversion and custom metrics.
+    /// `load` is called once per reference and once per distorted image.
+    /// Pairs are scored in parallel on the session's thread pool.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from `load` or from scoring, e.g. a distorted
+    /// image whose size differs from its reference, or [`Error::Cancelled`]
+    /// if the session's cancellation token is cancelled.
+    pub fn validate<F>(&self, dataset: &MosDataset, load: F) -> Result<ValidationReport>
+    where
+        F: Fn(&Path) -> Result<ImageData> + Sync,
+    {
+        let mut groups: BTreeMap<&Path, Vec<usize>> = BTreeMap::new();
+        for (i, pair) in dataset.pairs.iter().enumerate() {
+            groups.entry(&pair.reference).or_default().push(i);
+        }
+        let cancel = self.cancellation_token();
+
+        let scored = self.install(|| {
+            groups
+                .par_iter()
+                .map(|(reference_path, indices)| {
+                    if cancel.is_cancelled() {
+                        return Err(Error::Cancelled);
+                    }
+                    let reference = load(reference_path)?;
+                    let name = reference_path.to_string_lossy();
+                    let prepared = self.prepare(&name, &reference);
+                    indices
+                        .par_iter()
+                        .map(|&i| {
+                            let distorted = load(&dataset.pairs[i].distorted)?;
+                            let (metrics, _) = self.score_decoded(&prepared, &distorted)?;
+                            Ok((i, metrics))
+                        })
+                        .collect::<Result<Vec<_>>>()
+                })
+                .collect::<Result<Vec<_>>>()
+        })??;
+
+        let mut metrics: Vec<Option<MetricResult>> = vec![None; dataset.pairs.len()];
+        for (i, result) in scored.into_iter().flatten() {
+            metrics[i] = Some(result);
+        }
+        let samples: Vec<ValidationSample> = dataset
+            .pairs
+            .iter()
+            .zip(metrics)
+            .map(|(pair, metrics)| ValidationSample {
+                pair: pair.clone(),
+                metrics: metrics.unwrap_or_default(),
+            })
+            .collect();
+        let custom_directions = samples
+            .iter()
+            .flat_map(|s| s.metrics.custom.keys())
+            .filter_map(|name| Some((name.clone(), self.metric_direction(name)?)))
+            .collect();
+
+        Ok(ValidationReport {
+            dataset: dataset.name.clone(),
+            direction: dataset.direction,
+            samples,
+            custom_directions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::EvalConfig;
+    use crate::metrics::{MetricConfig, PerceptionLevel};
+
+    fn sample(score: f64, butteraugli: f64) -> ValidationSample {
+        ValidationSample {
+            pair: RatedPair {
+                reference: PathBuf::from("ref.png"),
+                distorted: PathBuf::from(format!("{score}.png")),
+                score,
+            },
+            metrics: MetricResult {
+                butteraugli: Some(butteraugli),
+                ..MetricResult::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_from_csv_resolves_paths_and_direction() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tid.csv");
+        std::fs::write(&path, "Ref_Img,Dist_Img,DMOS\na.png,a_01.png,1.5\n").unwrap();
+        let dataset = MosDataset::from_csv(&path).unwrap();
+        assert_eq!(dataset.name, "tid");
+        assert_eq!(dataset.direction, QualityDirection::LowerIsBetter);
+        assert_eq!(dataset.pairs[0].reference, dir.path().join("a.png"));
+        assert_eq!(dataset.pairs[0].distorted, dir.path().join("a_01.png"));
+        assert_eq!(dataset.pairs[0].score, 1.5);
+
+        std::fs::write(&path, "reference,distorted,mos\na.png,b.png,high\n").unwrap();
+        let err = MosDataset::from_csv(&path).unwrap_err();
+        assert!(matches!(err, Error::CsvImport { line: 2, .. }), "{err}");
+        std::fs::write(&path, "reference,distorted\n").unwrap();
+        assert!(MosDataset::from_csv(&path).is_err());
+    }
+
+    #[test]
+    fn test_correlations_are_oriented() {
+        // DMOS (lower is better) against Butteraugli (lower is better):
+        // agreeing scales give positive correlations.
+        let report = ValidationReport {
+            dataset: "test".to_string(),
+            direction: QualityDirection::LowerIsBetter,
+            samples: (0..20)
+                .map(|i| sample(f64::from(i), f64::from(i) * 0.5))
+                .collect(),
+            custom_directions: BTreeMap::new(),
+        };
+        let correlations = report.correlations(&Bootstrap::default());
+        assert_eq!(correlations.len(), 1);
+        assert_eq!(correlations[0].metric, "butteraugli");
+        let srocc = correlations[0].correlation.srocc;
+        assert!((srocc.value - 1.0).abs() < 1e-12, "{srocc:?}");
+        assert!(
+            report
+                .correlation("ssimulacra2", &Bootstrap::default())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_fit_thresholds_recovers_boundaries() {
+        // JND-like scores; Butteraugli is twice the score.
+        let report = ValidationReport {
+            dataset: "test".to_string(),
+            direction: QualityDirection::LowerIsBetter,
+            samples: (0..80)
+                .map(|i| {
+                    let score = f64::from(i) / 10.0;
+                    sample(score, score * 2.0)
+                })
+                .collect(),
+            custom_directions: BTreeMap::new(),
+        };
+        let fitted = report
+            .fit_thresholds("butteraugli", [0.5, 1.0, 2.0, 4.0])
+            .unwrap();
+        assert_eq!(fitted.samples, 80);
+        assert_eq!(fitted.accuracy, [1.0; 4]);
+        let thresholds = fitted.thresholds;
+        assert_eq!(thresholds.direction, QualityDirection::LowerIsBetter);
+        // Midway between the last sample above each boundary and the first
+        // one on it.
+        for (bound, expected) in thresholds.bounds.iter().zip([0.9, 1.9, 3.9, 7.9]) {
+            assert!((bound - expected).abs() < 1e-9, "{bound} vs {expected}");
+        }
+        assert_eq!(thresholds.level(0.5), PerceptionLevel::Imperceptible);
+        assert_eq!(thresholds.level(9.0), PerceptionLevel::Degraded);
+    }
+
+    #[test]
+    fn test_validate_scores_pairs() {
+        let config = EvalConfig::builder()
+            .report_dir("/tmp/test")
+            .metrics(MetricConfig::fast())
+            .build();
+        let session = EvalSession::new(config);
+        let dataset = MosDataset::new("synthetic", QualityDirection::HigherIsBetter)
+            .with_pair("ref", "noise-2", 4.5)
+            .with_pair("ref", "noise-8", 3.0)
+            .with_pair("ref", "noise-32", 1.0);
+        let report = session
+            .validate(&dataset, |path| {
+                let offset: u8 = path
+                    .to_str()
+                    .and_then(|p| p.strip_prefix("noise-"))
+                    .map_or(0, |n| n.parse().unwrap());
+                Ok(ImageData::RgbSlice {
+                    data: (0..16 * 16 * 3)
+                        .map(|i| if i % 2 == 0 { 100 + offset } else { 100 })
+                        .collect(),
+                    width: 16,
+                    height: 16,
+                })
+            })
+            .unwrap();
+        assert_eq!(report.samples.len(), 3);
+        assert_eq!(report.samples[1].pair.score, 3.0);
+        let psnr = report
+            .correlation("psnr", &Bootstrap::default())
+            .unwrap()
+            .srocc;
+        assert!((psnr.value - 1.0).abs() < 1e-12, "{psnr:?}");
+
+        let mismatched = dataset.with_pair("ref", "small", 2.0);
+        let err = session.validate(&mismatched, |path| {
+            let size = if path == Path::new("small") { 8 } else { 16 };
+            Ok(ImageData::RgbSlice {
+                data: vec![0; size * size * 3],
+                width: size,
+                height: size,
+            })
+        });
+        assert!(matches!(err, Err(Error::DimensionMismatch { .. })));
+    }
+}
@@ -34,6 +34,7 @@ use std::path::Path;
 use serde::{Deserialize, Serialize};
 
 use crate::error::{Error, Result};
+use crate::stats::{QualityDirection, RDPoint};
 
 /// An imported result from an external encoder benchmark.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -68,14 +69,64 @@ pub struct ExternalResult {
     /// Butteraugli distance (if available).
     pub butteraugli: Option<f64>,
 
+    /// SSIM (if available).
+    #[serde(default)]
+    pub ssim: Option<f64>,
+
+    /// MS-SSIM (if available).
+    #[serde(default)]
+    pub ms_ssim: Option<f64>,
+
     /// Encoding time in milliseconds (if available).
     pub encode_time_ms: Option<f64>,
 
-    /// Additional fields.
+    /// Columns not mapped to a field above, keyed by header. Custom metric
+    /// columns (as written for registered [`Metric`]s) end up here.
+    ///
+    /// [`Metric`]: crate::metrics::Metric
     #[serde(default)]
     pub extra: HashMap<String, String>,
 }
 
+impl ExternalResult {
+    /// Look up a metric score by name: a built-in metric field, or a numeric
+    /// column in [`ExternalResult::extra`] (matched case-insensitively).
+    #[must_use]
+    pub fn metric(&self, name: &str) -> Option<f64> {
+        match name {
+            "dssim" => self.dssim,
+            "ssimulacra2" => self.ssimulacra2,
+            "butteraugli" => self.butteraugli,
+            "psnr" => self.psnr,
+            "ssim" => self.ssim,
+            "ms_ssim" => self.ms_ssim,
+            _ => self
+                .extra
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .and_then(|(_, value)| value.parse().ok()),
+        }
+    }
+
+    /// Convert to a rate-distortion point for `metric`.
+    ///
+    /// Returns `None` without a bits-per-pixel value or metric score. The
+    /// score is oriented by `direction` so that higher is better. The quality
+    /// setting is NaN when the CSV has no quality column.
+    #[must_use]
+    pub fn rd_point(&self, metric: &str, direction: QualityDirection) -> Option<RDPoint> {
+        let mut point = RDPoint::new(
+            self.codec.clone(),
+            self.quality_setting.unwrap_or(f64::NAN),
+            self.bits_per_pixel?,
+            direction.higher_is_better(self.metric(metric)?),
+        );
+        point.encode_time_ms = self.encode_time_ms;
+        point.image = Some(self.image_name.clone());
+        Some(point)
+    }
+}
+
 /// Schema for CSV import.
 #[derive(Debug, Clone, Default)]
 pub struct CsvSchema {
@@ -99,6 +150,10 @@ pub struct CsvSchema {
     pub psnr_column: Option<String>,
     /// Column name for Butteraugli.
     pub butteraugli_column: Option<String>,
+    /// Column name for SSIM.
+    pub ssim_column: Option<String>,
+    /// Column name for MS-SSIM.
+    pub ms_ssim_column: Option<String>,
     /// Column name for encode time (ms).
     pub encode_time_column: Option<String>,
 }
@@ -218,6 +273,20 @@ impl CsvSchemaBuilder {
         self
     }
 
+    /// Set the SSIM column name.
+    #[must_use]
+    pub fn ssim_column(mut self, name: impl Into<String>) -> Self {
+        self.schema.ssim_column = Some(name.into());
+        self
+    }
+
+    /// Set the MS-SSIM column name.
+    #[must_use]
+    pub fn ms_ssim_column(mut self, name: impl Into<String>) -> Self {
+        self.schema.ms_ssim_column = Some(name.into());
+        self
+    }
+
     /// Set the encode time column name.
     #[must_use]
     pub fn encode_time_column(mut self, name: impl Into<String>) -> Self {
@@ -304,7 +373,7 @@ impl CsvImporter {
         let dssim_idx = self.schema.find_column(
             &header_refs,
             self.schema.dssim_column.as_deref(),
-            &["dssim", "ssim", "ms_ssim", "ms-ssim"],
+            &["dssim"],
         );
 
         let psnr_idx = self.schema.find_column(
@@ -319,6 +388,18 @@ impl CsvImporter {
             &["butteraugli", "butter", "ba"],
         );
 
+        let ssim_idx = self.schema.find_column(
+            &header_refs,
+            self.schema.ssim_column.as_deref(),
+            &["ssim", "ssim_y", "y_ssim"],
+        );
+
+        let ms_ssim_idx = self.schema.find_column(
+            &header_refs,
+            self.schema.ms_ssim_column.as_deref(),
+            &["ms_ssim", "ms-ssim", "msssim"],
+        );
+
         let encode_time_idx = self.schema.find_column(
             &header_refs,
             self.schema.encode_time_column.as_deref(),
@@ -336,6 +417,27 @@ impl CsvImporter {
             reason: "Could not find codec/encoder column".to_string(),
         })?;
 
+        let mapped = [
+            Some(image_idx),
+            Some(codec_idx),
+            version_idx,
+            quality_idx,
+            size_idx,
+            bpp_idx,
+            ssimulacra2_idx,
+            dssim_idx,
+            psnr_idx,
+            butteraugli_idx,
+            ssim_idx,
+            ms_ssim_idx,
+            encode_time_idx,
+        ];
+        let extra_columns: Vec<(usize, &String)> = headers
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !mapped.contains(&Some(*i)))
+            .collect();
+
         let mut results = Vec::new();
 
         for (line_num, record) in reader.records().enumerate() {
@@ -376,10 +478,22 @@ impl CsvImporter {
                 butteraugli: butteraugli_idx
                     .and_then(|i| record.get(i))
                     .and_then(|s| s.parse().ok()),
+                ssim: ssim_idx
+                    .and_then(|i| record.get(i))
+                    .and_then(|s| s.parse().ok()),
+                ms_ssim: ms_ssim_idx
+                    .and_then(|i| record.get(i))
+                    .and_then(|s| s.parse().ok()),
                 encode_time_ms: encode_time_idx
                     .and_then(|i| record.get(i))
                     .and_then(|s| s.parse().ok()),
-                extra: HashMap::new(),
+                extra: extra_columns
+                    .iter()
+                    .filter_map(|&(i, header)| {
+                        let value = record.get(i).filter(|v| !v.is_empty())?;
+                        Some((header.clone(), value.to_string()))
+                    })
+                    .collect(),
             };
 
             results.push(result);
@@ -419,4 +533,58 @@ mod tests {
         assert_eq!(find_header_index(&headers, "QUALITY"), Some(2));
         assert_eq!(find_header_index(&headers, "unknown"), None);
     }
+
+    #[test]
+    fn test_import_custom_metric_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.csv");
+        std::fs::write(
+            &path,
+            "image,codec,bpp,ssimulacra2,mae,notes\n\
+             a.png,webp,0.5,80.0,2.5,first\n\
+             a.png,avif,0.4,,3.5,\n",
+        )
+        .unwrap();
+
+        let results = CsvImporter::auto_detect().import(&path).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].metric("mae"), Some(2.5));
+        assert_eq!(results[0].metric("MAE"), Some(2.5));
+        assert_eq!(results[0].metric("ssimulacra2"), Some(80.0));
+        assert_eq!(
+            results[0].extra.get("notes").map(String::as_str),
+            Some("first")
+        );
+        assert!(!results[1].extra.contains_key("notes"));
+        assert!(!results[0].extra.contains_key("bpp"));
+
+        let point = results[1]
+            .rd_point("mae", QualityDirection::LowerIsBetter)
+            .unwrap();
+        assert_eq!(point.codec, "avif");
+        assert_eq!(point.quality, -3.5);
+        assert!(
+            results[1]
+                .rd_point("ssimulacra2", QualityDirection::HigherIsBetter)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_import_ssim_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.csv");
+        std::fs::write(
+            &path,
+            "image,codec,bpp,dssim,SSIM,MS-SSIM\n\
+             a.png,webp,0.5,0.002,0.95,0.98\n",
+        )
+        .unwrap();
+
+        let results = CsvImporter::auto_detect().import(&path).unwrap();
+        assert_eq!(results[0].dssim, Some(0.002));
+        assert_eq!(results[0].ssim, Some(0.95));
+        assert_eq!(results[0].metric("ms_ssim"), Some(0.98));
+        assert!(results[0].extra.is_empty());
+    }
 }
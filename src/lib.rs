@@ -57,16 +57,25 @@ pub use eval::{
     CodecResult,
     CorpusReport,
     EvalConfig,
+    EvalMode,
     EvalSession,
+    GenerationLoss,
     ImageData,
     ImageReport,
+    ParamGrid,
+    QualityTarget,
+    SizeTarget,
+    TimingPolicy,
+    TrackingAllocator,
     // Evaluation helpers (lightweight API for zen* projects)
     assert_perception_level,
     assert_quality,
     evaluate_single,
 };
 pub use import::{CsvImporter, ExternalResult};
-pub use metrics::{MetricConfig, MetricResult, PerceptionLevel};
+pub use metrics::{
+    Metric, MetricConfig, MetricResult, PerceptionLevel, PerceptionThresholds, ReferenceContext,
+};
 pub use stats::{ParetoFront, RDPoint, Summary};
 pub use viewing::{REFERENCE_PPD, ViewingCondition};
 
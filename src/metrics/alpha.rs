@@ -0,0 +1,234 @@
+//! Alpha channel handling for metric calculation.
+//!
+//! Perceptual metrics compare opaque RGB. Translucent images are composited
+//! over one or more backgrounds before scoring, so an encoder that damages
+//! the alpha channel is penalized wherever that damage would be visible. The
+//! alpha channel itself is also compared directly via [`alpha_rmse`].
+//!
+//! Compositing happens on code values (sRGB space for 8-bit images), matching
+//! how browsers blend images.
+
+use serde::{Deserialize, Serialize};
+
+use super::transfer::TransferFunction;
+
+/// Background to composite translucent images over before scoring.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AlphaBackground {
+    /// Solid black.
+    Black,
+    /// Solid white.
+    White,
+    /// Grey checkerboard (16-pixel cells of sRGB 102 and 153), as shown by
+    /// image editors. Reveals alpha errors that solid colours hide.
+    Checkerboard,
+}
+
+impl AlphaBackground {
+    /// Backgrounds used when [`MetricConfig::alpha_backgrounds`] is empty.
+    ///
+    /// [`MetricConfig::alpha_backgrounds`]: super::MetricConfig::alpha_backgrounds
+    pub const DEFAULT: &'static [Self] = &[Self::Black, Self::White];
+
+    /// Checkerboard cell size in pixels.
+    const CELL: usize = 16;
+
+    /// Background value at a pixel position.
+    #[inline]
+    fn value_at(self, x: usize, y: usize) -> u8 {
+        match self {
+            Self::Black => 0,
+            Self::White => 255,
+            Self::Checkerboard => {
+                if (x / Self::CELL + y / Self::CELL).is_multiple_of(2) {
+                    153
+                } else {
+                    102
+                }
+            }
+        }
+    }
+
+    /// Short name for reports.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Black => "black",
+            Self::White => "white",
+            Self::Checkerboard => "checkerboard",
+        }
+    }
+}
+
+/// Composite RGB8 pixels with a separate alpha plane over a background.
+///
+/// # Arguments
+///
+/// * `rgb` - RGB8 pixel data in row-major order (straight, not premultiplied).
+/// * `alpha` - One alpha byte per pixel, or `None` for an opaque image.
+/// * `width` - Image width in pixels.
+/// * `background` - Background to composite over.
+#[must_use]
+pub fn composite_over(
+    rgb: &[u8],
+    alpha: Option<&[u8]>,
+    width: usize,
+    background: AlphaBackground,
+) -> Vec<u8> {
+    let Some(alpha) = alpha else {
+        return rgb.to_vec();
+    };
+
+    let mut out = Vec::with_capacity(rgb.len());
+    for (i, (pixel, &a)) in rgb.chunks_exact(3).zip(alpha).enumerate() {
+        let bg = u32::from(background.value_at(i % width.max(1), i / width.max(1)));
+        let a = u32::from(a);
+        for &c in pixel {
+            // Rounded (c * a + bg * (255 - a)) / 255
+            out.push(((u32::from(c) * a + bg * (255 - a) + 127) / 255) as u8);
+        }
+    }
+    out
+}
+
+/// Composite normalized (0.0-1.0) RGB samples over a background.
+///
+/// The high-precision counterpart of [`composite_over`]. Blending happens on
+/// code values in `transfer`; the background is placed at the same light
+/// level it has in sRGB, relative to SDR reference white.
+///
+/// # Arguments
+///
+/// * `rgb` - Normalized RGB samples in row-major order (straight alpha).
+/// * `alpha` - One normalized alpha value per pixel, or `None` if opaque.
+/// * `width` - Image width in pixels.
+/// * `background` - Background to composite over.
+/// * `transfer` - Transfer function of the samples.
+#[must_use]
+pub fn composite_over_f32(
+    rgb: &[f32],
+    alpha: Option<&[f32]>,
+    width: usize,
+    background: AlphaBackground,
+    transfer: TransferFunction,
+) -> Vec<f32> {
+    let Some(alpha) = alpha else {
+        return rgb.to_vec();
+    };
+
+    let encode =
+        |v: u8| transfer.from_linear(TransferFunction::Srgb.to_linear(f32::from(v) / 255.0));
+    let (light, dark) = (encode(153), encode(102));
+    let (black, white) = (encode(0), encode(255));
+
+    let mut out = Vec::with_capacity(rgb.len());
+    for (i, (pixel, &a)) in rgb.chunks_exact(3).zip(alpha).enumerate() {
+        // Backgrounds only use these four levels; avoid re-encoding per pixel.
+        let bg = match background.value_at(i % width.max(1), i / width.max(1)) {
+            0 => black,
+            102 => dark,
+            153 => light,
+            255 => white,
+            other => encode(other),
+        };
+        let a = a.clamp(0.0, 1.0);
+        out.extend(pixel.iter().map(|&c| c * a + bg * (1.0 - a)));
+    }
+    out
+}
+
+/// Root-mean-square error between two alpha planes, in 0-255 units.
+///
+/// A missing plane is treated as fully opaque, so dropping the alpha channel
+/// of a translucent image is reported as an error.
+#[must_use]
+pub fn alpha_rmse(reference: Option<&[u8]>, test: Option<&[u8]>, pixel_count: usize) -> f64 {
+    if pixel_count == 0 {
+        return 0.0;
+    }
+    let at = |plane: Option<&[u8]>, i: usize| plane.map_or(255.0, |p| f64::from(p[i]));
+    let sum: f64 = (0..pixel_count)
+        .map(|i| {
+            let diff = at(reference, i) - at(test, i);
+            diff * diff
+        })
+        .sum();
+    (sum / pixel_count as f64).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_composite_opaque_is_identity() {
+        let rgb = vec![10, 20, 30, 40, 50, 60];
+        assert_eq!(
+            composite_over(&rgb, Some(&[255, 255]), 2, AlphaBackground::Black),
+            rgb
+        );
+        assert_eq!(composite_over(&rgb, None, 2, AlphaBackground::White), rgb);
+    }
+
+    #[test]
+    fn test_composite_transparent_shows_background() {
+        let rgb = vec![200, 100, 50, 200, 100, 50];
+        let alpha = [0, 0];
+        assert_eq!(
+            composite_over(&rgb, Some(&alpha), 2, AlphaBackground::Black),
+            vec![0; 6]
+        );
+        assert_eq!(
+            composite_over(&rgb, Some(&alpha), 2, AlphaBackground::White),
+            vec![255; 6]
+        );
+    }
+
+    #[test]
+    fn test_composite_half_alpha() {
+        let out = composite_over(&[255, 0, 0], Some(&[128]), 1, AlphaBackground::Black);
+        assert_eq!(out, vec![128, 0, 0]);
+    }
+
+    #[test]
+    fn test_checkerboard_alternates() {
+        let width = 32;
+        let rgb = vec![0u8; width * 3];
+        let alpha = vec![0u8; width];
+        let out = composite_over(&rgb, Some(&alpha), width, AlphaBackground::Checkerboard);
+        assert_eq!(out[0], 153);
+        assert_eq!(out[16 * 3], 102);
+    }
+
+    #[test]
+    fn test_composite_f32_matches_u8() {
+        let rgb = [200u8, 100, 50, 10, 20, 30];
+        let alpha = [64u8, 255];
+        let rgb_f: Vec<f32> = rgb.iter().map(|&v| f32::from(v) / 255.0).collect();
+        let alpha_f: Vec<f32> = alpha.iter().map(|&v| f32::from(v) / 255.0).collect();
+        for bg in [
+            AlphaBackground::Black,
+            AlphaBackground::White,
+            AlphaBackground::Checkerboard,
+        ] {
+            let expected = composite_over(&rgb, Some(&alpha), 2, bg);
+            let actual = composite_over_f32(&rgb_f, Some(&alpha_f), 2, bg, TransferFunction::Srgb);
+            for (e, a) in expected.iter().zip(&actual) {
+                assert!(
+                    (f32::from(*e) - a * 255.0).abs() <= 1.0,
+                    "{bg:?}: {e} vs {a}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_alpha_rmse() {
+        assert!(alpha_rmse(None, None, 4).abs() < f64::EPSILON);
+        let a = [255, 0, 255, 0];
+        assert!(alpha_rmse(Some(&a), Some(&a), 4).abs() < f64::EPSILON);
+        // Dropping alpha: half the pixels are off by 255.
+        let rmse = alpha_rmse(Some(&a), None, 4);
+        assert!((rmse - 255.0 / 2f64.sqrt()).abs() < 1e-9);
+    }
+}
@@ -10,15 +10,63 @@
 //! - < 5.0: Noticeable difference
 //! - >= 5.0: Degraded
 //!
+//! # Max-norm and p-norm
+//!
+//! The score is the max-norm: the worst pixel of butteraugli's diffmap.
+//! libjxl's tools also report a p-norm over the whole diffmap (3-norm by
+//! default), which moves far less between similar images and so averages
+//! better across a corpus. [`calculate_butteraugli_scores`] returns both from
+//! one comparison; [`pnorm`] computes it from any diffmap.
+//!
 //! # ICC Profile Support
 //!
 //! Use [`calculate_butteraugli_icc`] for images with non-sRGB color profiles.
 
-use butteraugli::{ButteraugliParams, Img, RGB8, butteraugli as butteraugli_compare};
+use butteraugli::{
+    ButteraugliParams, ButteraugliReference, ButteraugliResult, Img, RGB, RGB8,
+    butteraugli as butteraugli_compare, butteraugli_linear,
+};
 
+use super::DistortionMap;
 use super::icc::ColorProfile;
 use crate::error::{Error, Result};
 
+/// Exponent of the p-norm libjxl reports by default.
+pub const DEFAULT_PNORM: f64 = 3.0;
+
+/// Max-norm and p-norm Butteraugli distances from the same diffmap.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ButteraugliScores {
+    /// Worst pixel of the diffmap; what [`calculate_butteraugli`] returns.
+    pub max: f64,
+    /// p-norm of the diffmap, see [`pnorm`].
+    pub pnorm: f64,
+}
+
+/// p-norm of a butteraugli diffmap, as libjxl's `ComputeDistanceP`
+/// computes it: the mean of the `p`-, `2p`- and `4p`-norms over all pixels.
+///
+/// Returns 0.0 for an empty map.
+#[must_use]
+pub fn pnorm(map: &DistortionMap, p: f64) -> f64 {
+    if map.values.is_empty() {
+        return 0.0;
+    }
+    let mut sums = [0.0f64; 3];
+    for &value in &map.values {
+        let d = f64::from(value).powf(p);
+        sums[0] += d;
+        sums[1] += d * d;
+        sums[2] += d * d * d * d;
+    }
+    let per_pixel = 1.0 / map.values.len() as f64;
+    let norms = sums
+        .iter()
+        .zip([1.0, 2.0, 4.0])
+        .map(|(sum, scale)| (sum * per_pixel).powf(1.0 / (p * scale)));
+    norms.sum::<f64>() / 3.0
+}
+
 /// Convert raw RGB bytes to Vec<RGB8>.
 fn rgb_bytes_to_pixels(rgb: &[u8]) -> Vec<RGB8> {
     rgb.chunks_exact(3)
@@ -48,36 +96,44 @@ pub fn calculate_butteraugli(
     width: usize,
     height: usize,
 ) -> Result<f64> {
-    if reference.len() != test.len() {
-        return Err(Error::DimensionMismatch {
-            expected: (width, height),
-            actual: (test.len() / 3 / height, height),
-        });
-    }
-
-    let expected_len = width * height * 3;
-    if reference.len() != expected_len {
-        return Err(Error::MetricCalculation {
-            metric: "Butteraugli".to_string(),
-            reason: format!(
-                "Invalid image size: expected {} bytes, got {}",
-                expected_len,
-                reference.len()
-            ),
-        });
-    }
-
-    let img1 = Img::new(rgb_bytes_to_pixels(reference), width, height);
-    let img2 = Img::new(rgb_bytes_to_pixels(test), width, height);
     let params = ButteraugliParams::default();
-    let result = butteraugli_compare(img1.as_ref(), img2.as_ref(), &params).map_err(|e| {
-        Error::MetricCalculation {
-            metric: "Butteraugli".to_string(),
-            reason: e.to_string(),
-        }
-    })?;
+    compare_srgb(reference, test, width, height, &params).map(|result| result.score)
+}
 
-    Ok(result.score)
+/// Calculate Butteraugli and its per-pixel distortion map.
+///
+/// Same as [`calculate_butteraugli`], but also returns butteraugli's
+/// diffmap: one value per pixel on the same scale as the score (<1.0 is
+/// imperceptible).
+///
+/// # Errors
+///
+/// Returns an error if the images have different sizes or if calculation fails.
+pub fn calculate_butteraugli_with_map(
+    reference: &[u8],
+    test: &[u8],
+    width: usize,
+    height: usize,
+) -> Result<(f64, DistortionMap)> {
+    let params = ButteraugliParams::default().with_compute_diffmap(true);
+    with_map(compare_srgb(reference, test, width, height, &params)?)
+}
+
+/// Calculate the max-norm and `p`-norm Butteraugli distances, plus the
+/// diffmap both come from.
+///
+/// # Errors
+///
+/// Returns an error if the images have different sizes or if calculation fails.
+pub fn calculate_butteraugli_scores(
+    reference: &[u8],
+    test: &[u8],
+    width: usize,
+    height: usize,
+    p: f64,
+) -> Result<(ButteraugliScores, DistortionMap)> {
+    let params = ButteraugliParams::default().with_compute_diffmap(true);
+    with_scores(compare_srgb(reference, test, width, height, &params)?, p)
 }
 
 /// Calculate Butteraugli with custom intensity target.
@@ -103,6 +159,115 @@ pub fn calculate_butteraugli_with_intensity(
     height: usize,
     intensity_target: f32,
 ) -> Result<f64> {
+    let params = ButteraugliParams::default().with_intensity_target(intensity_target);
+    compare_srgb(reference, test, width, height, &params).map(|result| result.score)
+}
+
+/// Calculate Butteraugli between two linear-light images.
+///
+/// Used for high bit depth and HDR images, which are compared without
+/// quantizing to 8 bits. Values are linear RGB where `1.0` is displayed at
+/// `intensity_target` cd/m²; pass [`super::transfer::SDR_WHITE_NITS`] for
+/// values relative to HDR reference white, or 80.0 (the default) for SDR.
+///
+/// # Errors
+///
+/// Returns an error if the buffers do not match `width * height * 3` or if
+/// calculation fails.
+pub fn calculate_butteraugli_linear(
+    reference: &[f32],
+    test: &[f32],
+    width: usize,
+    height: usize,
+    intensity_target: f32,
+) -> Result<f64> {
+    let params = ButteraugliParams::default().with_intensity_target(intensity_target);
+    compare_linear(reference, test, width, height, &params).map(|result| result.score)
+}
+
+/// Calculate Butteraugli and its distortion map between two linear-light
+/// images. See [`calculate_butteraugli_linear`] and
+/// [`calculate_butteraugli_with_map`].
+///
+/// # Errors
+///
+/// Returns an error if the buffers do not match `width * height * 3` or if
+/// calculation fails.
+pub fn calculate_butteraugli_linear_with_map(
+    reference: &[f32],
+    test: &[f32],
+    width: usize,
+    height: usize,
+    intensity_target: f32,
+) -> Result<(f64, DistortionMap)> {
+    let params = ButteraugliParams::default()
+        .with_intensity_target(intensity_target)
+        .with_compute_diffmap(true);
+    with_map(compare_linear(reference, test, width, height, &params)?)
+}
+
+/// Calculate the max-norm and `p`-norm Butteraugli distances between two
+/// linear-light images. See [`calculate_butteraugli_linear`] and
+/// [`calculate_butteraugli_scores`].
+///
+/// # Errors
+///
+/// Returns an error if the buffers do not match `width * height * 3` or if
+/// calculation fails.
+pub fn calculate_butteraugli_linear_scores(
+    reference: &[f32],
+    test: &[f32],
+    width: usize,
+    height: usize,
+    intensity_target: f32,
+    p: f64,
+) -> Result<(ButteraugliScores, DistortionMap)> {
+    let params = ButteraugliParams::default()
+        .with_intensity_target(intensity_target)
+        .with_compute_diffmap(true);
+    with_scores(compare_linear(reference, test, width, height, &params)?, p)
+}
+
+/// Precompute the reference side of [`calculate_butteraugli_scores`], so
+/// it can be compared against many test images with [`compare_prepared`].
+pub(crate) fn prepare_reference(
+    reference: &[u8],
+    width: usize,
+    height: usize,
+) -> Result<ButteraugliReference> {
+    let params = ButteraugliParams::default().with_compute_diffmap(true);
+    ButteraugliReference::new(reference, width, height, params).map_err(|e| {
+        Error::MetricCalculation {
+            metric: "Butteraugli".to_string(),
+            reason: e.to_string(),
+        }
+    })
+}
+
+/// Both norms and the diffmap of `test` against a reference from
+/// [`prepare_reference`].
+pub(crate) fn compare_prepared(
+    reference: &ButteraugliReference,
+    test: &[u8],
+    p: f64,
+) -> Result<(ButteraugliScores, DistortionMap)> {
+    let result = reference
+        .compare(test)
+        .map_err(|e| Error::MetricCalculation {
+            metric: "Butteraugli".to_string(),
+            reason: e.to_string(),
+        })?;
+    with_scores(result, p)
+}
+
+/// Validate RGB8 buffers and run butteraugli.
+fn compare_srgb(
+    reference: &[u8],
+    test: &[u8],
+    width: usize,
+    height: usize,
+    params: &ButteraugliParams,
+) -> Result<ButteraugliResult> {
     if reference.len() != test.len() {
         return Err(Error::DimensionMismatch {
             expected: (width, height),
@@ -124,15 +289,73 @@ pub fn calculate_butteraugli_with_intensity(
 
     let img1 = Img::new(rgb_bytes_to_pixels(reference), width, height);
     let img2 = Img::new(rgb_bytes_to_pixels(test), width, height);
-    let params = ButteraugliParams::default().with_intensity_target(intensity_target);
-    let result = butteraugli_compare(img1.as_ref(), img2.as_ref(), &params).map_err(|e| {
+    butteraugli_compare(img1.as_ref(), img2.as_ref(), params).map_err(|e| {
         Error::MetricCalculation {
             metric: "Butteraugli".to_string(),
             reason: e.to_string(),
         }
+    })
+}
+
+/// Validate linear RGB buffers and run butteraugli.
+fn compare_linear(
+    reference: &[f32],
+    test: &[f32],
+    width: usize,
+    height: usize,
+    params: &ButteraugliParams,
+) -> Result<ButteraugliResult> {
+    let expected_len = width * height * 3;
+    if reference.len() != expected_len || test.len() != expected_len {
+        return Err(Error::MetricCalculation {
+            metric: "Butteraugli".to_string(),
+            reason: format!(
+                "Invalid image size: expected {expected_len} samples, got {} and {}",
+                reference.len(),
+                test.len()
+            ),
+        });
+    }
+
+    let to_img = |data: &[f32]| {
+        let pixels: Vec<RGB<f32>> = data
+            .chunks_exact(3)
+            .map(|c| RGB::new(c[0], c[1], c[2]))
+            .collect();
+        Img::new(pixels, width, height)
+    };
+    let img1 = to_img(reference);
+    let img2 = to_img(test);
+    butteraugli_linear(img1.as_ref(), img2.as_ref(), params).map_err(|e| Error::MetricCalculation {
+        metric: "Butteraugli".to_string(),
+        reason: e.to_string(),
+    })
+}
+
+/// Extract the score and diffmap from a result computed with
+/// `with_compute_diffmap(true)`.
+fn with_map(result: ButteraugliResult) -> Result<(f64, DistortionMap)> {
+    let diffmap = result.diffmap.ok_or_else(|| Error::MetricCalculation {
+        metric: "Butteraugli".to_string(),
+        reason: "No diffmap returned".to_string(),
     })?;
+    let map = DistortionMap {
+        width: diffmap.width(),
+        height: diffmap.height(),
+        values: diffmap.pixels().collect(),
+    };
+    Ok((result.score, map))
+}
 
-    Ok(result.score)
+/// Both norms and the diffmap from a result computed with
+/// `with_compute_diffmap(true)`.
+fn with_scores(result: ButteraugliResult, p: f64) -> Result<(ButteraugliScores, DistortionMap)> {
+    let (max, map) = with_map(result)?;
+    let scores = ButteraugliScores {
+        max,
+        pnorm: pnorm(&map, p),
+    };
+    Ok((scores, map))
 }
 
 /// Calculate Butteraugli with ICC profile support.
@@ -205,4 +428,74 @@ mod tests {
             "Identical images should have score ~0 at any intensity"
         );
     }
+
+    #[test]
+    fn test_linear_matches_u8() {
+        let ref_data: Vec<u8> = (0..64 * 64 * 3).map(|i| (i % 251) as u8).collect();
+        let test_data: Vec<u8> = ref_data.iter().map(|&v| v.saturating_add(6)).collect();
+        let linear = |d: &[u8]| {
+            d.iter()
+                .map(|&v| crate::metrics::xyb::srgb_u8_to_linear(v))
+                .collect::<Vec<_>>()
+        };
+        let score_u8 = calculate_butteraugli(&ref_data, &test_data, 64, 64).unwrap();
+        let score_linear =
+            calculate_butteraugli_linear(&linear(&ref_data), &linear(&test_data), 64, 64, 80.0)
+                .unwrap();
+        assert!(
+            (score_u8 - score_linear).abs() < 0.05 * score_u8.max(0.1),
+            "u8 {score_u8} vs linear {score_linear}"
+        );
+    }
+
+    #[test]
+    fn test_distortion_map() {
+        let ref_data = vec![128u8; 64 * 64 * 3];
+        let mut test_data = ref_data.clone();
+        for y in 0..16 {
+            for x in 0..16 {
+                test_data[(y * 64 + x) * 3] = 255;
+            }
+        }
+        let (score, map) = calculate_butteraugli_with_map(&ref_data, &test_data, 64, 64).unwrap();
+        let expected = calculate_butteraugli(&ref_data, &test_data, 64, 64).unwrap();
+        assert!((score - expected).abs() < 1e-6);
+        assert_eq!((map.width, map.height), (64, 64));
+        assert!(map.values[8 * 64 + 8] > map.values[56 * 64 + 56]);
+        assert!((f64::from(map.max_value()) - score).abs() < 1e-3 * score.max(1.0));
+    }
+
+    #[test]
+    fn test_pnorm() {
+        let flat = DistortionMap {
+            width: 2,
+            height: 2,
+            values: vec![2.0; 4],
+        };
+        assert!((pnorm(&flat, DEFAULT_PNORM) - 2.0).abs() < 1e-9);
+
+        // One bad pixel out of four: every norm is below the max.
+        let spike = DistortionMap {
+            width: 2,
+            height: 2,
+            values: vec![0.0, 0.0, 0.0, 4.0],
+        };
+        let expected = [3.0, 6.0, 12.0]
+            .iter()
+            .map(|p| 4.0 * 0.25f64.powf(1.0 / p))
+            .sum::<f64>()
+            / 3.0;
+        assert!((pnorm(&spike, 3.0) - expected).abs() < 1e-9);
+        assert!(pnorm(&spike, 3.0) < 4.0);
+
+        let ref_data = vec![128u8; 64 * 64 * 3];
+        let mut test_data = ref_data.clone();
+        test_data[..16 * 3].fill(255);
+        let (scores, map) =
+            calculate_butteraugli_scores(&ref_data, &test_data, 64, 64, DEFAULT_PNORM).unwrap();
+        let max = calculate_butteraugli(&ref_data, &test_data, 64, 64).unwrap();
+        assert!((scores.max - max).abs() < 1e-6);
+        assert!(scores.pnorm > 0.0 && scores.pnorm < scores.max);
+        assert_eq!(scores.pnorm, pnorm(&map, DEFAULT_PNORM));
+    }
 }
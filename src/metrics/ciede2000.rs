@@ -0,0 +1,306 @@
+//! CIEDE2000 colour difference.
+//!
+//! ΔE₀₀ (CIE 142-2001, as formulated by Sharma, Wu & Dalal 2005) measures
+//! how different two colours look, with corrections for lightness, chroma and
+//! hue that make it far more uniform than Euclidean distance in CIELAB. It
+//! catches what the structural metrics tend to miss: hue shifts from chroma
+//! subsampling or XYB quantization on saturated areas. Roughly, 1 is a
+//! just-noticeable difference side by side and values above 5 are obvious.
+//!
+//! Pixels are compared independently, so the per-pixel values are summarized
+//! as mean, 95th percentile and maximum in [`DeltaEScores`]. Both images are
+//! interpreted as sRGB (D65 white, `kL = kC = kH = 1`); convert ICC-tagged
+//! images first, as the evaluation session does with
+//! [`transform_to_srgb`](super::icc::transform_to_srgb).
+
+use crate::error::{Error, Result};
+use crate::metrics::DistortionMap;
+use crate::metrics::transfer::TransferFunction;
+use crate::stats::{mean, percentile};
+
+/// Linear sRGB (BT.709 primaries) to CIE XYZ, D65 white.
+const RGB_TO_XYZ: [[f64; 3]; 3] = [
+    [0.412_456_4, 0.357_576_1, 0.180_437_5],
+    [0.212_672_9, 0.715_152_2, 0.072_175_0],
+    [0.019_333_9, 0.119_192_0, 0.950_304_1],
+];
+
+/// `25^7`, from the chroma terms of the formula.
+const POW25_7: f64 = 6_103_515_625.0;
+
+/// Summary of per-pixel ΔE₀₀ values (all lower is better, 0 = identical).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeltaEScores {
+    /// Mean over pixels.
+    pub mean: f64,
+    /// 95th percentile over pixels.
+    pub p95: f64,
+    /// Largest difference of any pixel.
+    pub max: f64,
+}
+
+/// Calculate ΔE₀₀ between two sRGB RGB8 images, with the per-pixel values
+/// as a distortion map.
+///
+/// # Errors
+///
+/// Returns an error if either buffer does not hold `width * height` RGB
+/// pixels, or the image is empty.
+pub fn calculate_ciede2000(
+    reference: &[u8],
+    test: &[u8],
+    width: usize,
+    height: usize,
+) -> Result<(DeltaEScores, DistortionMap)> {
+    check_len(reference.len(), width, height)?;
+    check_len(test.len(), width, height)?;
+    let linear: Vec<f64> = (0..=255u8)
+        .map(|v| f64::from(TransferFunction::Srgb.to_linear(f32::from(v) / 255.0)))
+        .collect();
+    let lab = |p: &[u8]| srgb_linear_to_lab([p[0], p[1], p[2]].map(|v| linear[usize::from(v)]));
+    Ok(scores(
+        reference
+            .chunks_exact(3)
+            .zip(test.chunks_exact(3))
+            .map(|(r, t)| delta_e_2000(lab(r), lab(t))),
+        width,
+        height,
+    ))
+}
+
+/// Calculate ΔE₀₀ between two linear-light images with sRGB primaries,
+/// where `1.0` is SDR white.
+///
+/// Used for high bit depth images so colours are compared without
+/// quantizing to 8 bits. HDR highlights above `1.0` extend L* beyond 100,
+/// where CIEDE2000 was never fitted, so treat scores of such content as
+/// indicative only.
+///
+/// # Errors
+///
+/// Returns an error if either buffer does not hold `width * height` RGB
+/// pixels, or the image is empty.
+pub fn calculate_ciede2000_linear(
+    reference: &[f32],
+    test: &[f32],
+    width: usize,
+    height: usize,
+) -> Result<(DeltaEScores, DistortionMap)> {
+    check_len(reference.len(), width, height)?;
+    check_len(test.len(), width, height)?;
+    let lab = |p: &[f32]| srgb_linear_to_lab([p[0], p[1], p[2]].map(f64::from));
+    Ok(scores(
+        reference
+            .chunks_exact(3)
+            .zip(test.chunks_exact(3))
+            .map(|(r, t)| delta_e_2000(lab(r), lab(t))),
+        width,
+        height,
+    ))
+}
+
+/// Convert linear sRGB to CIELAB `[L*, a*, b*]` relative to D65 white.
+#[must_use]
+pub fn srgb_linear_to_lab(rgb: [f64; 3]) -> [f64; 3] {
+    // The white point is the image of RGB white, so white maps to a* = b* = 0
+    // exactly.
+    let [x, y, z] = RGB_TO_XYZ.map(|row| {
+        let white: f64 = row.iter().sum();
+        (row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2]) / white
+    });
+    let f = |t: f64| {
+        const DELTA: f64 = 6.0 / 29.0;
+        if t > DELTA * DELTA * DELTA {
+            t.cbrt()
+        } else {
+            t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// CIEDE2000 difference between two CIELAB colours.
+#[must_use]
+pub fn delta_e_2000(lab1: [f64; 3], lab2: [f64; 3]) -> f64 {
+    let [l1, a1, b1] = lab1;
+    let [l2, a2, b2] = lab2;
+
+    let c_bar = f64::midpoint(a1.hypot(b1), a2.hypot(b2));
+    let c_bar7 = c_bar.powi(7);
+    let g = 0.5 * (1.0 - (c_bar7 / (c_bar7 + POW25_7)).sqrt());
+    let (a1, a2) = ((1.0 + g) * a1, (1.0 + g) * a2);
+    let (c1, c2) = (a1.hypot(b1), a2.hypot(b2));
+    let hue = |a: f64, b: f64| {
+        if a == 0.0 && b == 0.0 {
+            0.0
+        } else {
+            b.atan2(a).to_degrees().rem_euclid(360.0)
+        }
+    };
+    let (h1, h2) = (hue(a1, b1), hue(a2, b2));
+    let achromatic = c1 * c2 == 0.0;
+
+    let dl = l2 - l1;
+    let dc = c2 - c1;
+    let dh = if achromatic {
+        0.0
+    } else if h2 - h1 > 180.0 {
+        h2 - h1 - 360.0
+    } else if h2 - h1 < -180.0 {
+        h2 - h1 + 360.0
+    } else {
+        h2 - h1
+    };
+    let dh = 2.0 * (c1 * c2).sqrt() * (dh / 2.0).to_radians().sin();
+
+    let l_mean = f64::midpoint(l1, l2);
+    let c_mean = f64::midpoint(c1, c2);
+    let h_mean = if achromatic {
+        h1 + h2
+    } else if (h1 - h2).abs() <= 180.0 {
+        f64::midpoint(h1, h2)
+    } else if h1 + h2 < 360.0 {
+        (h1 + h2 + 360.0) / 2.0
+    } else {
+        (h1 + h2 - 360.0) / 2.0
+    };
+
+    let cos = |deg: f64| deg.to_radians().cos();
+    let t =
+        1.0 - 0.17 * cos(h_mean - 30.0) + 0.24 * cos(2.0 * h_mean) + 0.32 * cos(3.0 * h_mean + 6.0)
+            - 0.20 * cos(4.0 * h_mean - 63.0);
+    let d_theta = 30.0 * (-((h_mean - 275.0) / 25.0).powi(2)).exp();
+    let c_mean7 = c_mean.powi(7);
+    let r_c = 2.0 * (c_mean7 / (c_mean7 + POW25_7)).sqrt();
+    let l50 = (l_mean - 50.0).powi(2);
+    let s_l = 1.0 + 0.015 * l50 / (20.0 + l50).sqrt();
+    let s_c = 1.0 + 0.045 * c_mean;
+    let s_h = 1.0 + 0.015 * c_mean * t;
+    let r_t = -(2.0 * d_theta).to_radians().sin() * r_c;
+
+    let (dl, dc, dh) = (dl / s_l, dc / s_c, dh / s_h);
+    (dl * dl + dc * dc + dh * dh + r_t * dc * dh)
+        .max(0.0)
+        .sqrt()
+}
+
+/// Summarize per-pixel differences and keep them as a map.
+fn scores(
+    values: impl Iterator<Item = f64>,
+    width: usize,
+    height: usize,
+) -> (DeltaEScores, DistortionMap) {
+    let values: Vec<f64> = values.collect();
+    let scores = DeltaEScores {
+        mean: mean(&values),
+        p95: percentile(&values, 0.95),
+        max: values.iter().copied().fold(0.0, f64::max),
+    };
+    #[allow(clippy::cast_possible_truncation)] // map precision is f32
+    let map = DistortionMap {
+        width,
+        height,
+        values: values.iter().map(|&v| v as f32).collect(),
+    };
+    (scores, map)
+}
+
+fn check_len(len: usize, width: usize, height: usize) -> Result<()> {
+    if width == 0 || height == 0 {
+        return Err(Error::MetricCalculation {
+            metric: "CIEDE2000".to_string(),
+            reason: "empty image".to_string(),
+        });
+    }
+    let expected = width * height * 3;
+    if len != expected {
+        return Err(Error::MetricCalculation {
+            metric: "CIEDE2000".to_string(),
+            reason: format!("Invalid image size: expected {expected} samples, got {len}"),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sharma_reference_pairs() {
+        // Test data from Sharma, Wu & Dalal (2005), table 1.
+        let pairs = [
+            ([50.0, 2.6772, -79.7751], [50.0, 0.0, -82.7485], 2.0425),
+            ([50.0, 0.0, 0.0], [50.0, -1.0, 2.0], 2.3669),
+            ([50.0, 2.49, -0.001], [50.0, -2.49, 0.0011], 7.2195),
+            ([50.0, 2.5, 0.0], [73.0, 25.0, -18.0], 27.1492),
+            (
+                [60.2574, -34.0099, 36.2677],
+                [60.4626, -34.1751, 39.4387],
+                1.2644,
+            ),
+            (
+                [2.0776, 0.0795, -1.1350],
+                [0.9033, -0.0636, -0.5514],
+                0.9082,
+            ),
+        ];
+        for (lab1, lab2, expected) in pairs {
+            let de = delta_e_2000(lab1, lab2);
+            assert!((de - expected).abs() < 1e-4, "{lab1:?} {lab2:?}: {de}");
+            assert!((delta_e_2000(lab2, lab1) - expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_lab_reference_colours() {
+        let white = srgb_linear_to_lab([1.0, 1.0, 1.0]);
+        assert!((white[0] - 100.0).abs() < 1e-9);
+        assert!(white[1].abs() < 1e-9 && white[2].abs() < 1e-9);
+        assert_eq!(srgb_linear_to_lab([0.0, 0.0, 0.0]), [0.0, 0.0, 0.0]);
+        let red = srgb_linear_to_lab([1.0, 0.0, 0.0]);
+        assert!((red[0] - 53.24).abs() < 0.01, "{red:?}");
+        assert!((red[1] - 80.09).abs() < 0.01, "{red:?}");
+        assert!((red[2] - 67.20).abs() < 0.01, "{red:?}");
+    }
+
+    #[test]
+    fn test_hue_shift_scores() {
+        let reference: Vec<u8> = (0..16).flat_map(|_| [200, 40, 40]).collect();
+        let (identical, map) = calculate_ciede2000(&reference, &reference, 4, 4).unwrap();
+        assert_eq!(identical.max, 0.0);
+        assert_eq!((map.width, map.height, map.values.len()), (4, 4, 16));
+
+        // One pixel shifted towards orange.
+        let mut test = reference.clone();
+        test[4] = 90;
+        let (shifted, map) = calculate_ciede2000(&reference, &test, 4, 4).unwrap();
+        assert!(shifted.max > 5.0, "{shifted:?}");
+        assert!((shifted.mean - shifted.max / 16.0).abs() < 1e-9);
+        assert!(shifted.p95 > shifted.mean && shifted.p95 < shifted.max);
+        assert!(map.values[1] > 5.0 && map.values[0] == 0.0);
+    }
+
+    #[test]
+    fn test_linear_matches_u8() {
+        let reference: Vec<u8> = (0..64u8).flat_map(|v| [v * 4, 255 - v, v]).collect();
+        let test: Vec<u8> = reference.iter().map(|&v| v.saturating_add(6)).collect();
+        let to_linear = |rgb: &[u8]| -> Vec<f32> {
+            rgb.iter()
+                .map(|&v| TransferFunction::Srgb.to_linear(f32::from(v) / 255.0))
+                .collect()
+        };
+        let (u8_scores, _) = calculate_ciede2000(&reference, &test, 8, 8).unwrap();
+        let (linear, _) =
+            calculate_ciede2000_linear(&to_linear(&reference), &to_linear(&test), 8, 8).unwrap();
+        assert!((u8_scores.mean - linear.mean).abs() < 1e-3);
+        assert!((u8_scores.max - linear.max).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_size_mismatch_is_error() {
+        assert!(calculate_ciede2000(&[0; 12], &[0; 9], 2, 2).is_err());
+        assert!(calculate_ciede2000(&[], &[], 0, 0).is_err());
+    }
+}
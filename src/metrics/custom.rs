@@ -0,0 +1,115 @@
+//! Pluggable metrics.
+//!
+//! Implement [`Metric`] and register it with
+//! [`EvalSession::add_metric`](crate::eval::EvalSession::add_metric) to score
+//! every decoded image with an in-house metric alongside the built-in ones.
+//! Scores are stored in [`MetricResult::custom`](super::MetricResult::custom)
+//! under the metric's name and are written as extra CSV columns.
+//!
+//! ```rust,ignore
+//! use codec_eval::metrics::{Metric, prelude::*};
+//! use codec_eval::stats::QualityDirection;
+//!
+//! struct MeanAbsError;
+//!
+//! impl Metric for MeanAbsError {
+//!     fn name(&self) -> &str {
+//!         "mae"
+//!     }
+//!
+//!     fn direction(&self) -> QualityDirection {
+//!         QualityDirection::LowerIsBetter
+//!     }
+//!
+//!     fn compute(&self, reference: ImgRef<'_, RGB8>, test: ImgRef<'_, RGB8>) -> Result<f64> {
+//!         // ...
+//!     }
+//! }
+//!
+//! session.add_metric(MeanAbsError);
+//! ```
+
+use imgref::ImgRef;
+use rgb::RGB8;
+
+use super::DistortionMap;
+use crate::error::Result;
+use crate::stats::QualityDirection;
+
+/// A quality metric comparing a reference image to a decoded test image.
+///
+/// Both images are 8-bit sRGB of the same size, with alpha compositing and
+/// viewing simulation already applied.
+pub trait Metric: Send + Sync {
+    /// Unique name, used as the key in [`MetricResult::custom`] and as the
+    /// CSV column header. Must not clash with a built-in metric name.
+    ///
+    /// [`MetricResult::custom`]: super::MetricResult::custom
+    fn name(&self) -> &str;
+
+    /// Whether higher or lower scores mean better quality.
+    fn direction(&self) -> QualityDirection;
+
+    /// Compute the score.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the metric cannot be computed for these images.
+    fn compute(&self, reference: ImgRef<'_, RGB8>, test: ImgRef<'_, RGB8>) -> Result<f64>;
+
+    /// Compute a per-pixel distortion map, if the metric has one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the map cannot be computed for these images.
+    fn distortion_map(
+        &self,
+        _reference: ImgRef<'_, RGB8>,
+        _test: ImgRef<'_, RGB8>,
+    ) -> Result<Option<DistortionMap>> {
+        Ok(None)
+    }
+}
+
+/// Names and directions of the built-in metrics, as used by
+/// [`MetricResult::value`](super::MetricResult::value).
+pub const BUILTIN_METRICS: &[(&str, QualityDirection)] = &[
+    ("dssim", QualityDirection::LowerIsBetter),
+    ("ssimulacra2", QualityDirection::HigherIsBetter),
+    ("butteraugli", QualityDirection::LowerIsBetter),
+    ("butteraugli_pnorm", QualityDirection::LowerIsBetter),
+    ("psnr", QualityDirection::HigherIsBetter),
+    ("ssim", QualityDirection::HigherIsBetter),
+    ("ms_ssim", QualityDirection::HigherIsBetter),
+    ("ciede2000", QualityDirection::LowerIsBetter),
+    ("ciede2000_p95", QualityDirection::LowerIsBetter),
+    ("ciede2000_max", QualityDirection::LowerIsBetter),
+    ("alpha_rmse", QualityDirection::LowerIsBetter),
+];
+
+/// Direction of a built-in metric, or `None` for unknown names.
+#[must_use]
+pub fn builtin_direction(name: &str) -> Option<QualityDirection> {
+    BUILTIN_METRICS
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|&(_, direction)| direction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_direction() {
+        assert_eq!(
+            builtin_direction("ssimulacra2"),
+            Some(QualityDirection::HigherIsBetter)
+        );
+        assert_eq!(
+            builtin_direction("butteraugli"),
+            Some(QualityDirection::LowerIsBetter)
+        );
+        assert_eq!(builtin_direction("mae"), None);
+    }
+}
@@ -6,22 +6,30 @@
 //!
 //! Use [`calculate_dssim_icc`] for images with non-sRGB color profiles.
 
-use dssim_core::Dssim;
+use std::borrow::Cow;
+
+use dssim_core::{Dssim, DssimImage};
 use imgref::ImgVec;
 use rgb::RGBA;
 
+use super::DistortionMap;
 use super::icc::ColorProfile;
+use super::resample::resample_linear;
 use crate::error::{Error, Result};
-use crate::viewing::ViewingCondition;
+use crate::viewing::{SimulationMode, ViewingCondition};
 
 /// Calculate DSSIM between two images.
 ///
+/// Both images are first resampled to simulate `viewing` (see
+/// [`ViewingCondition::simulation_params`], [`SimulationMode::Accurate`]), so
+/// the same pair scores differently on a desktop than on a phone or with an
+/// undersized srcset image.
+///
 /// # Arguments
 ///
-/// * `reference` - Reference image as RGBA f32 values (0.0-1.0).
-/// * `test` - Test image as RGBA f32 values (0.0-1.0).
-/// * `viewing` - Viewing condition for PPD-based adjustment (currently unused
-///   by dssim-core, but reserved for future use).
+/// * `reference` - Reference image as RGBA f32 values (0.0-1.0, linear light).
+/// * `test` - Test image as RGBA f32 values (0.0-1.0, linear light).
+/// * `viewing` - Viewing condition to simulate before comparison.
 ///
 /// # Returns
 ///
@@ -40,7 +48,7 @@ use crate::viewing::ViewingCondition;
 pub fn calculate_dssim(
     reference: &ImgVec<RGBA<f32>>,
     test: &ImgVec<RGBA<f32>>,
-    _viewing: &ViewingCondition,
+    viewing: &ViewingCondition,
 ) -> Result<f64> {
     if reference.width() != test.width() || reference.height() != test.height() {
         return Err(Error::DimensionMismatch {
@@ -49,14 +57,115 @@ pub fn calculate_dssim(
         });
     }
 
-    let dssim = Dssim::new();
+    let (reference, test) = simulate_viewing(reference, test, viewing);
+    compare_dssim(&reference, &test)
+}
+
+/// Calculate DSSIM and its per-pixel distortion map.
+///
+/// Same as [`calculate_dssim`], but also returns the full-resolution SSIM map
+/// of the first scale as `1 - ssim` per pixel (0 = identical), at the
+/// simulated viewing size.
+///
+/// # Errors
+///
+/// Returns an error if the images have different dimensions or if DSSIM
+/// calculation fails.
+pub fn calculate_dssim_with_map(
+    reference: &ImgVec<RGBA<f32>>,
+    test: &ImgVec<RGBA<f32>>,
+    viewing: &ViewingCondition,
+) -> Result<(f64, DistortionMap)> {
+    if reference.width() != test.width() || reference.height() != test.height() {
+        return Err(Error::DimensionMismatch {
+            expected: (reference.width(), reference.height()),
+            actual: (test.width(), test.height()),
+        });
+    }
+
+    let (reference, test) = simulate_viewing(reference, test, viewing);
+    compare_dssim_with_map(&reference, &test)
+}
+
+type LinearImage = ImgVec<RGBA<f32>>;
+
+/// Resample both images per `viewing` ([`SimulationMode::Accurate`]).
+fn simulate_viewing<'a>(
+    reference: &'a LinearImage,
+    test: &'a LinearImage,
+    viewing: &ViewingCondition,
+) -> (Cow<'a, LinearImage>, Cow<'a, LinearImage>) {
+    let params = viewing.simulation_params(
+        reference.width() as u32,
+        reference.height() as u32,
+        SimulationMode::Accurate,
+    );
+    if !params.requires_scaling() {
+        return (Cow::Borrowed(reference), Cow::Borrowed(test));
+    }
+
+    let simulate = |img: &LinearImage| {
+        let flat: Vec<f32> = img.pixels().flat_map(|p| [p.r, p.g, p.b, p.a]).collect();
+        let (w, h) = (params.target_width as usize, params.target_height as usize);
+        let pixels = resample_linear(&flat, 4, img.width(), img.height(), w, h)
+            .chunks_exact(4)
+            .map(|p| RGBA::new(p[0], p[1], p[2], p[3]))
+            .collect();
+        ImgVec::new(pixels, w, h)
+    };
+    (Cow::Owned(simulate(reference)), Cow::Owned(simulate(test)))
+}
+
+/// Compare two same-sized images with DSSIM, without viewing simulation.
+pub(crate) fn compare_dssim(
+    reference: &ImgVec<RGBA<f32>>,
+    test: &ImgVec<RGBA<f32>>,
+) -> Result<f64> {
+    compare(reference, test, false).map(|(dssim, _)| dssim)
+}
+
+/// Compare two same-sized images with DSSIM and keep the distortion map.
+pub(crate) fn compare_dssim_with_map(
+    reference: &ImgVec<RGBA<f32>>,
+    test: &ImgVec<RGBA<f32>>,
+) -> Result<(f64, DistortionMap)> {
+    let (dssim, map) = compare(reference, test, true)?;
+    let map = map.ok_or_else(|| Error::MetricCalculation {
+        metric: "DSSIM".to_string(),
+        reason: "No SSIM map returned".to_string(),
+    })?;
+    Ok((dssim, map))
+}
 
-    let ref_image = dssim
+fn compare(
+    reference: &ImgVec<RGBA<f32>>,
+    test: &ImgVec<RGBA<f32>>,
+    save_map: bool,
+) -> Result<(f64, Option<DistortionMap>)> {
+    compare_prepared(&prepare_reference(reference)?, test, save_map)
+}
+
+/// Convert a reference image into DSSIM's internal representation, so it can
+/// be compared against many test images with [`compare_prepared`].
+pub(crate) fn prepare_reference(reference: &ImgVec<RGBA<f32>>) -> Result<DssimImage<f32>> {
+    Dssim::new()
         .create_image(reference)
         .ok_or_else(|| Error::MetricCalculation {
             metric: "DSSIM".to_string(),
             reason: "Failed to create reference image".to_string(),
-        })?;
+        })
+}
+
+/// Compare a test image against a reference from [`prepare_reference`].
+pub(crate) fn compare_prepared(
+    reference: &DssimImage<f32>,
+    test: &ImgVec<RGBA<f32>>,
+    save_map: bool,
+) -> Result<(f64, Option<DistortionMap>)> {
+    let mut dssim = Dssim::new();
+    if save_map {
+        dssim.set_save_ssim_maps(1);
+    }
 
     let test_image = dssim
         .create_image(test)
@@ -65,9 +174,14 @@ pub fn calculate_dssim(
             reason: "Failed to create test image".to_string(),
         })?;
 
-    let (dssim_val, _ssim_maps) = dssim.compare(&ref_image, test_image);
+    let (dssim_val, ssim_maps) = dssim.compare(reference, test_image);
 
-    Ok(f64::from(dssim_val))
+    let map = ssim_maps.into_iter().next().map(|m| DistortionMap {
+        width: m.map.width(),
+        height: m.map.height(),
+        values: m.map.pixels().map(|ssim| (1.0 - ssim).max(0.0)).collect(),
+    });
+    Ok((f64::from(dssim_val), map))
 }
 
 /// Convert a single sRGB u8 component to linear f32.
@@ -154,7 +268,7 @@ pub fn rgba8_to_dssim_image(data: &[u8], width: usize, height: usize) -> ImgVec<
 /// * `test_profile` - Color profile of the test image.
 /// * `width` - Image width in pixels.
 /// * `height` - Image height in pixels.
-/// * `viewing` - Viewing condition for PPD-based adjustment.
+/// * `viewing` - Viewing condition to simulate before comparison.
 pub fn calculate_dssim_icc(
     reference: &[u8],
     reference_profile: &ColorProfile,
@@ -196,6 +310,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_distortion_map() {
+        let flat = |v: f32| ImgVec::new(vec![RGBA::new(v, v, v, 1.0); 64 * 64], 64, 64);
+        let reference = flat(0.5);
+        let mut test = flat(0.5);
+        for y in 0..16_usize {
+            for x in 0..16_usize {
+                test[(x, y)] = RGBA::new(0.9, 0.1, 0.1, 1.0);
+            }
+        }
+
+        let (score, map) =
+            calculate_dssim_with_map(&reference, &test, &ViewingCondition::desktop()).unwrap();
+        let expected = calculate_dssim(&reference, &test, &ViewingCondition::desktop()).unwrap();
+        assert!((score - expected).abs() < 1e-9);
+        assert_eq!(map.values.len(), map.width * map.height);
+        let at = |x: usize, y: usize| map.values[y * map.width + x];
+        assert!(at(4, 4) > at(map.width - 4, map.height - 4));
+    }
+
     #[test]
     fn test_different_images() {
         let ref_pixels: Vec<RGBA<f32>> = (0..100 * 100)
@@ -248,6 +382,38 @@ mod tests {
         assert!(matches!(result, Err(Error::DimensionMismatch { .. })));
     }
 
+    #[test]
+    fn test_viewing_condition_changes_score() {
+        let (w, h) = (96, 96);
+        let reference: Vec<u8> = (0..w * h * 3).map(|i| ((i / 3) % 200) as u8).collect();
+        // High-frequency noise that shrinks when viewed at higher PPD.
+        let test: Vec<u8> = reference
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| {
+                if (i / 3) % 2 == 0 {
+                    v.saturating_add(24)
+                } else {
+                    v
+                }
+            })
+            .collect();
+        let ref_img = rgb8_to_dssim_image(&reference, w, h);
+        let test_img = rgb8_to_dssim_image(&test, w, h);
+
+        let desktop = calculate_dssim(&ref_img, &test_img, &ViewingCondition::desktop()).unwrap();
+        let phone = calculate_dssim(
+            &ref_img,
+            &test_img,
+            &crate::viewing::presets::native_phone(),
+        )
+        .unwrap();
+        assert!(
+            phone < desktop,
+            "phone {phone} should hide more than desktop {desktop}"
+        );
+    }
+
     #[test]
     fn test_rgb8_conversion() {
         let rgb_data = vec![255u8, 0, 0, 0, 255, 0]; // Red, Green pixels
@@ -0,0 +1,149 @@
+//! False-colour rendering of [`DistortionMap`]s.
+//!
+//! Maps are rendered on a fixed scale per metric, so heatmaps from different
+//! codecs and quality levels can be compared side by side: dark blue is no
+//! distortion, red and white are at or above [`heatmap_scale`].
+
+use super::DistortionMap;
+#[cfg(feature = "heatmap")]
+use crate::error::{Error, Result};
+
+/// Colour stops from no distortion (0.0) to saturation (1.0).
+const STOPS: [[f32; 3]; 6] = [
+    [0.0, 0.0, 64.0],
+    [0.0, 64.0, 255.0],
+    [0.0, 200.0, 120.0],
+    [255.0, 230.0, 0.0],
+    [230.0, 0.0, 0.0],
+    [255.0, 255.0, 255.0],
+];
+
+/// Value at which a metric's heatmap saturates.
+///
+/// Butteraugli saturates at 3.0 (clearly visible), DSSIM at `1 - ssim = 0.1`,
+/// CIEDE2000 at ΔE₀₀ 5 (obvious).
+/// Other metrics return `None`; their maps are scaled to their own maximum.
+#[must_use]
+pub fn heatmap_scale(metric: &str) -> Option<f32> {
+    match metric {
+        "butteraugli" => Some(3.0),
+        "dssim" => Some(0.1),
+        "ciede2000" => Some(5.0),
+        _ => None,
+    }
+}
+
+/// Map a value in `0.0..=max` to a false colour.
+#[must_use]
+pub fn false_color(value: f32, max: f32) -> [u8; 3] {
+    let t = if max > 0.0 {
+        (value / max).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let pos = t * (STOPS.len() - 1) as f32;
+    let i = (pos.floor() as usize).min(STOPS.len() - 2);
+    let frac = pos - i as f32;
+    let (a, b) = (STOPS[i], STOPS[i + 1]);
+    [0, 1, 2].map(|c| (a[c] + (b[c] - a[c]) * frac).round() as u8)
+}
+
+impl DistortionMap {
+    /// Largest value in the map (0.0 for an empty map).
+    #[must_use]
+    pub fn max_value(&self) -> f32 {
+        self.values.iter().copied().fold(0.0, f32::max)
+    }
+
+    /// Mean value of the map (0.0 for an empty map).
+    #[must_use]
+    pub fn mean_value(&self) -> f32 {
+        if self.values.is_empty() {
+            return 0.0;
+        }
+        self.values.iter().sum::<f32>() / self.values.len() as f32
+    }
+
+    /// Keep the larger value per pixel. Maps of different sizes are left
+    /// unchanged.
+    pub(crate) fn merge_max(&mut self, other: &Self) {
+        if (self.width, self.height) != (other.width, other.height) {
+            return;
+        }
+        for (a, &b) in self.values.iter_mut().zip(&other.values) {
+            *a = a.max(b);
+        }
+    }
+
+    /// Render as RGB8 false colour, saturating at `max`.
+    #[must_use]
+    pub fn to_false_color_rgb8(&self, max: f32) -> Vec<u8> {
+        self.values
+            .iter()
+            .flat_map(|&v| false_color(v, max))
+            .collect()
+    }
+
+    /// Write as a false-colour PNG, saturating at `max`.
+    #[cfg(feature = "heatmap")]
+    pub fn write_png(&self, path: &std::path::Path, max: f32) -> Result<()> {
+        let to_err = |e: png::EncodingError| {
+            Error::Report(format!("failed to write {}: {e}", path.display()))
+        };
+        let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(to_err)?;
+        writer
+            .write_image_data(&self.to_false_color_rgb8(max))
+            .map_err(to_err)?;
+        writer.finish().map_err(to_err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_false_color_endpoints() {
+        assert_eq!(false_color(0.0, 1.0), [0, 0, 64]);
+        assert_eq!(false_color(1.0, 1.0), [255, 255, 255]);
+        assert_eq!(false_color(5.0, 1.0), [255, 255, 255]);
+        assert_eq!(false_color(1.0, 0.0), [0, 0, 64]);
+    }
+
+    #[test]
+    fn test_merge_max() {
+        let mut a = DistortionMap {
+            width: 2,
+            height: 1,
+            values: vec![1.0, 0.0],
+        };
+        let b = DistortionMap {
+            width: 2,
+            height: 1,
+            values: vec![0.5, 2.0],
+        };
+        a.merge_max(&b);
+        assert_eq!(a.values, vec![1.0, 2.0]);
+        assert_eq!(a.max_value(), 2.0);
+        assert_eq!(a.mean_value(), 1.5);
+    }
+
+    #[cfg(feature = "heatmap")]
+    #[test]
+    fn test_write_png() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("map.png");
+        let map = DistortionMap {
+            width: 3,
+            height: 2,
+            values: vec![0.0, 0.5, 1.0, 1.5, 2.0, 3.0],
+        };
+        map.write_png(&path, 3.0).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(&bytes[1..4], b"PNG");
+    }
+}
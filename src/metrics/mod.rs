@@ -7,6 +7,10 @@
 //! - **SSIMULACRA2**: Perceptual similarity metric (higher is better, 100 = identical)
 //! - **Butteraugli**: Perceptual difference metric (lower is better, <1.0 = imperceptible)
 //! - **PSNR**: Peak Signal-to-Noise Ratio (higher is better) - NOT RECOMMENDED
+//! - **SSIM / MS-SSIM**: Classic luma structural similarity (higher is better,
+//!   1 = identical), for comparison with published results
+//! - **CIEDE2000**: Per-pixel colour difference ΔE₀₀ (lower is better,
+//!   0 = identical), sensitive to hue shifts
 //!
 //! ## Recommended Metrics
 //!
@@ -25,24 +29,47 @@
 //! | Subtle | < 0.0015 | > 70 | < 3.0 | Barely noticeable |
 //! | Noticeable | < 0.003 | > 50 | < 5.0 | Visible on inspection |
 //! | Degraded | >= 0.003 | <= 50 | >= 5.0 | Clearly visible artifacts |
+//!
+//! These are [`PerceptionThresholds::DSSIM`] and friends. Thresholds fitted
+//! to a subjective dataset come from
+//! [`ValidationReport::fit_thresholds`](crate::eval::ValidationReport::fit_thresholds).
 
+pub mod alpha;
 pub mod butteraugli;
+pub mod ciede2000;
+pub mod custom;
 pub mod dssim;
+pub mod heatmap;
 pub mod icc;
 pub mod prelude;
+pub mod reference;
+pub mod resample;
+pub mod ssim;
 pub mod ssimulacra2;
+pub mod tiles;
+pub mod transfer;
 pub mod xyb;
 
 // Re-export ICC types for convenience
 pub use icc::{ColorProfile, prepare_for_comparison, transform_to_srgb};
 
+pub use alpha::AlphaBackground;
+pub use custom::Metric;
+pub use reference::ReferenceContext;
+pub use tiles::{Rect, Region, TileGrid, TileScore, TileScores};
+pub use transfer::TransferFunction;
+
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
+use crate::stats::QualityDirection;
+
 // Re-export XYB roundtrip for convenience
 pub use xyb::xyb_roundtrip;
 
 /// Configuration for which metrics to calculate.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MetricConfig {
     /// Calculate DSSIM (structural dissimilarity).
     pub dssim: bool,
@@ -50,8 +77,30 @@ pub struct MetricConfig {
     pub ssimulacra2: bool,
     /// Calculate Butteraugli (perceptual difference, lower is better).
     pub butteraugli: bool,
+    /// Exponent of the Butteraugli p-norm reported alongside the max-norm
+    /// (see [`butteraugli::pnorm`]). Defaults to libjxl's 3.
+    ///
+    /// Omitted from JSON at the default, so journals written before it
+    /// existed keep their settings fingerprint.
+    #[serde(
+        default = "default_butteraugli_p",
+        skip_serializing_if = "is_default_butteraugli_p"
+    )]
+    pub butteraugli_p: f64,
     /// Calculate PSNR (peak signal-to-noise ratio). NOT RECOMMENDED.
     pub psnr: bool,
+    /// Calculate classic luma SSIM (see [`ssim`]).
+    ///
+    /// Omitted from JSON when off, so journals written before it existed
+    /// keep their settings fingerprint.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ssim: bool,
+    /// Calculate MS-SSIM (see [`ssim`]).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ms_ssim: bool,
+    /// Calculate CIEDE2000 colour difference (see [`ciede2000`]).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ciede2000: bool,
     /// Roundtrip reference through XYB color space before comparing.
     ///
     /// When enabled, the reference image is converted RGB → XYB → u8 → XYB → RGB
@@ -60,6 +109,43 @@ pub struct MetricConfig {
     ///
     /// Recommended for codecs that operate in XYB color space (e.g., jpegli).
     pub xyb_roundtrip: bool,
+    /// Backgrounds to composite translucent images over before scoring.
+    ///
+    /// Only used when the reference or decoded image has an alpha channel.
+    /// Each metric reports its worst score across these backgrounds. When
+    /// empty, [`AlphaBackground::DEFAULT`] (black and white) is used.
+    #[serde(default)]
+    pub alpha_backgrounds: Vec<AlphaBackground>,
+    /// Also score every tile of this grid, reported in [`MetricResult::tiles`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tiles: Option<TileGrid>,
+}
+
+impl Default for MetricConfig {
+    fn default() -> Self {
+        Self {
+            dssim: false,
+            ssimulacra2: false,
+            butteraugli: false,
+            butteraugli_p: butteraugli::DEFAULT_PNORM,
+            psnr: false,
+            ssim: false,
+            ms_ssim: false,
+            ciede2000: false,
+            xyb_roundtrip: false,
+            alpha_backgrounds: Vec::new(),
+            tiles: None,
+        }
+    }
+}
+
+fn default_butteraugli_p() -> f64 {
+    butteraugli::DEFAULT_PNORM
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)] // serde passes fields by reference
+fn is_default_butteraugli_p(p: &f64) -> bool {
+    *p == butteraugli::DEFAULT_PNORM
 }
 
 impl MetricConfig {
@@ -71,7 +157,11 @@ impl MetricConfig {
             ssimulacra2: true,
             butteraugli: true,
             psnr: true,
+            ssim: true,
+            ms_ssim: true,
+            ciede2000: true,
             xyb_roundtrip: false,
+            ..Self::default()
         }
     }
 
@@ -84,6 +174,7 @@ impl MetricConfig {
             butteraugli: false,
             psnr: true,
             xyb_roundtrip: false,
+            ..Self::default()
         }
     }
 
@@ -96,6 +187,7 @@ impl MetricConfig {
             butteraugli: true,
             psnr: false,
             xyb_roundtrip: false,
+            ..Self::default()
         }
     }
 
@@ -112,6 +204,7 @@ impl MetricConfig {
             butteraugli: true,
             psnr: false,
             xyb_roundtrip: true,
+            ..Self::default()
         }
     }
 
@@ -124,15 +217,63 @@ impl MetricConfig {
             butteraugli: false,
             psnr: false,
             xyb_roundtrip: false,
+            ..Self::default()
         }
     }
 
+    /// Also calculate SSIM and MS-SSIM, e.g. to compare against published
+    /// results.
+    #[must_use]
+    pub fn with_ssim(mut self) -> Self {
+        self.ssim = true;
+        self.ms_ssim = true;
+        self
+    }
+
+    /// Also calculate the CIEDE2000 colour difference.
+    #[must_use]
+    pub fn with_ciede2000(mut self) -> Self {
+        self.ciede2000 = true;
+        self
+    }
+
+    /// Set the exponent of the Butteraugli p-norm.
+    #[must_use]
+    pub fn with_butteraugli_p(mut self, p: f64) -> Self {
+        self.butteraugli_p = p;
+        self
+    }
+
     /// Enable XYB roundtrip on this config.
     #[must_use]
     pub fn with_xyb_roundtrip(mut self) -> Self {
         self.xyb_roundtrip = true;
         self
     }
+
+    /// Set the backgrounds translucent images are composited over.
+    #[must_use]
+    pub fn with_alpha_backgrounds(mut self, backgrounds: Vec<AlphaBackground>) -> Self {
+        self.alpha_backgrounds = backgrounds;
+        self
+    }
+
+    /// Score tiles of `grid` alongside the whole image.
+    #[must_use]
+    pub fn with_tiles(mut self, grid: TileGrid) -> Self {
+        self.tiles = Some(grid);
+        self
+    }
+
+    /// Backgrounds to composite over, falling back to [`AlphaBackground::DEFAULT`].
+    #[must_use]
+    pub fn effective_alpha_backgrounds(&self) -> &[AlphaBackground] {
+        if self.alpha_backgrounds.is_empty() {
+            AlphaBackground::DEFAULT
+        } else {
+            &self.alpha_backgrounds
+        }
+    }
 }
 
 /// Results from metric calculations.
@@ -143,12 +284,133 @@ pub struct MetricResult {
     /// SSIMULACRA2 score (higher is better, 100 = identical).
     pub ssimulacra2: Option<f64>,
     /// Butteraugli score (lower is better, <1.0 = imperceptible).
+    ///
+    /// This is the max-norm, the worst pixel of the diffmap.
     pub butteraugli: Option<f64>,
+    /// Butteraugli p-norm over the same diffmap, with the exponent of
+    /// [`MetricConfig::butteraugli_p`] (lower is better). Steadier than the
+    /// max-norm for corpus averages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub butteraugli_pnorm: Option<f64>,
     /// PSNR value in dB (higher is better). NOT RECOMMENDED.
     pub psnr: Option<f64>,
+    /// Luma SSIM (higher is better, 1 = identical).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssim: Option<f64>,
+    /// MS-SSIM (higher is better, 1 = identical).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ms_ssim: Option<f64>,
+    /// Mean CIEDE2000 ΔE₀₀ over pixels (lower is better, 0 = identical).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ciede2000: Option<f64>,
+    /// 95th percentile of per-pixel CIEDE2000 ΔE₀₀.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ciede2000_p95: Option<f64>,
+    /// Largest per-pixel CIEDE2000 ΔE₀₀.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ciede2000_max: Option<f64>,
+    /// RMS error of the alpha channel in 0-255 units (lower is better).
+    ///
+    /// Only set when the reference or decoded image has an alpha channel.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alpha_rmse: Option<f64>,
+    /// Scores from registered [`Metric`]s, keyed by [`Metric::name`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub custom: BTreeMap<String, f64>,
+    /// Per-tile and per-region scores with worst-tile and percentile
+    /// aggregates, when [`MetricConfig::tiles`] or regions are set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tiles: Option<Box<TileScores>>,
 }
 
 impl MetricResult {
+    /// Keep the worse of each score from `self` and `other`.
+    ///
+    /// Used to combine scores computed over several alpha backgrounds.
+    /// `direction` resolves custom metric names; unknown ones keep `self`'s
+    /// score.
+    pub(crate) fn merge_worst(
+        &mut self,
+        other: &Self,
+        direction: impl Fn(&str) -> Option<QualityDirection>,
+    ) {
+        fn pick(a: &mut Option<f64>, b: Option<f64>, worse: fn(f64, f64) -> f64) {
+            *a = match (*a, b) {
+                (Some(x), Some(y)) => Some(worse(x, y)),
+                (x, y) => x.or(y),
+            };
+        }
+        pick(&mut self.dssim, other.dssim, f64::max);
+        pick(&mut self.ssimulacra2, other.ssimulacra2, f64::min);
+        pick(&mut self.butteraugli, other.butteraugli, f64::max);
+        pick(
+            &mut self.butteraugli_pnorm,
+            other.butteraugli_pnorm,
+            f64::max,
+        );
+        pick(&mut self.psnr, other.psnr, f64::min);
+        pick(&mut self.ssim, other.ssim, f64::min);
+        pick(&mut self.ms_ssim, other.ms_ssim, f64::min);
+        pick(&mut self.ciede2000, other.ciede2000, f64::max);
+        pick(&mut self.ciede2000_p95, other.ciede2000_p95, f64::max);
+        pick(&mut self.ciede2000_max, other.ciede2000_max, f64::max);
+        pick(&mut self.alpha_rmse, other.alpha_rmse, f64::max);
+        for (name, &value) in &other.custom {
+            match (self.custom.get_mut(name), direction(name)) {
+                (Some(current), Some(direction)) => *current = direction.worse(*current, value),
+                (Some(_), None) => {}
+                (None, _) => {
+                    self.custom.insert(name.clone(), value);
+                }
+            }
+        }
+        match (&mut self.tiles, &other.tiles) {
+            (Some(tiles), Some(other)) => tiles.merge_worst(other, &direction),
+            (None, Some(other)) => self.tiles = Some(other.clone()),
+            (_, None) => {}
+        }
+    }
+
+    /// Set a score by metric name, as looked up by [`value`](Self::value).
+    pub(crate) fn set_value(&mut self, name: &str, value: f64) {
+        match name {
+            "dssim" => self.dssim = Some(value),
+            "ssimulacra2" => self.ssimulacra2 = Some(value),
+            "butteraugli" => self.butteraugli = Some(value),
+            "butteraugli_pnorm" => self.butteraugli_pnorm = Some(value),
+            "psnr" => self.psnr = Some(value),
+            "ssim" => self.ssim = Some(value),
+            "ms_ssim" => self.ms_ssim = Some(value),
+            "ciede2000" => self.ciede2000 = Some(value),
+            "ciede2000_p95" => self.ciede2000_p95 = Some(value),
+            "ciede2000_max" => self.ciede2000_max = Some(value),
+            "alpha_rmse" => self.alpha_rmse = Some(value),
+            _ => {
+                self.custom.insert(name.to_string(), value);
+            }
+        }
+    }
+
+    /// Look up a score by metric name: a built-in metric
+    /// (see [`custom::BUILTIN_METRICS`]) or a registered [`Metric`].
+    #[must_use]
+    pub fn value(&self, name: &str) -> Option<f64> {
+        match name {
+            "dssim" => self.dssim,
+            "ssimulacra2" => self.ssimulacra2,
+            "butteraugli" => self.butteraugli,
+            "butteraugli_pnorm" => self.butteraugli_pnorm,
+            "psnr" => self.psnr,
+            "ssim" => self.ssim,
+            "ms_ssim" => self.ms_ssim,
+            "ciede2000" => self.ciede2000,
+            "ciede2000_p95" => self.ciede2000_p95,
+            "ciede2000_max" => self.ciede2000_max,
+            "alpha_rmse" => self.alpha_rmse,
+            _ => self.custom.get(name).copied(),
+        }
+    }
+
     /// Get the perception level based on DSSIM value.
     #[must_use]
     pub fn perception_level(&self) -> Option<PerceptionLevel> {
@@ -168,6 +430,20 @@ impl MetricResult {
     }
 }
 
+/// Per-pixel distortion values for a comparison.
+///
+/// Larger values mean more visible distortion. The map may be smaller than
+/// the compared images if the metric works on a downscaled grid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistortionMap {
+    /// Map width.
+    pub width: usize,
+    /// Map height.
+    pub height: usize,
+    /// Row-major values, `width * height` entries.
+    pub values: Vec<f32>,
+}
+
 /// Perceptual quality level based on metric thresholds.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PerceptionLevel {
@@ -187,51 +463,21 @@ impl PerceptionLevel {
     /// Determine perception level from DSSIM value.
     #[must_use]
     pub fn from_dssim(dssim: f64) -> Self {
-        if dssim < 0.0003 {
-            Self::Imperceptible
-        } else if dssim < 0.0007 {
-            Self::Marginal
-        } else if dssim < 0.0015 {
-            Self::Subtle
-        } else if dssim < 0.003 {
-            Self::Noticeable
-        } else {
-            Self::Degraded
-        }
+        PerceptionThresholds::DSSIM.level(dssim)
     }
 
     /// Determine perception level from SSIMULACRA2 value.
     /// SSIMULACRA2 is higher-is-better (100 = identical).
     #[must_use]
     pub fn from_ssimulacra2(score: f64) -> Self {
-        if score > 90.0 {
-            Self::Imperceptible
-        } else if score > 80.0 {
-            Self::Marginal
-        } else if score > 70.0 {
-            Self::Subtle
-        } else if score > 50.0 {
-            Self::Noticeable
-        } else {
-            Self::Degraded
-        }
+        PerceptionThresholds::SSIMULACRA2.level(score)
     }
 
     /// Determine perception level from Butteraugli value.
     /// Butteraugli is lower-is-better (<1.0 = imperceptible).
     #[must_use]
     pub fn from_butteraugli(score: f64) -> Self {
-        if score < 1.0 {
-            Self::Imperceptible
-        } else if score < 2.0 {
-            Self::Marginal
-        } else if score < 3.0 {
-            Self::Subtle
-        } else if score < 5.0 {
-            Self::Noticeable
-        } else {
-            Self::Degraded
-        }
+        PerceptionThresholds::BUTTERAUGLI.level(score)
     }
 
     /// Get the maximum DSSIM value for this perception level.
@@ -295,6 +541,72 @@ impl std::fmt::Display for PerceptionLevel {
     }
 }
 
+/// Metric thresholds separating the [`PerceptionLevel`]s.
+///
+/// The built-in sets back [`PerceptionLevel::from_dssim`] and friends; sets
+/// fitted to subjective scores come from
+/// [`ValidationReport::fit_thresholds`](crate::eval::ValidationReport::fit_thresholds).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PerceptionThresholds {
+    /// Whether higher or lower metric values are better.
+    pub direction: QualityDirection,
+    /// Upper bounds (for lower-is-better metrics) or lower bounds (for
+    /// higher-is-better ones) of Imperceptible, Marginal, Subtle and
+    /// Noticeable. Values at or beyond the last are Degraded.
+    pub bounds: [f64; 4],
+}
+
+impl PerceptionThresholds {
+    /// DSSIM thresholds from imageflow's empirical data.
+    pub const DSSIM: Self = Self {
+        direction: QualityDirection::LowerIsBetter,
+        bounds: [0.0003, 0.0007, 0.0015, 0.003],
+    };
+
+    /// SSIMULACRA2 thresholds.
+    pub const SSIMULACRA2: Self = Self {
+        direction: QualityDirection::HigherIsBetter,
+        bounds: [90.0, 80.0, 70.0, 50.0],
+    };
+
+    /// Butteraugli (max-norm) thresholds.
+    pub const BUTTERAUGLI: Self = Self {
+        direction: QualityDirection::LowerIsBetter,
+        bounds: [1.0, 2.0, 3.0, 5.0],
+    };
+
+    /// Built-in thresholds of `metric`, if it has any.
+    #[must_use]
+    pub fn builtin(metric: &str) -> Option<Self> {
+        match metric {
+            "dssim" => Some(Self::DSSIM),
+            "ssimulacra2" => Some(Self::SSIMULACRA2),
+            "butteraugli" => Some(Self::BUTTERAUGLI),
+            _ => None,
+        }
+    }
+
+    /// Perception level of a metric value.
+    #[must_use]
+    pub fn level(&self, value: f64) -> PerceptionLevel {
+        const LEVELS: [PerceptionLevel; 4] = [
+            PerceptionLevel::Imperceptible,
+            PerceptionLevel::Marginal,
+            PerceptionLevel::Subtle,
+            PerceptionLevel::Noticeable,
+        ];
+        let within = |bound: f64| match self.direction {
+            QualityDirection::HigherIsBetter => value > bound,
+            QualityDirection::LowerIsBetter => value < bound,
+        };
+        LEVELS
+            .into_iter()
+            .zip(self.bounds)
+            .find(|&(_, bound)| within(bound))
+            .map_or(PerceptionLevel::Degraded, |(level, _)| level)
+    }
+}
+
 /// Calculate PSNR between two images.
 ///
 /// # Arguments
@@ -330,10 +642,103 @@ pub fn calculate_psnr(reference: &[u8], test: &[u8], width: usize, height: usize
     }
 }
 
+/// Calculate PSNR between two images with normalized (0.0-1.0) samples.
+///
+/// Used for high bit depth images so PSNR is computed without quantizing to
+/// 8 bits. Samples are compared as code values in their own transfer
+/// function, so both images should use the same one.
+///
+/// # Returns
+///
+/// PSNR value in decibels with a peak of 1.0. Returns `f64::INFINITY` if
+/// images are identical.
+#[must_use]
+pub fn calculate_psnr_f32(reference: &[f32], test: &[f32], width: usize, height: usize) -> f64 {
+    assert_eq!(reference.len(), test.len());
+    assert_eq!(reference.len(), width * height * 3);
+
+    let mse_sum: f64 = reference
+        .iter()
+        .zip(test)
+        .map(|(&r, &t)| {
+            let diff = f64::from(r) - f64::from(t);
+            diff * diff
+        })
+        .sum();
+    let mse = mse_sum / (width * height * 3) as f64;
+
+    if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (1.0 / mse).log10()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_merge_worst() {
+        let mut a = MetricResult {
+            dssim: Some(0.001),
+            ssimulacra2: Some(80.0),
+            butteraugli: None,
+            butteraugli_pnorm: None,
+            psnr: Some(40.0),
+            ssim: None,
+            ms_ssim: None,
+            ciede2000: None,
+            ciede2000_p95: None,
+            ciede2000_max: None,
+            alpha_rmse: None,
+            custom: BTreeMap::from([("mae".to_string(), 2.0), ("vmaf".to_string(), 90.0)]),
+            tiles: None,
+        };
+        let b = MetricResult {
+            dssim: Some(0.002),
+            ssimulacra2: Some(85.0),
+            butteraugli: Some(1.5),
+            butteraugli_pnorm: None,
+            psnr: Some(35.0),
+            ssim: None,
+            ms_ssim: None,
+            ciede2000: None,
+            ciede2000_p95: None,
+            ciede2000_max: None,
+            alpha_rmse: None,
+            custom: BTreeMap::from([("mae".to_string(), 3.0), ("vmaf".to_string(), 95.0)]),
+            tiles: None,
+        };
+        a.merge_worst(&b, |name| match name {
+            "mae" => Some(QualityDirection::LowerIsBetter),
+            "vmaf" => Some(QualityDirection::HigherIsBetter),
+            _ => None,
+        });
+        assert_eq!(a.dssim, Some(0.002));
+        assert_eq!(a.ssimulacra2, Some(80.0));
+        assert_eq!(a.butteraugli, Some(1.5));
+        assert_eq!(a.psnr, Some(35.0));
+        assert_eq!(a.value("mae"), Some(3.0));
+        assert_eq!(a.value("vmaf"), Some(90.0));
+        assert_eq!(a.value("dssim"), Some(0.002));
+        assert_eq!(a.value("unknown"), None);
+    }
+
+    #[test]
+    fn test_effective_alpha_backgrounds() {
+        assert_eq!(
+            MetricConfig::all().effective_alpha_backgrounds(),
+            AlphaBackground::DEFAULT
+        );
+        let config =
+            MetricConfig::fast().with_alpha_backgrounds(vec![AlphaBackground::Checkerboard]);
+        assert_eq!(
+            config.effective_alpha_backgrounds(),
+            &[AlphaBackground::Checkerboard]
+        );
+    }
+
     #[test]
     fn test_perception_level_thresholds() {
         assert_eq!(
@@ -382,6 +787,17 @@ mod tests {
         assert!(psnr < 29.0);
     }
 
+    #[test]
+    fn test_psnr_f32_matches_u8() {
+        let reference = vec![100u8; 16 * 16 * 3];
+        let test = vec![110u8; 16 * 16 * 3];
+        let to_f32 = |d: &[u8]| d.iter().map(|&v| f32::from(v) / 255.0).collect::<Vec<_>>();
+        let psnr_u8 = calculate_psnr(&reference, &test, 16, 16);
+        let psnr_f32 = calculate_psnr_f32(&to_f32(&reference), &to_f32(&test), 16, 16);
+        assert!((psnr_u8 - psnr_f32).abs() < 1e-3);
+        assert!(calculate_psnr_f32(&to_f32(&reference), &to_f32(&reference), 16, 16).is_infinite());
+    }
+
     #[test]
     fn test_metric_config_all() {
         let config = MetricConfig::all();
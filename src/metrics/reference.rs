@@ -0,0 +1,305 @@
+//! Reference-side state shared across comparisons.
+//!
+//! DSSIM, SSIMULACRA2 and Butteraugli each convert both images into an
+//! internal representation (a multi-scale LAB pyramid, blurred XYB planes,
+//! opsin dynamics) before comparing them. In a codec sweep the reference is
+//! the same for every codec and quality, so a [`ReferenceContext`] converts
+//! it once and each comparison only processes the test image.
+//!
+//! PSNR, SSIM, MS-SSIM and CIEDE2000 have no reference state worth keeping
+//! and are computed directly.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use butteraugli::ButteraugliReference;
+use dssim_core::DssimImage;
+use fast_ssim2::Ssimulacra2Reference;
+
+use super::{
+    DistortionMap, MetricConfig, MetricResult, butteraugli as butteraugli_metric, calculate_psnr,
+    ciede2000, dssim, ssim, ssimulacra2,
+};
+use crate::error::{Error, Result};
+
+/// A reference image with every enabled metric's reference state
+/// precomputed, for scoring many test images against it.
+///
+/// # Example
+///
+/// ```
+/// use codec_eval::metrics::{MetricConfig, ReferenceContext};
+///
+/// let reference = vec![128u8; 64 * 64 * 3];
+/// let context = ReferenceContext::new(reference, 64, 64, &MetricConfig::perceptual());
+///
+/// for level in [120u8, 124, 127] {
+///     let decoded = vec![level; 64 * 64 * 3];
+///     let result = context.compare(&decoded)?;
+///     assert!(result.ssimulacra2.is_some());
+/// }
+/// # Ok::<(), codec_eval::Error>(())
+/// ```
+pub struct ReferenceContext {
+    reference: Vec<u8>,
+    width: usize,
+    height: usize,
+    config: MetricConfig,
+    dssim: Option<DssimImage<f32>>,
+    ssimulacra2: Option<Ssimulacra2Reference>,
+    butteraugli: Option<ButteraugliReference>,
+}
+
+impl ReferenceContext {
+    /// Precompute the reference state of every metric `config` enables.
+    ///
+    /// `reference` is RGB8 sRGB pixels exactly as the metrics should see
+    /// them: apply any XYB roundtrip or viewing simulation first. A metric
+    /// that cannot precompute, for instance on images under 8x8 pixels,
+    /// compares from scratch each time instead and so reports the same
+    /// errors as its `calculate_*` function.
+    #[must_use]
+    pub fn new(reference: Vec<u8>, width: usize, height: usize, config: &MetricConfig) -> Self {
+        let valid = reference.len() == width * height * 3;
+        let dssim = (valid && config.dssim)
+            .then(|| {
+                dssim::prepare_reference(&dssim::rgb8_to_dssim_image(&reference, width, height))
+                    .ok()
+            })
+            .flatten();
+        let ssimulacra2 = (valid && config.ssimulacra2)
+            .then(|| ssimulacra2::prepare_reference(&reference, width, height).ok())
+            .flatten();
+        let butteraugli = (valid && config.butteraugli)
+            .then(|| butteraugli_metric::prepare_reference(&reference, width, height).ok())
+            .flatten();
+        Self {
+            reference,
+            width,
+            height,
+            config: config.clone(),
+            dssim,
+            ssimulacra2,
+            butteraugli,
+        }
+    }
+
+    /// Reference pixels, RGB8.
+    #[must_use]
+    pub fn reference(&self) -> &[u8] {
+        &self.reference
+    }
+
+    /// Image width in pixels.
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Image height in pixels.
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Metrics this context computes.
+    #[must_use]
+    pub fn config(&self) -> &MetricConfig {
+        &self.config
+    }
+
+    /// Score `test` with every enabled built-in metric.
+    ///
+    /// `test` is RGB8 sRGB pixels of the same size as the reference.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `test` does not hold as many samples as the
+    /// reference or a metric fails.
+    pub fn compare(&self, test: &[u8]) -> Result<MetricResult> {
+        self.score(test, None)
+    }
+
+    /// Like [`compare`](Self::compare), also returning the DSSIM, Butteraugli
+    /// and CIEDE2000 distortion maps keyed by metric name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `test` does not hold as many samples as the
+    /// reference or a metric fails.
+    pub fn compare_with_maps(
+        &self,
+        test: &[u8],
+    ) -> Result<(MetricResult, BTreeMap<String, DistortionMap>)> {
+        let mut maps = BTreeMap::new();
+        let result = self.score(test, Some(&mut maps))?;
+        Ok((result, maps))
+    }
+
+    fn score(
+        &self,
+        test: &[u8],
+        mut maps: Option<&mut BTreeMap<String, DistortionMap>>,
+    ) -> Result<MetricResult> {
+        let (reference, width, height) = (self.reference.as_slice(), self.width, self.height);
+        if test.len() != reference.len() {
+            return Err(Error::MetricCalculation {
+                metric: "ReferenceContext".to_string(),
+                reason: format!(
+                    "Invalid image size: expected {} samples, got {}",
+                    reference.len(),
+                    test.len()
+                ),
+            });
+        }
+        let save_maps = maps.is_some();
+        let mut keep = |name: &str, map: DistortionMap| {
+            if let Some(maps) = maps.as_deref_mut() {
+                maps.insert(name.to_string(), map);
+            }
+        };
+        let mut result = MetricResult::default();
+
+        if self.config.psnr {
+            result.psnr = Some(calculate_psnr(reference, test, width, height));
+        }
+
+        if self.config.ssim {
+            result.ssim = Some(ssim::calculate_ssim(reference, test, width, height)?);
+        }
+
+        if self.config.ms_ssim {
+            result.ms_ssim = Some(ssim::calculate_ms_ssim(reference, test, width, height)?);
+        }
+
+        if self.config.ciede2000 {
+            let (scores, map) = ciede2000::calculate_ciede2000(reference, test, width, height)?;
+            result.ciede2000 = Some(scores.mean);
+            result.ciede2000_p95 = Some(scores.p95);
+            result.ciede2000_max = Some(scores.max);
+            keep("ciede2000", map);
+        }
+
+        if self.config.dssim {
+            let fallback;
+            let prepared = if let Some(prepared) = &self.dssim {
+                prepared
+            } else {
+                let ref_img = dssim::rgb8_to_dssim_image(reference, width, height);
+                fallback = dssim::prepare_reference(&ref_img)?;
+                &fallback
+            };
+            let test_img = dssim::rgb8_to_dssim_image(test, width, height);
+            let (score, map) = dssim::compare_prepared(prepared, &test_img, save_maps)?;
+            result.dssim = Some(score);
+            if let Some(map) = map {
+                keep("dssim", map);
+            }
+        }
+
+        if self.config.ssimulacra2 {
+            result.ssimulacra2 = Some(match &self.ssimulacra2 {
+                Some(prepared) => ssimulacra2::compare_prepared(prepared, test, width, height)?,
+                None => ssimulacra2::calculate_ssimulacra2(reference, test, width, height)?,
+            });
+        }
+
+        if self.config.butteraugli {
+            let p = self.config.butteraugli_p;
+            let (scores, map) = match &self.butteraugli {
+                Some(prepared) => butteraugli_metric::compare_prepared(prepared, test, p)?,
+                None => butteraugli_metric::calculate_butteraugli_scores(
+                    reference, test, width, height, p,
+                )?,
+            };
+            result.butteraugli = Some(scores.max);
+            result.butteraugli_pnorm = Some(scores.pnorm);
+            keep("butteraugli", map);
+        }
+
+        Ok(result)
+    }
+}
+
+impl fmt::Debug for ReferenceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReferenceContext")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("config", &self.config)
+            .field("dssim", &self.dssim.is_some())
+            .field("ssimulacra2", &self.ssimulacra2.is_some())
+            .field("butteraugli", &self.butteraugli.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(width: usize, height: usize, shift: usize) -> Vec<u8> {
+        (0..width * height * 3)
+            .map(|i| ((i * 7 + shift * (i % 5)) % 256) as u8)
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_direct_calculation() {
+        let (w, h) = (48, 40);
+        let reference = gradient(w, h, 0);
+        let context = ReferenceContext::new(reference.clone(), w, h, &MetricConfig::all());
+
+        for shift in [1, 3] {
+            let test = gradient(w, h, shift);
+            let (result, maps) = context.compare_with_maps(&test).unwrap();
+
+            let ssim2 = ssimulacra2::calculate_ssimulacra2(&reference, &test, w, h).unwrap();
+            assert!((result.ssimulacra2.unwrap() - ssim2).abs() < 1e-6);
+
+            let (ba, _) = butteraugli_metric::calculate_butteraugli_scores(
+                &reference,
+                &test,
+                w,
+                h,
+                butteraugli_metric::DEFAULT_PNORM,
+            )
+            .unwrap();
+            assert!((result.butteraugli.unwrap() - ba.max).abs() < 1e-6);
+            assert!((result.butteraugli_pnorm.unwrap() - ba.pnorm).abs() < 1e-6);
+
+            let dssim = dssim::compare_dssim(
+                &dssim::rgb8_to_dssim_image(&reference, w, h),
+                &dssim::rgb8_to_dssim_image(&test, w, h),
+            )
+            .unwrap();
+            assert!((result.dssim.unwrap() - dssim).abs() < 1e-9);
+
+            assert_eq!(result.psnr, Some(calculate_psnr(&reference, &test, w, h)));
+            for name in ["butteraugli", "ciede2000", "dssim"] {
+                assert!(maps.contains_key(name), "missing {name} map");
+            }
+        }
+    }
+
+    #[test]
+    fn test_small_image_falls_back() {
+        // Too small to precompute SSIMULACRA2 and Butteraugli.
+        let reference = gradient(4, 4, 0);
+        let context =
+            ReferenceContext::new(reference.clone(), 4, 4, &MetricConfig::ssimulacra2_only());
+        assert!(context.compare(&reference).is_err());
+
+        let context = ReferenceContext::new(reference.clone(), 4, 4, &MetricConfig::fast());
+        assert!(context.compare(&reference).is_ok());
+    }
+
+    #[test]
+    fn test_size_mismatch() {
+        let context = ReferenceContext::new(gradient(16, 16, 0), 16, 16, &MetricConfig::all());
+        assert!(matches!(
+            context.compare(&gradient(16, 8, 0)),
+            Err(Error::MetricCalculation { .. })
+        ));
+    }
+}
@@ -0,0 +1,173 @@
+//! Image resampling for viewing-condition simulation.
+//!
+//! Uses a separable triangle (bilinear) filter evaluated in linear light. When
+//! downsampling, the filter support is widened by the scale factor so every
+//! source pixel contributes, which approximates how browsers and the eye
+//! average detail away. When upsampling it reduces to plain bilinear
+//! interpolation, matching typical browser upscaling.
+
+use rayon::prelude::*;
+
+use super::xyb::{linear_to_srgb_u8, srgb_u8_to_linear};
+
+/// Contributing source pixels for one output pixel.
+struct Taps {
+    start: usize,
+    weights: Vec<f32>,
+}
+
+/// Compute normalized triangle-filter taps for resampling `src` samples to `dst`.
+fn filter_taps(src: usize, dst: usize) -> Vec<Taps> {
+    let scale = dst as f64 / src as f64;
+    // Filter radius in source pixels.
+    let support = if scale < 1.0 { 1.0 / scale } else { 1.0 };
+
+    (0..dst)
+        .map(|i| {
+            let center = (i as f64 + 0.5) / scale;
+            let start = ((center - support).floor().max(0.0)) as usize;
+            let end = ((center + support).ceil() as usize).min(src);
+
+            let mut weights: Vec<f32> = (start..end)
+                .map(|j| {
+                    let distance = ((j as f64 + 0.5) - center).abs() / support;
+                    (1.0 - distance).max(0.0) as f32
+                })
+                .collect();
+            let sum: f32 = weights.iter().sum();
+            if sum > 0.0 {
+                for w in &mut weights {
+                    *w /= sum;
+                }
+                Taps { start, weights }
+            } else {
+                // Degenerate case: fall back to nearest neighbour.
+                let nearest = (center.floor() as usize).min(src - 1);
+                Taps {
+                    start: nearest,
+                    weights: vec![1.0],
+                }
+            }
+        })
+        .collect()
+}
+
+/// Resample interleaved linear-light samples.
+///
+/// # Arguments
+///
+/// * `src` - Interleaved samples, `width * height * channels` values.
+/// * `channels` - Samples per pixel.
+/// * `width`, `height` - Source dimensions.
+/// * `new_width`, `new_height` - Target dimensions (must be non-zero).
+#[must_use]
+pub fn resample_linear(
+    src: &[f32],
+    channels: usize,
+    width: usize,
+    height: usize,
+    new_width: usize,
+    new_height: usize,
+) -> Vec<f32> {
+    if (width, height) == (new_width, new_height) || width == 0 || height == 0 {
+        return src.to_vec();
+    }
+
+    // Horizontal pass: height rows of new_width pixels.
+    let h_taps = filter_taps(width, new_width);
+    let mut tmp = vec![0.0f32; new_width * height * channels];
+    tmp.par_chunks_mut(new_width * channels)
+        .zip(src.par_chunks(width * channels))
+        .for_each(|(out, row)| {
+            for (x, taps) in h_taps.iter().enumerate() {
+                for c in 0..channels {
+                    out[x * channels + c] = taps
+                        .weights
+                        .iter()
+                        .enumerate()
+                        .map(|(i, w)| row[(taps.start + i) * channels + c] * w)
+                        .sum();
+                }
+            }
+        });
+
+    // Vertical pass.
+    let v_taps = filter_taps(height, new_height);
+    let stride = new_width * channels;
+    let mut out = vec![0.0f32; stride * new_height];
+    out.par_chunks_mut(stride)
+        .zip(v_taps.par_iter())
+        .for_each(|(row, taps)| {
+            for (i, w) in taps.weights.iter().enumerate() {
+                let src_row = &tmp[(taps.start + i) * stride..][..stride];
+                for (o, s) in row.iter_mut().zip(src_row) {
+                    *o += s * w;
+                }
+            }
+        });
+    out
+}
+
+/// Resample an sRGB RGB8 image, filtering in linear light.
+///
+/// # Arguments
+///
+/// * `rgb` - RGB8 pixel data in row-major order (sRGB gamma-encoded).
+/// * `width`, `height` - Source dimensions.
+/// * `new_width`, `new_height` - Target dimensions (must be non-zero).
+#[must_use]
+pub fn resample_srgb8(
+    rgb: &[u8],
+    width: usize,
+    height: usize,
+    new_width: usize,
+    new_height: usize,
+) -> Vec<u8> {
+    if (width, height) == (new_width, new_height) {
+        return rgb.to_vec();
+    }
+    let linear: Vec<f32> = rgb.iter().map(|&v| srgb_u8_to_linear(v)).collect();
+    resample_linear(&linear, 3, width, height, new_width, new_height)
+        .into_iter()
+        .map(linear_to_srgb_u8)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity() {
+        let rgb: Vec<u8> = (0..4 * 3 * 3).map(|i| (i * 7) as u8).collect();
+        assert_eq!(resample_srgb8(&rgb, 4, 3, 4, 3), rgb);
+    }
+
+    #[test]
+    fn test_constant_image_stays_constant() {
+        let rgb = vec![77u8; 10 * 7 * 3];
+        for (w, h) in [(5, 3), (20, 14), (13, 9), (1, 1)] {
+            let out = resample_srgb8(&rgb, 10, 7, w, h);
+            assert_eq!(out.len(), w * h * 3);
+            assert!(out.iter().all(|&v| v == 77), "{w}x{h}");
+        }
+    }
+
+    #[test]
+    fn test_downsample_averages_in_linear_light() {
+        // 2x2 black/white checkerboard averages to 50% linear, not 50% sRGB.
+        let rgb = [0, 0, 0, 255, 255, 255, 255, 255, 255, 0, 0, 0];
+        let out = resample_srgb8(&rgb, 2, 2, 1, 1);
+        assert_eq!(out, vec![188, 188, 188]);
+    }
+
+    #[test]
+    fn test_upsample_interpolates() {
+        let linear = [0.0f32, 1.0];
+        let out = resample_linear(&linear, 1, 2, 1, 4, 1);
+        assert_eq!(out.len(), 4);
+        assert!(out.windows(2).all(|w| w[0] <= w[1]));
+        assert!((out[0] - 0.0).abs() < 1e-6);
+        assert!((out[3] - 1.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,510 @@
+//! Classic SSIM and MS-SSIM.
+//!
+//! These are the structural similarity indices most codec papers and
+//! third-party benchmarks report, computed on luma per the reference
+//! definitions:
+//!
+//! - **SSIM** (Wang, Bovik, Sheikh & Simoncelli 2004): an 11x11 Gaussian
+//!   window with σ = 1.5, `K1 = 0.01`, `K2 = 0.03`, averaged over the
+//!   positions where the window fits entirely inside the image. Like the
+//!   authors' `ssim.m`, images are first downsampled by
+//!   `f = max(1, round(min(width, height) / 256))` with an `f x f` box
+//!   filter, so images with a shorter side of 384 pixels or more are scored
+//!   at roughly 256 pixels and match published numbers.
+//! - **MS-SSIM** (Wang, Simoncelli & Bovik 2003): five scales, each half the
+//!   size of the previous one after a 2x2 box filter, with the published
+//!   weights `0.0448, 0.2856, 0.3001, 0.2363, 0.1333`. Contrast-structure
+//!   terms of the first four scales are combined with the full SSIM of the
+//!   coarsest one. As in the authors' `msssim.m`, there is no automatic
+//!   downsampling; the scales already cover it.
+//!
+//! Both are higher-is-better with 1.0 for identical images. Luma is
+//! `0.299 R + 0.587 G + 0.114 B` (BT.601), rounded to integers for 8-bit
+//! input as MATLAB's `rgb2gray` does.
+//!
+//! Unlike the reference code, small images are still scored: the window
+//! shrinks to the image below 11 pixels, and MS-SSIM drops the coarsest
+//! scales that would be smaller than the window, renormalizing the remaining
+//! weights. Negative contrast-structure terms are clamped to zero so the
+//! weighted product stays defined.
+
+use crate::error::{Error, Result};
+
+/// Window size of the reference SSIM.
+const WINDOW: usize = 11;
+/// Standard deviation of the Gaussian window.
+const SIGMA: f64 = 1.5;
+const K1: f64 = 0.01;
+const K2: f64 = 0.03;
+/// MS-SSIM weights per scale, finest first.
+const MS_SSIM_WEIGHTS: [f64; 5] = [0.0448, 0.2856, 0.3001, 0.2363, 0.1333];
+
+/// Calculate SSIM between two RGB8 images.
+///
+/// # Errors
+///
+/// Returns an error if either buffer does not hold `width * height` RGB
+/// pixels, or the image is empty.
+pub fn calculate_ssim(reference: &[u8], test: &[u8], width: usize, height: usize) -> Result<f64> {
+    let (reference, test) = luma_pair_u8("SSIM", reference, test, width, height)?;
+    Ok(reference_ssim(&reference, &test, 255.0))
+}
+
+/// Calculate MS-SSIM between two RGB8 images.
+///
+/// # Errors
+///
+/// Returns an error if either buffer does not hold `width * height` RGB
+/// pixels, or the image is empty.
+pub fn calculate_ms_ssim(
+    reference: &[u8],
+    test: &[u8],
+    width: usize,
+    height: usize,
+) -> Result<f64> {
+    let (reference, test) = luma_pair_u8("MS-SSIM", reference, test, width, height)?;
+    Ok(ms_ssim(reference, test, 255.0))
+}
+
+/// Calculate SSIM between two RGB images with normalized (0.0-1.0) samples.
+///
+/// Used for high bit depth images so SSIM is computed without quantizing to
+/// 8 bits. Samples are compared as code values in their own transfer
+/// function, so both images should use the same one.
+///
+/// # Errors
+///
+/// Returns an error if either buffer does not hold `width * height` RGB
+/// pixels, or the image is empty.
+pub fn calculate_ssim_f32(
+    reference: &[f32],
+    test: &[f32],
+    width: usize,
+    height: usize,
+) -> Result<f64> {
+    let (reference, test) = luma_pair_f32("SSIM", reference, test, width, height)?;
+    Ok(reference_ssim(&reference, &test, 1.0))
+}
+
+/// Calculate MS-SSIM between two RGB images with normalized (0.0-1.0)
+/// samples. See [`calculate_ssim_f32`].
+///
+/// # Errors
+///
+/// Returns an error if either buffer does not hold `width * height` RGB
+/// pixels, or the image is empty.
+pub fn calculate_ms_ssim_f32(
+    reference: &[f32],
+    test: &[f32],
+    width: usize,
+    height: usize,
+) -> Result<f64> {
+    let (reference, test) = luma_pair_f32("MS-SSIM", reference, test, width, height)?;
+    Ok(ms_ssim(reference, test, 1.0))
+}
+
+/// A single-channel image.
+#[derive(Debug, Clone)]
+struct Plane {
+    width: usize,
+    height: usize,
+    data: Vec<f64>,
+}
+
+impl Plane {
+    /// Average 2x2 blocks, repeating the last row and column of odd sizes.
+    fn downsample(&self) -> Self {
+        let width = self.width.div_ceil(2);
+        let height = self.height.div_ceil(2);
+        let at = |x: usize, y: usize| {
+            self.data[y.min(self.height - 1) * self.width + x.min(self.width - 1)]
+        };
+        let data = (0..height)
+            .flat_map(|y| {
+                (0..width).map(move |x| {
+                    let (x, y) = (2 * x, 2 * y);
+                    (at(x, y) + at(x + 1, y) + at(x, y + 1) + at(x + 1, y + 1)) / 4.0
+                })
+            })
+            .collect();
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+
+    /// Average `f x f` blocks as `ssim.m` does: `imfilter` with a box kernel,
+    /// `'symmetric'` padding and `'same'` alignment, then every `f`-th sample
+    /// starting from the first.
+    fn box_downsample(&self, f: usize) -> Self {
+        // MATLAB centers an `f`-tap kernel on tap `floor((f + 1) / 2)`.
+        let offset = (f - 1) / 2;
+        // Sample `i - offset`, reflected at both edges with the edge repeated.
+        let mirror = |i: usize, n: usize| {
+            if i < offset {
+                (offset - 1 - i).min(n - 1)
+            } else if i - offset < n {
+                i - offset
+            } else {
+                (2 * n).saturating_sub(i - offset + 1)
+            }
+        };
+        let width = self.width.div_ceil(f);
+        let height = self.height.div_ceil(f);
+        let scale = 1.0 / (f * f) as f64;
+        let data = (0..height)
+            .flat_map(|y| {
+                (0..width).map(move |x| {
+                    let mut sum = 0.0;
+                    for dy in 0..f {
+                        let row = mirror(y * f + dy, self.height) * self.width;
+                        for dx in 0..f {
+                            sum += self.data[row + mirror(x * f + dx, self.width)];
+                        }
+                    }
+                    sum * scale
+                })
+            })
+            .collect();
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+
+    /// Correlate with the separable `kernel` in both directions, keeping
+    /// only positions where it fits entirely inside the plane.
+    fn filter_valid(&self, kernel: &[f64]) -> Self {
+        let size = kernel.len();
+        let width = self.width + 1 - size;
+        let height = self.height + 1 - size;
+        let mut rows = Vec::with_capacity(width * self.height);
+        for row in self.data.chunks_exact(self.width) {
+            rows.extend(
+                row.windows(size)
+                    .map(|w| w.iter().zip(kernel).map(|(v, k)| v * k).sum::<f64>()),
+            );
+        }
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                data.push(
+                    kernel
+                        .iter()
+                        .enumerate()
+                        .map(|(i, k)| rows[(y + i) * width + x] * k)
+                        .sum(),
+                );
+            }
+        }
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+
+    fn map(&self, other: &Self, f: impl Fn(f64, f64) -> f64) -> Self {
+        Self {
+            width: self.width,
+            height: self.height,
+            data: self
+                .data
+                .iter()
+                .zip(&other.data)
+                .map(|(&a, &b)| f(a, b))
+                .collect(),
+        }
+    }
+}
+
+/// Mean SSIM and mean contrast-structure term of one scale.
+struct SsimStats {
+    ssim: f64,
+    cs: f64,
+}
+
+/// Normalized Gaussian window of `size` taps.
+fn gaussian_kernel(size: usize) -> Vec<f64> {
+    let center = (size as f64 - 1.0) / 2.0;
+    let kernel: Vec<f64> = (0..size)
+        .map(|i| {
+            let d = i as f64 - center;
+            (-d * d / (2.0 * SIGMA * SIGMA)).exp()
+        })
+        .collect();
+    let sum: f64 = kernel.iter().sum();
+    kernel.into_iter().map(|k| k / sum).collect()
+}
+
+/// Automatic downsampling factor of `ssim.m`.
+fn downsample_factor(width: usize, height: usize) -> usize {
+    ((width.min(height) as f64 / 256.0).round() as usize).max(1)
+}
+
+/// SSIM with `ssim.m`'s automatic downsampling.
+fn reference_ssim(reference: &Plane, test: &Plane, dynamic_range: f64) -> f64 {
+    let f = downsample_factor(reference.width, reference.height);
+    if f == 1 {
+        return ssim(reference, test, dynamic_range).ssim;
+    }
+    let (reference, test) = (reference.box_downsample(f), test.box_downsample(f));
+    ssim(&reference, &test, dynamic_range).ssim
+}
+
+fn ssim(reference: &Plane, test: &Plane, dynamic_range: f64) -> SsimStats {
+    let size = WINDOW.min(reference.width).min(reference.height);
+    let kernel = gaussian_kernel(size);
+    let c1 = (K1 * dynamic_range).powi(2);
+    let c2 = (K2 * dynamic_range).powi(2);
+
+    let mu1 = reference.filter_valid(&kernel);
+    let mu2 = test.filter_valid(&kernel);
+    let sq1 = reference.map(reference, |a, b| a * b).filter_valid(&kernel);
+    let sq2 = test.map(test, |a, b| a * b).filter_valid(&kernel);
+    let cross = reference.map(test, |a, b| a * b).filter_valid(&kernel);
+
+    let (mut ssim_sum, mut cs_sum) = (0.0, 0.0);
+    for i in 0..mu1.data.len() {
+        let (m1, m2) = (mu1.data[i], mu2.data[i]);
+        let var1 = sq1.data[i] - m1 * m1;
+        let var2 = sq2.data[i] - m2 * m2;
+        let covar = cross.data[i] - m1 * m2;
+        let cs = (2.0 * covar + c2) / (var1 + var2 + c2);
+        let luminance = (2.0 * m1 * m2 + c1) / (m1 * m1 + m2 * m2 + c1);
+        ssim_sum += luminance * cs;
+        cs_sum += cs;
+    }
+    let n = mu1.data.len() as f64;
+    SsimStats {
+        ssim: ssim_sum / n,
+        cs: cs_sum / n,
+    }
+}
+
+fn ms_ssim(mut reference: Plane, mut test: Plane, dynamic_range: f64) -> f64 {
+    // Keep scales whose smaller side still fits the full window; the first
+    // scale is always scored.
+    let mut scales = 1;
+    let mut side = reference.width.min(reference.height);
+    while scales < MS_SSIM_WEIGHTS.len() && side.div_ceil(2) >= WINDOW {
+        side = side.div_ceil(2);
+        scales += 1;
+    }
+    let weights = &MS_SSIM_WEIGHTS[..scales];
+    let total: f64 = weights.iter().sum();
+
+    let mut score = 1.0;
+    for (scale, &weight) in weights.iter().enumerate() {
+        let stats = ssim(&reference, &test, dynamic_range);
+        let weight = weight / total;
+        if scale + 1 == scales {
+            score *= stats.ssim.max(0.0).powf(weight);
+        } else {
+            score *= stats.cs.max(0.0).powf(weight);
+            reference = reference.downsample();
+            test = test.downsample();
+        }
+    }
+    score
+}
+
+fn check_len(metric: &str, len: usize, width: usize, height: usize) -> Result<()> {
+    if width == 0 || height == 0 {
+        return Err(Error::MetricCalculation {
+            metric: metric.to_string(),
+            reason: "empty image".to_string(),
+        });
+    }
+    let expected = width * height * 3;
+    if len != expected {
+        return Err(Error::MetricCalculation {
+            metric: metric.to_string(),
+            reason: format!("Invalid image size: expected {expected} samples, got {len}"),
+        });
+    }
+    Ok(())
+}
+
+fn luma_pair_u8(
+    metric: &str,
+    reference: &[u8],
+    test: &[u8],
+    width: usize,
+    height: usize,
+) -> Result<(Plane, Plane)> {
+    check_len(metric, reference.len(), width, height)?;
+    check_len(metric, test.len(), width, height)?;
+    let luma = |rgb: &[u8]| Plane {
+        width,
+        height,
+        data: rgb
+            .chunks_exact(3)
+            .map(|p| {
+                let [r, g, b] = [p[0], p[1], p[2]].map(f64::from);
+                (0.299 * r + 0.587 * g + 0.114 * b).round()
+            })
+            .collect(),
+    };
+    Ok((luma(reference), luma(test)))
+}
+
+fn luma_pair_f32(
+    metric: &str,
+    reference: &[f32],
+    test: &[f32],
+    width: usize,
+    height: usize,
+) -> Result<(Plane, Plane)> {
+    check_len(metric, reference.len(), width, height)?;
+    check_len(metric, test.len(), width, height)?;
+    let luma = |rgb: &[f32]| Plane {
+        width,
+        height,
+        data: rgb
+            .chunks_exact(3)
+            .map(|p| {
+                let [r, g, b] = [p[0], p[1], p[2]].map(f64::from);
+                0.299 * r + 0.587 * g + 0.114 * b
+            })
+            .collect(),
+    };
+    Ok((luma(reference), luma(test)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(width: usize, height: usize) -> Vec<u8> {
+        (0..width * height)
+            .flat_map(|i| {
+                let (x, y) = (i % width, i / width);
+                let v = ((x * 7 + y * 13) % 256) as u8;
+                [v, v / 2, 255 - v]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_identical_images_score_one() {
+        let image = gradient(64, 48);
+        assert!((calculate_ssim(&image, &image, 64, 48).unwrap() - 1.0).abs() < 1e-12);
+        assert!((calculate_ms_ssim(&image, &image, 64, 48).unwrap() - 1.0).abs() < 1e-12);
+        // Smaller than the window.
+        let tiny = gradient(5, 4);
+        assert!((calculate_ssim(&tiny, &tiny, 5, 4).unwrap() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_distortion_lowers_scores() {
+        let (width, height) = (200, 180);
+        let reference = gradient(width, height);
+        let noise = |amount: u8| -> Vec<u8> {
+            reference
+                .iter()
+                .enumerate()
+                .map(|(i, &v)| {
+                    if (i * 2_654_435_761) % 7 < 3 {
+                        v.saturating_add(amount)
+                    } else {
+                        v.saturating_sub(amount)
+                    }
+                })
+                .collect()
+        };
+        let (light, heavy) = (noise(4), noise(40));
+
+        let ssim_light = calculate_ssim(&reference, &light, width, height).unwrap();
+        let ssim_heavy = calculate_ssim(&reference, &heavy, width, height).unwrap();
+        assert!(ssim_light < 1.0 && ssim_heavy < ssim_light);
+
+        let ms_light = calculate_ms_ssim(&reference, &light, width, height).unwrap();
+        let ms_heavy = calculate_ms_ssim(&reference, &heavy, width, height).unwrap();
+        assert!(ms_light < 1.0 && ms_heavy < ms_light);
+
+        // Normalized samples give the same answer for the same pixels,
+        // apart from luma rounding.
+        let to_f32 =
+            |rgb: &[u8]| -> Vec<f32> { rgb.iter().map(|&v| f32::from(v) / 255.0).collect() };
+        let ssim_f32 =
+            calculate_ssim_f32(&to_f32(&reference), &to_f32(&heavy), width, height).unwrap();
+        assert!(
+            (ssim_f32 - ssim_heavy).abs() < 0.01,
+            "{ssim_f32} vs {ssim_heavy}"
+        );
+    }
+
+    #[test]
+    fn test_constant_offset_is_luminance_only() {
+        // A uniform shift leaves contrast and structure intact, so all the
+        // loss comes from the luminance term.
+        let reference = vec![100u8; 32 * 32 * 3];
+        let brighter = vec![120u8; 32 * 32 * 3];
+        let expected =
+            (2.0 * 100.0 * 120.0 + 6.5025) / (100.0f64.powi(2) + 120.0f64.powi(2) + 6.5025);
+        let score = calculate_ssim(&reference, &brighter, 32, 32).unwrap();
+        assert!((score - expected).abs() < 1e-9, "{score} vs {expected}");
+    }
+
+    #[test]
+    fn test_downsample_odd_sizes() {
+        let plane = Plane {
+            width: 3,
+            height: 1,
+            data: vec![0.0, 4.0, 8.0],
+        };
+        let half = plane.downsample();
+        assert_eq!((half.width, half.height), (2, 1));
+        assert_eq!(half.data, [2.0, 8.0]);
+    }
+
+    #[test]
+    fn test_reference_downsample_factor() {
+        assert_eq!(downsample_factor(383, 1000), 1);
+        assert_eq!(downsample_factor(512, 384), 2);
+        assert_eq!(downsample_factor(640, 2000), 3);
+    }
+
+    #[test]
+    fn test_box_downsample_matches_matlab_alignment() {
+        let plane = Plane {
+            width: 5,
+            height: 1,
+            data: vec![0.0, 3.0, 6.0, 9.0, 12.0],
+        };
+        // f = 2 averages samples (0, 1), (2, 3) and (4, 4 mirrored).
+        let half = plane.box_downsample(2);
+        assert_eq!((half.width, half.height), (3, 1));
+        assert_eq!(half.data, [1.5, 7.5, 12.0]);
+        // f = 3 is centered: (0 mirrored, 0, 1) and (2, 3, 4).
+        let third = plane.box_downsample(3);
+        assert_eq!(third.data, [1.0, 9.0]);
+    }
+
+    #[test]
+    fn test_large_images_are_downsampled() {
+        let (w, h) = (400, 400);
+        let reference = gradient(w, h);
+        let test: Vec<u8> = reference.iter().map(|&v| v.saturating_add(9)).collect();
+        let (ref_luma, test_luma) = luma_pair_u8("SSIM", &reference, &test, w, h).unwrap();
+        let expected = ssim(
+            &ref_luma.box_downsample(2),
+            &test_luma.box_downsample(2),
+            255.0,
+        )
+        .ssim;
+        let score = calculate_ssim(&reference, &test, w, h).unwrap();
+        assert!((score - expected).abs() < 1e-12);
+        assert!((score - ssim(&ref_luma, &test_luma, 255.0).ssim).abs() > 1e-6);
+    }
+
+    #[test]
+    fn test_size_mismatch_is_error() {
+        let image = gradient(8, 8);
+        assert!(calculate_ssim(&image, &image[..30], 8, 8).is_err());
+        assert!(calculate_ms_ssim(&image, &image, 9, 8).is_err());
+    }
+}
@@ -28,7 +28,7 @@
 //!
 //! Without proper ICC handling, scores can be off by 1-2 points at high quality levels.
 
-use fast_ssim2::compute_ssimulacra2;
+use fast_ssim2::{Ssimulacra2Reference, compute_ssimulacra2};
 use imgref::ImgVec;
 
 use super::icc::ColorProfile;
@@ -146,6 +146,83 @@ pub fn calculate_ssimulacra2_icc(
     calculate_ssimulacra2(&ref_srgb, &test_srgb, width, height)
 }
 
+/// Calculate SSIMULACRA2 between two linear-light images.
+///
+/// Used for high bit depth and HDR images, which are compared without
+/// quantizing to 8 bits. Values are linear RGB relative to SDR reference
+/// white (see [`super::transfer`]).
+///
+/// # Errors
+///
+/// Returns an error if the buffers do not match `width * height * 3` or if
+/// calculation fails.
+pub fn calculate_ssimulacra2_linear(
+    reference: &[f32],
+    test: &[f32],
+    width: usize,
+    height: usize,
+) -> Result<f64> {
+    let expected_len = width * height * 3;
+    if reference.len() != expected_len || test.len() != expected_len {
+        return Err(Error::MetricCalculation {
+            metric: "SSIMULACRA2".to_string(),
+            reason: format!(
+                "Invalid image size: expected {expected_len} samples, got {} and {}",
+                reference.len(),
+                test.len()
+            ),
+        });
+    }
+
+    let to_img = |data: &[f32]| {
+        let pixels: Vec<[f32; 3]> = data.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect();
+        ImgVec::new(pixels, width, height)
+    };
+    let ref_img = to_img(reference);
+    let test_img = to_img(test);
+
+    compute_ssimulacra2(ref_img.as_ref(), test_img.as_ref()).map_err(|e| Error::MetricCalculation {
+        metric: "SSIMULACRA2".to_string(),
+        reason: format!("Failed to compute SSIMULACRA2: {e:?}"),
+    })
+}
+
+/// Precompute the reference side of [`calculate_ssimulacra2`], so it can be
+/// compared against many test images with [`compare_prepared`].
+pub(crate) fn prepare_reference(
+    reference: &[u8],
+    width: usize,
+    height: usize,
+) -> Result<Ssimulacra2Reference> {
+    Ssimulacra2Reference::new(rgb8_image(reference, width, height).as_ref()).map_err(|e| {
+        Error::MetricCalculation {
+            metric: "SSIMULACRA2".to_string(),
+            reason: format!("Failed to compute SSIMULACRA2: {e:?}"),
+        }
+    })
+}
+
+/// SSIMULACRA2 of `test` against a reference from [`prepare_reference`].
+pub(crate) fn compare_prepared(
+    reference: &Ssimulacra2Reference,
+    test: &[u8],
+    width: usize,
+    height: usize,
+) -> Result<f64> {
+    reference
+        .compare(rgb8_image(test, width, height).as_ref())
+        .map_err(|e| Error::MetricCalculation {
+            metric: "SSIMULACRA2".to_string(),
+            reason: format!("Failed to compute SSIMULACRA2: {e:?}"),
+        })
+}
+
+/// Flat RGB8 buffer as the pixel type fast-ssim2 takes.
+fn rgb8_image(data: &[u8], width: usize, height: usize) -> ImgVec<[u8; 3]> {
+    let pixels = data.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect();
+    ImgVec::new(pixels, width, height)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -180,4 +257,22 @@ mod tests {
         let result = calculate_ssimulacra2(&small, &large, 100, 100);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_linear_matches_u8() {
+        let ref_data: Vec<u8> = (0..64 * 64 * 3).map(|i| (i % 251) as u8).collect();
+        let test_data: Vec<u8> = ref_data.iter().map(|&v| v.saturating_add(6)).collect();
+        let linear = |d: &[u8]| {
+            d.iter()
+                .map(|&v| crate::metrics::xyb::srgb_u8_to_linear(v))
+                .collect::<Vec<_>>()
+        };
+        let score_u8 = calculate_ssimulacra2(&ref_data, &test_data, 64, 64).unwrap();
+        let score_linear =
+            calculate_ssimulacra2_linear(&linear(&ref_data), &linear(&test_data), 64, 64).unwrap();
+        assert!(
+            (score_u8 - score_linear).abs() < 0.5,
+            "u8 {score_u8} vs linear {score_linear}"
+        );
+    }
 }
@@ -0,0 +1,418 @@
+//! Tile-level and region-of-interest scoring.
+//!
+//! A whole-image score averages away local failures: a smeared face, ringing
+//! around text or a banded sky can hide behind a good overall number. With
+//! [`MetricConfig::tiles`](super::MetricConfig::tiles) set, the eval session
+//! also scores every tile of a [`TileGrid`], and every [`Region`] registered
+//! for the image with `EvalConfigBuilder::regions`. The scores are stored in
+//! [`MetricResult::tiles`] as [`TileScores`], together with the worst tile
+//! and the 90th-percentile tile per metric.
+//!
+//! Tiles and regions are given in source image pixels and are scored after
+//! viewing simulation, like the whole image. Areas smaller than 8x8 pixels
+//! after simulation, including regions outside the image, are listed with
+//! empty metrics.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::MetricResult;
+use super::custom::BUILTIN_METRICS;
+use crate::stats::QualityDirection;
+
+/// An axis-aligned rectangle in image pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rect {
+    /// Left edge.
+    pub x: u32,
+    /// Top edge.
+    pub y: u32,
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+}
+
+impl Rect {
+    /// Create a rectangle.
+    #[must_use]
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Clip to a `width` x `height` image.
+    #[must_use]
+    pub fn clamp(self, width: u32, height: u32) -> Self {
+        let x = self.x.min(width);
+        let y = self.y.min(height);
+        Self {
+            x,
+            y,
+            width: self.width.min(width - x),
+            height: self.height.min(height - y),
+        }
+    }
+
+    /// Map from a `from` sized image to a `to` sized one, growing to whole
+    /// pixels so the result covers at least the original area. The
+    /// rectangle is clipped to `from` first.
+    #[must_use]
+    pub(crate) fn scale(self, from: (u32, u32), to: (u32, u32)) -> Self {
+        let clipped = self.clamp(from.0, from.1);
+        if from == to {
+            return clipped;
+        }
+        let axis = |start: u32, len: u32, from: u32, to: u32| {
+            let (from, to) = (u64::from(from.max(1)), u64::from(to));
+            let lo = u64::from(start) * to / from;
+            let hi = ((u64::from(start) + u64::from(len)) * to).div_ceil(from);
+            (lo as u32, hi.saturating_sub(lo) as u32)
+        };
+        let (x, width) = axis(clipped.x, clipped.width, from.0, to.0);
+        let (y, height) = axis(clipped.y, clipped.height, from.1, to.1);
+        Self::new(x, y, width, height).clamp(to.0, to.1)
+    }
+
+    /// Copy the pixels under this rectangle out of a row-major image with
+    /// `channels` values per pixel.
+    #[must_use]
+    pub(crate) fn crop<T: Copy>(self, data: &[T], width: usize, channels: usize) -> Vec<T> {
+        let (x, w) = (self.x as usize, self.width as usize);
+        let mut out = Vec::with_capacity(w * self.height as usize * channels);
+        for row in self.y as usize..(self.y + self.height) as usize {
+            let start = (row * width + x) * channels;
+            out.extend_from_slice(&data[start..start + w * channels]);
+        }
+        out
+    }
+}
+
+/// A named region of interest, such as a face or a block of text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Region {
+    /// Label reported with the region's scores.
+    pub name: String,
+    /// Area of the region in source image pixels.
+    pub rect: Rect,
+}
+
+impl Region {
+    /// Create a region named `name`.
+    #[must_use]
+    pub fn new(name: &str, x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            rect: Rect::new(x, y, width, height),
+        }
+    }
+}
+
+/// Grid of tiles an image is split into for local scoring.
+///
+/// The image is divided into as many columns and rows as fit the nominal
+/// tile size best, then split evenly, so every pixel belongs to exactly one
+/// tile and edge tiles are never slivers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TileGrid {
+    /// Nominal tile width in source pixels.
+    pub tile_width: u32,
+    /// Nominal tile height in source pixels.
+    pub tile_height: u32,
+}
+
+impl Default for TileGrid {
+    /// 256x256 tiles.
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+impl TileGrid {
+    /// Smallest tile side; smaller sizes are raised to it.
+    pub const MIN_TILE: u32 = 16;
+
+    /// Square tiles of `size` pixels.
+    #[must_use]
+    pub fn new(size: u32) -> Self {
+        Self::with_size(size, size)
+    }
+
+    /// Tiles of `width` x `height` pixels.
+    #[must_use]
+    pub fn with_size(width: u32, height: u32) -> Self {
+        Self {
+            tile_width: width.max(Self::MIN_TILE),
+            tile_height: height.max(Self::MIN_TILE),
+        }
+    }
+
+    /// Columns and rows for a `width` x `height` image.
+    #[must_use]
+    pub fn layout(&self, width: u32, height: u32) -> (u32, u32) {
+        let count =
+            |len: u32, tile: u32| ((f64::from(len) / f64::from(tile)).round() as u32).max(1);
+        (
+            count(width, self.tile_width),
+            count(height, self.tile_height),
+        )
+    }
+
+    /// Tile rectangles for a `width` x `height` image, row-major.
+    #[must_use]
+    pub fn tiles(&self, width: u32, height: u32) -> Vec<Rect> {
+        let (columns, rows) = self.layout(width, height);
+        let edge = |i: u32, len: u32, count: u32| {
+            (u64::from(i) * u64::from(len) / u64::from(count)) as u32
+        };
+        let mut tiles = Vec::with_capacity((columns * rows) as usize);
+        for row in 0..rows {
+            let (top, bottom) = (edge(row, height, rows), edge(row + 1, height, rows));
+            for column in 0..columns {
+                let (left, right) = (
+                    edge(column, width, columns),
+                    edge(column + 1, width, columns),
+                );
+                tiles.push(Rect::new(left, top, right - left, bottom - top));
+            }
+        }
+        tiles
+    }
+}
+
+/// Scores of one tile or region.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TileScore {
+    /// Region name; `None` for grid tiles.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Area in source image pixels.
+    pub rect: Rect,
+    /// Scores over this area only; empty if the area was too small to score.
+    pub metrics: MetricResult,
+}
+
+/// Local scores of one comparison: every tile and region, plus aggregates.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TileScores {
+    /// Tile columns (0 when only regions were scored).
+    pub columns: u32,
+    /// Tile rows (0 when only regions were scored).
+    pub rows: u32,
+    /// Tile scores, row-major.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tiles: Vec<TileScore>,
+    /// Region scores, in registration order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regions: Vec<TileScore>,
+    /// Worst tile score per metric.
+    pub worst: MetricResult,
+    /// Per metric, the score that 90% of tiles meet or beat.
+    pub p90: MetricResult,
+}
+
+impl TileScores {
+    /// Collect tile and region scores and compute the aggregates.
+    ///
+    /// `direction` resolves custom metric names; metrics it does not know
+    /// are left out of the aggregates.
+    pub(crate) fn new(
+        (columns, rows): (u32, u32),
+        tiles: Vec<TileScore>,
+        regions: Vec<TileScore>,
+        direction: impl Fn(&str) -> Option<QualityDirection>,
+    ) -> Self {
+        let mut scores = Self {
+            columns,
+            rows,
+            tiles,
+            regions,
+            worst: MetricResult::default(),
+            p90: MetricResult::default(),
+        };
+        scores.aggregate(&direction);
+        scores
+    }
+
+    /// The score that a `fraction` (0.0-1.0) of tiles meet or beat, by
+    /// nearest rank. `fraction = 1.0` is the worst tile.
+    #[must_use]
+    pub fn percentile(
+        &self,
+        metric: &str,
+        direction: QualityDirection,
+        fraction: f64,
+    ) -> Option<f64> {
+        let mut values: Vec<f64> = self
+            .tiles
+            .iter()
+            .filter_map(|t| t.metrics.value(metric))
+            .collect();
+        if values.is_empty() {
+            return None;
+        }
+        // Best first.
+        values.sort_by(|a, b| {
+            direction
+                .higher_is_better(*b)
+                .total_cmp(&direction.higher_is_better(*a))
+        });
+        let rank = (fraction.clamp(0.0, 1.0) * values.len() as f64).ceil() as usize;
+        Some(values[rank.clamp(1, values.len()) - 1])
+    }
+
+    /// The tile with the worst score for `metric`.
+    #[must_use]
+    pub fn worst_tile(&self, metric: &str, direction: QualityDirection) -> Option<&TileScore> {
+        worst_of(&self.tiles, metric, direction)
+    }
+
+    /// The region with the worst score for `metric`.
+    #[must_use]
+    pub fn worst_region(&self, metric: &str, direction: QualityDirection) -> Option<&TileScore> {
+        worst_of(&self.regions, metric, direction)
+    }
+
+    /// Keep the worse score of each tile and region from `self` and `other`,
+    /// which must come from the same layout, and recompute the aggregates.
+    pub(crate) fn merge_worst(
+        &mut self,
+        other: &Self,
+        direction: &dyn Fn(&str) -> Option<QualityDirection>,
+    ) {
+        let pairs = self
+            .tiles
+            .iter_mut()
+            .zip(&other.tiles)
+            .chain(self.regions.iter_mut().zip(&other.regions));
+        for (mine, theirs) in pairs {
+            mine.metrics.merge_worst(&theirs.metrics, direction);
+        }
+        self.aggregate(direction);
+    }
+
+    fn aggregate(&mut self, direction: &dyn Fn(&str) -> Option<QualityDirection>) {
+        let mut worst = MetricResult::default();
+        let mut p90 = MetricResult::default();
+        let custom: BTreeSet<&String> = self
+            .tiles
+            .iter()
+            .flat_map(|t| t.metrics.custom.keys())
+            .collect();
+        let builtin = BUILTIN_METRICS.iter().map(|&(name, d)| (name, Some(d)));
+        let custom = custom.iter().map(|name| (name.as_str(), direction(name)));
+        for (name, direction) in builtin.chain(custom) {
+            let Some(direction) = direction else {
+                continue;
+            };
+            if let Some(value) = self.percentile(name, direction, 1.0) {
+                worst.set_value(name, value);
+            }
+            if let Some(value) = self.percentile(name, direction, 0.9) {
+                p90.set_value(name, value);
+            }
+        }
+        self.worst = worst;
+        self.p90 = p90;
+    }
+}
+
+fn worst_of<'a>(
+    scores: &'a [TileScore],
+    metric: &str,
+    direction: QualityDirection,
+) -> Option<&'a TileScore> {
+    scores
+        .iter()
+        .filter_map(|t| Some((t, direction.higher_is_better(t.metrics.value(metric)?))))
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(t, _)| t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(psnr: f64) -> TileScore {
+        TileScore {
+            metrics: MetricResult {
+                psnr: Some(psnr),
+                ..MetricResult::default()
+            },
+            ..TileScore::default()
+        }
+    }
+
+    #[test]
+    fn test_grid_covers_image_evenly() {
+        let grid = TileGrid::new(100);
+        assert_eq!(grid.layout(250, 90), (3, 1));
+        let tiles = grid.tiles(250, 90);
+        assert_eq!(tiles.len(), 3);
+        assert_eq!(tiles[0], Rect::new(0, 0, 83, 90));
+        assert_eq!(tiles[2], Rect::new(166, 0, 84, 90));
+        let area: u32 = tiles.iter().map(|t| t.width * t.height).sum();
+        assert_eq!(area, 250 * 90);
+
+        // Images smaller than a tile are one tile.
+        assert_eq!(grid.tiles(20, 20), vec![Rect::new(0, 0, 20, 20)]);
+        assert_eq!(TileGrid::new(1).tile_width, TileGrid::MIN_TILE);
+    }
+
+    #[test]
+    fn test_rect_scale_and_crop() {
+        let rect = Rect::new(10, 10, 5, 5).scale((100, 100), (50, 50));
+        assert_eq!(rect, Rect::new(5, 5, 3, 3));
+        assert_eq!(
+            Rect::new(90, 0, 20, 10).clamp(100, 5),
+            Rect::new(90, 0, 10, 5)
+        );
+
+        // Clipped before scaling, so far-off rectangles cannot overflow.
+        assert_eq!(
+            Rect::new(u32::MAX - 1, 0, 10, 10).scale((100, 100), (50, 50)),
+            Rect::new(50, 0, 0, 5)
+        );
+
+        let data: Vec<u8> = (0..16).collect();
+        assert_eq!(Rect::new(1, 1, 2, 2).crop(&data, 4, 1), vec![5, 6, 9, 10]);
+    }
+
+    #[test]
+    fn test_aggregates_follow_direction() {
+        let tiles: Vec<TileScore> = (1..=10).map(|i| tile(f64::from(i) * 10.0)).collect();
+        let mut regions = vec![tile(35.0), tile(25.0)];
+        regions[1].name = Some("face".to_string());
+        let scores = TileScores::new((5, 2), tiles, regions, |_| None);
+
+        // PSNR is higher-is-better: the worst tile is the lowest.
+        assert_eq!(scores.worst.psnr, Some(10.0));
+        // Nine of ten tiles score 20 dB or better.
+        assert_eq!(scores.p90.psnr, Some(20.0));
+        assert!(scores.worst.dssim.is_none());
+
+        let face = scores
+            .worst_region("psnr", QualityDirection::HigherIsBetter)
+            .unwrap();
+        assert_eq!(face.name.as_deref(), Some("face"));
+        assert_eq!(
+            scores.percentile("psnr", QualityDirection::LowerIsBetter, 1.0),
+            Some(100.0)
+        );
+    }
+
+    #[test]
+    fn test_merge_worst_per_tile() {
+        let mut a = TileScores::new((2, 1), vec![tile(40.0), tile(20.0)], vec![], |_| None);
+        let b = TileScores::new((2, 1), vec![tile(30.0), tile(50.0)], vec![], |_| None);
+        a.merge_worst(&b, &|_| None);
+        assert_eq!(a.tiles[0].metrics.psnr, Some(30.0));
+        assert_eq!(a.tiles[1].metrics.psnr, Some(20.0));
+        assert_eq!(a.worst.psnr, Some(20.0));
+    }
+}
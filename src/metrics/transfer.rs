@@ -0,0 +1,194 @@
+//! Transfer functions for high bit depth and HDR images.
+//!
+//! Metrics that work in linear light (DSSIM, SSIMULACRA2, Butteraugli) need
+//! to know how code values map to luminance. Linear values produced here are
+//! relative to SDR reference white: `1.0` is sRGB white, or 203 cd/m² for PQ
+//! and HLG content (ITU-R BT.2408). HDR highlights exceed `1.0`.
+
+use serde::{Deserialize, Serialize};
+
+/// Transfer function (OETF/EOTF) of an image's code values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TransferFunction {
+    /// sRGB (IEC 61966-2-1).
+    #[default]
+    Srgb,
+    /// Perceptual Quantizer (SMPTE ST 2084), absolute luminance up to 10000 cd/m².
+    Pq,
+    /// Hybrid Log-Gamma (ITU-R BT.2100), displayed on a 1000 cd/m² reference display.
+    Hlg,
+    /// Linear light; `1.0` is SDR reference white.
+    Linear,
+}
+
+/// Luminance of SDR reference white for HDR content in cd/m² (ITU-R BT.2408).
+pub const SDR_WHITE_NITS: f32 = 203.0;
+
+/// Peak luminance of the HLG reference display in cd/m².
+const HLG_PEAK_NITS: f32 = 1000.0;
+
+/// HLG system gamma at the reference peak luminance.
+const HLG_GAMMA: f32 = 1.2;
+
+// SMPTE ST 2084 constants.
+const PQ_M1: f32 = 2610.0 / 16384.0;
+const PQ_M2: f32 = 2523.0 / 4096.0 * 128.0;
+const PQ_C1: f32 = 3424.0 / 4096.0;
+const PQ_C2: f32 = 2413.0 / 4096.0 * 32.0;
+const PQ_C3: f32 = 2392.0 / 4096.0 * 32.0;
+const PQ_PEAK_NITS: f32 = 10000.0;
+
+// ITU-R BT.2100 HLG constants.
+const HLG_A: f32 = 0.178_832_77;
+const HLG_B: f32 = 0.284_668_92;
+const HLG_C: f32 = 0.559_910_7;
+
+impl TransferFunction {
+    /// Whether this is an HDR transfer function (PQ or HLG).
+    #[must_use]
+    pub fn is_hdr(self) -> bool {
+        matches!(self, Self::Pq | Self::Hlg)
+    }
+
+    /// Largest linear value a full-scale code value decodes to.
+    ///
+    /// `1.0` for sRGB and linear (values above SDR white are not bounded).
+    #[must_use]
+    pub fn peak_linear(self) -> f32 {
+        match self {
+            Self::Srgb | Self::Linear => 1.0,
+            Self::Pq => PQ_PEAK_NITS / SDR_WHITE_NITS,
+            Self::Hlg => HLG_PEAK_NITS / SDR_WHITE_NITS,
+        }
+    }
+
+    /// Short name for reports.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Srgb => "srgb",
+            Self::Pq => "pq",
+            Self::Hlg => "hlg",
+            Self::Linear => "linear",
+        }
+    }
+
+    /// Convert a normalized code value (0.0-1.0) to linear light relative to
+    /// SDR reference white.
+    ///
+    /// The HLG OOTF is applied per component, which is exact for neutral
+    /// colours and a close approximation elsewhere.
+    #[must_use]
+    pub fn to_linear(self, v: f32) -> f32 {
+        match self {
+            Self::Srgb => {
+                let v = v.clamp(0.0, 1.0);
+                if v <= 0.04045 {
+                    v / 12.92
+                } else {
+                    ((v + 0.055) / 1.055).powf(2.4)
+                }
+            }
+            Self::Pq => {
+                let p = v.clamp(0.0, 1.0).powf(1.0 / PQ_M2);
+                let nits =
+                    ((p - PQ_C1).max(0.0) / (PQ_C2 - PQ_C3 * p)).powf(1.0 / PQ_M1) * PQ_PEAK_NITS;
+                nits / SDR_WHITE_NITS
+            }
+            Self::Hlg => {
+                let v = v.clamp(0.0, 1.0);
+                let scene = if v <= 0.5 {
+                    v * v / 3.0
+                } else {
+                    (((v - HLG_C) / HLG_A).exp() + HLG_B) / 12.0
+                };
+                scene.powf(HLG_GAMMA) * HLG_PEAK_NITS / SDR_WHITE_NITS
+            }
+            Self::Linear => v.max(0.0),
+        }
+    }
+
+    /// Convert linear light relative to SDR reference white back to a
+    /// normalized code value. Inverse of [`TransferFunction::to_linear`].
+    #[must_use]
+    pub fn from_linear(self, v: f32) -> f32 {
+        let v = v.max(0.0);
+        match self {
+            Self::Srgb => {
+                let v = v.min(1.0);
+                if v <= 0.003_130_8 {
+                    v * 12.92
+                } else {
+                    1.055 * v.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            Self::Pq => {
+                let y = (v * SDR_WHITE_NITS / PQ_PEAK_NITS).min(1.0);
+                let p = y.powf(PQ_M1);
+                ((PQ_C1 + PQ_C2 * p) / (1.0 + PQ_C3 * p)).powf(PQ_M2)
+            }
+            Self::Hlg => {
+                let scene = (v * SDR_WHITE_NITS / HLG_PEAK_NITS)
+                    .min(1.0)
+                    .powf(1.0 / HLG_GAMMA);
+                if scene <= 1.0 / 12.0 {
+                    (3.0 * scene).sqrt()
+                } else {
+                    HLG_A * (12.0 * scene - HLG_B).ln() + HLG_C
+                }
+            }
+            Self::Linear => v,
+        }
+    }
+}
+
+/// Convert normalized code values to linear light, component by component.
+#[must_use]
+pub fn linearize(signal: &[f32], transfer: TransferFunction) -> Vec<f32> {
+    signal.iter().map(|&v| transfer.to_linear(v)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        for transfer in [
+            TransferFunction::Srgb,
+            TransferFunction::Pq,
+            TransferFunction::Hlg,
+            TransferFunction::Linear,
+        ] {
+            for i in 0..=20 {
+                let v = i as f32 / 20.0;
+                let back = transfer.from_linear(transfer.to_linear(v));
+                assert!((back - v).abs() < 1e-3, "{transfer:?} {v} -> {back}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_pq_reference_levels() {
+        // PQ code value 0.58 is about 203 cd/m² (SDR reference white).
+        assert!((TransferFunction::Pq.to_linear(0.5807) - 1.0).abs() < 0.01);
+        // Full scale is 10000 cd/m².
+        let peak = TransferFunction::Pq.to_linear(1.0);
+        assert!((peak * SDR_WHITE_NITS - 10000.0).abs() < 1.0);
+        assert!((peak - TransferFunction::Pq.peak_linear()).abs() < 1e-3);
+        assert!(TransferFunction::Pq.to_linear(0.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_hlg_reference_white() {
+        // HLG 75% is SDR reference white on a 1000 cd/m² display.
+        assert!((TransferFunction::Hlg.to_linear(0.75) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_srgb_matches_u8_path() {
+        let v = 128.0 / 255.0;
+        let expected = crate::metrics::xyb::srgb_u8_to_linear(128);
+        assert!((TransferFunction::Srgb.to_linear(v) - expected).abs() < 1e-6);
+    }
+}
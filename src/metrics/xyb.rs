@@ -77,13 +77,13 @@ fn linear_to_srgb_f32(v: f32) -> f32 {
 
 /// Convert sRGB u8 to linear float.
 #[inline]
-fn srgb_u8_to_linear(v: u8) -> f32 {
+pub(crate) fn srgb_u8_to_linear(v: u8) -> f32 {
     srgb_to_linear_f32(f32::from(v) / 255.0)
 }
 
 /// Convert linear float to sRGB u8.
 #[inline]
-fn linear_to_srgb_u8(v: f32) -> u8 {
+pub(crate) fn linear_to_srgb_u8(v: f32) -> u8 {
     (linear_to_srgb_f32(v.clamp(0.0, 1.0)) * 255.0).round() as u8
 }
 
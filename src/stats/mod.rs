@@ -11,6 +11,10 @@
 //! - [`trimmed_mean`]: Robust mean excluding outliers
 //! - [`iqr`]: Interquartile range
 //!
+//! ## Metric Validation
+//!
+//! - [`correlate`]: SROCC, PLCC and KROCC with bootstrap confidence intervals
+//!
 //! ## Rate-Distortion Analysis
 //!
 //! - [`bd_rate`]: Bjontegaard Delta Rate calculation
@@ -18,16 +22,19 @@
 
 #[cfg(feature = "chart")]
 pub mod chart;
+mod correlation;
 mod pareto;
 pub mod rd_knee;
 
 #[cfg(feature = "chart")]
 pub use chart::{ChartConfig, ChartPoint, ChartSeries, generate_svg};
+pub use correlation::{Bootstrap, Correlation, CorrelationSummary, correlate, krocc, plcc, srocc};
 pub use pareto::{ParetoFront, RDPoint};
 pub use rd_knee::{
-    AngleBin, AxisRange, BinScheme, CodecConfig, ConfiguredParetoFront, ConfiguredRDPoint,
-    CorpusAggregate, DualAngleBin, EncodeResult, FixedFrame, NormalizationContext, ParamValue,
-    QualityDirection, RDCalibration, RDKnee, RDPosition, plot_rd_svg,
+    AngleBin, AxisRange, BinScheme, ButteraugliNorm, CodecConfig, ConfiguredParetoFront,
+    ConfiguredRDPoint, CorpusAggregate, DualAngleBin, EncodeResult, FixedFrame,
+    NormalizationContext, ParamValue, QualityDirection, RDCalibration, RDKnee, RDPosition,
+    plot_rd_svg,
 };
 
 use serde::{Deserialize, Serialize};
@@ -309,7 +316,9 @@ fn percentile_sorted(sorted: &[f64], p: f64) -> f64 {
 ///
 /// # Returns
 ///
-/// BD-Rate as a percentage. Negative = test is better.
+/// BD-Rate as a percentage. Negative = test is better. A test curve that
+/// needs exactly twice the reference bitrate everywhere scores 100%; half
+/// the bitrate scores -50%.
 #[must_use]
 pub fn bd_rate(reference: &[(f64, f64)], test: &[(f64, f64)]) -> Option<f64> {
     if reference.len() < 4 || test.len() < 4 {
@@ -341,8 +350,8 @@ pub fn bd_rate(reference: &[(f64, f64)], test: &[(f64, f64)]) -> Option<f64> {
     let avg_ref = ref_area / (max_quality - min_quality);
     let avg_test = test_area / (max_quality - min_quality);
 
-    // BD-Rate = (10^(avg_test - avg_ref) - 1) * 100
-    let bd = (10_f64.powf(avg_test - avg_ref) - 1.0) * 100.0;
+    // BD-Rate = (e^(avg_test - avg_ref) - 1) * 100, matching the natural log above
+    let bd = ((avg_test - avg_ref).exp() - 1.0) * 100.0;
 
     Some(bd)
 }
@@ -448,4 +457,14 @@ mod tests {
         assert!(bd.is_some());
         assert!(bd.unwrap().abs() < 0.1); // Should be ~0 for same curve
     }
+
+    #[test]
+    fn test_bd_rate_known_values() {
+        let reference = vec![(1.0, 30.0), (2.0, 35.0), (4.0, 40.0), (8.0, 45.0)];
+        for (scale, expected) in [(2.0, 100.0), (1.2, 20.0), (0.5, -50.0)] {
+            let test: Vec<_> = reference.iter().map(|&(r, q)| (r * scale, q)).collect();
+            let bd = bd_rate(&reference, &test).unwrap();
+            assert!((bd - expected).abs() < 1e-6, "{scale}x: got {bd}");
+        }
+    }
 }
@@ -25,6 +25,9 @@ pub struct RDPoint {
     /// Optional encode time in milliseconds.
     pub encode_time_ms: Option<f64>,
 
+    /// Optional peak heap growth during the encode, in bytes.
+    pub encode_peak_bytes: Option<u64>,
+
     /// Optional image name.
     pub image: Option<String>,
 }
@@ -39,6 +42,7 @@ impl RDPoint {
             bpp,
             quality,
             encode_time_ms: None,
+            encode_peak_bytes: None,
             image: None,
         }
     }
@@ -59,6 +63,38 @@ impl RDPoint {
 
         better_or_equal_bpp && better_or_equal_quality && strictly_better
     }
+
+    /// Check if this point dominates another with encode time as a third
+    /// objective.
+    ///
+    /// Objectives are those of [`dominates`](Self::dominates) plus lower
+    /// `encode_time_ms` is better. A point without an encode time counts as
+    /// slower than any timed point.
+    #[must_use]
+    pub fn dominates_with_time(&self, other: &Self) -> bool {
+        self.dominates_with_cost(other, |p| p.encode_time_ms.unwrap_or(f64::INFINITY))
+    }
+
+    /// Check if this point dominates another with peak encode memory as a
+    /// third objective (lower is better). A point without a measurement
+    /// counts as using more memory than any measured point.
+    #[must_use]
+    pub fn dominates_with_memory(&self, other: &Self) -> bool {
+        self.dominates_with_cost(other, |p| {
+            p.encode_peak_bytes.map_or(f64::INFINITY, |b| b as f64)
+        })
+    }
+
+    fn dominates_with_cost(&self, other: &Self, cost: impl Fn(&Self) -> f64) -> bool {
+        let (self_cost, other_cost) = (cost(self), cost(other));
+
+        let better_or_equal =
+            self.bpp <= other.bpp && self.quality >= other.quality && self_cost <= other_cost;
+        let strictly_better =
+            self.bpp < other.bpp || self.quality > other.quality || self_cost < other_cost;
+
+        better_or_equal && strictly_better
+    }
 }
 
 /// Pareto front of rate-distortion points.
@@ -74,15 +110,36 @@ impl ParetoFront {
     /// Returns a new `ParetoFront` containing only the non-dominated points.
     #[must_use]
     pub fn compute(points: &[RDPoint]) -> Self {
+        Self::compute_by(points, RDPoint::dominates)
+    }
+
+    /// Compute the Pareto front over size, quality and encode time.
+    ///
+    /// A slower point survives if it is smaller or better than every faster
+    /// one, so the front shows what each extra millisecond of encoding buys.
+    /// See [`RDPoint::dominates_with_time`].
+    #[must_use]
+    pub fn compute_with_time(points: &[RDPoint]) -> Self {
+        Self::compute_by(points, RDPoint::dominates_with_time)
+    }
+
+    /// Compute the Pareto front over size, quality and peak encode memory.
+    /// See [`RDPoint::dominates_with_memory`].
+    #[must_use]
+    pub fn compute_with_memory(points: &[RDPoint]) -> Self {
+        Self::compute_by(points, RDPoint::dominates_with_memory)
+    }
+
+    fn compute_by(points: &[RDPoint], dominates: fn(&RDPoint, &RDPoint) -> bool) -> Self {
         let mut front = Vec::new();
 
         for point in points {
             // Check if any existing front point dominates this one
-            let is_dominated = front.iter().any(|p: &RDPoint| p.dominates(point));
+            let is_dominated = front.iter().any(|p: &RDPoint| dominates(p, point));
 
             if !is_dominated {
                 // Remove any front points that this point dominates
-                front.retain(|p| !point.dominates(p));
+                front.retain(|p| !dominates(point, p));
                 front.push(point.clone());
             }
         }
@@ -269,4 +326,44 @@ mod tests {
         let best = front.best_at_quality(85.0).unwrap();
         assert_eq!(best.codec, "b");
     }
+
+    #[test]
+    fn test_pareto_with_time() {
+        let timed = |codec: &str, bpp: f64, quality: f64, ms: f64| {
+            let mut point = RDPoint::new(codec, 80.0, bpp, quality);
+            point.encode_time_ms = Some(ms);
+            point
+        };
+        let points = vec![
+            timed("slow", 1.0, 90.0, 500.0),
+            // Worse size and quality, but ten times faster.
+            timed("fast", 1.2, 88.0, 50.0),
+            // Dominated on every axis by "fast".
+            timed("worse", 1.3, 87.0, 60.0),
+            RDPoint::new("untimed", 80.0, 1.2, 88.0),
+        ];
+
+        let front = ParetoFront::compute(&points);
+        assert_eq!(front.codecs(), vec!["slow"]);
+
+        let front = ParetoFront::compute_with_time(&points);
+        assert_eq!(front.codecs(), vec!["fast", "slow"]);
+    }
+
+    #[test]
+    fn test_pareto_with_memory() {
+        let measured = |codec: &str, bpp: f64, bytes: u64| {
+            let mut point = RDPoint::new(codec, 80.0, bpp, 90.0);
+            point.encode_peak_bytes = Some(bytes);
+            point
+        };
+        let points = vec![
+            measured("big", 1.0, 64 << 20),
+            measured("lean", 1.1, 4 << 20),
+            measured("bloated", 1.2, 128 << 20),
+        ];
+
+        let front = ParetoFront::compute_with_memory(&points);
+        assert_eq!(front.codecs(), vec!["big", "lean"]);
+    }
 }
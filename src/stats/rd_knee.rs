@@ -39,6 +39,10 @@
 //! SSIMULACRA2 and Butteraugli produce different angles for the same encode.
 //! Comparing `theta_s2` and `theta_ba` reveals what kind of artifacts the
 //! codec configuration produces at that operating point.
+//!
+//! The Butteraugli axis holds either the max-norm or the p-norm score, as
+//! recorded in [`CorpusAggregate::butteraugli_norm`]. The p-norm is steadier
+//! across images; [`FixedFrame::WEB`]'s `ba_max` is on the max-norm scale.
 
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
@@ -59,7 +63,8 @@ pub struct FixedFrame {
     pub bpp_max: f64,
     /// SSIMULACRA2 scale maximum. Always 100.
     pub s2_max: f64,
-    /// Butteraugli practical worst-case. Default: 15.0.
+    /// Butteraugli practical worst-case. Default: 15.0, on the max-norm
+    /// scale; p-norm scores are lower.
     pub ba_max: f64,
     /// Quality-axis stretch factor. Calibrated from reference knee so
     /// that `atan2(q_norm * aspect, 1 - bpp_norm) = 45 deg` at the knee.
@@ -123,6 +128,28 @@ impl Default for FixedFrame {
     }
 }
 
+/// Which Butteraugli score fills the `ba` axis.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ButteraugliNorm {
+    /// Max-norm, the worst pixel (`MetricResult::butteraugli`).
+    #[default]
+    Max,
+    /// p-norm over the whole diffmap (`MetricResult::butteraugli_pnorm`).
+    PNorm,
+}
+
+impl ButteraugliNorm {
+    /// Metric name of this score, as used by `MetricResult::value`.
+    #[must_use]
+    pub fn metric(self) -> &'static str {
+        match self {
+            Self::Max => "butteraugli",
+            Self::PNorm => "butteraugli_pnorm",
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Normalization (retained for knee detection on corpus-aggregate data)
 // ---------------------------------------------------------------------------
@@ -164,6 +191,27 @@ pub enum QualityDirection {
     LowerIsBetter,
 }
 
+impl QualityDirection {
+    /// Map a raw score so that higher is always better (negates
+    /// lower-is-better scores).
+    #[must_use]
+    pub fn higher_is_better(self, value: f64) -> f64 {
+        match self {
+            Self::HigherIsBetter => value,
+            Self::LowerIsBetter => -value,
+        }
+    }
+
+    /// The worse of two scores.
+    #[must_use]
+    pub fn worse(self, a: f64, b: f64) -> f64 {
+        match self {
+            Self::HigherIsBetter => a.min(b),
+            Self::LowerIsBetter => a.max(b),
+        }
+    }
+}
+
 /// Normalization context for knee detection (uses per-curve observed ranges).
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct NormalizationContext {
@@ -227,6 +275,10 @@ pub struct RDCalibration {
     /// Knee in Butteraugli space.
     pub butteraugli: RDKnee,
 
+    /// Which Butteraugli score the knee was found on.
+    #[serde(default)]
+    pub butteraugli_norm: ButteraugliNorm,
+
     /// Which corpus was used.
     pub corpus: String,
 
@@ -629,6 +681,9 @@ pub struct CorpusAggregate {
     /// Averaged R-D points sorted by bpp: (bpp, mean_s2, mean_butteraugli).
     pub curve: Vec<(f64, f64, f64)>,
     pub image_count: usize,
+    /// Which Butteraugli score the curve holds.
+    #[serde(default)]
+    pub butteraugli_norm: ButteraugliNorm,
 }
 
 impl CorpusAggregate {
@@ -662,6 +717,7 @@ impl CorpusAggregate {
             frame: *frame,
             ssimulacra2: s2_knee,
             butteraugli: ba_knee,
+            butteraugli_norm: self.butteraugli_norm,
             corpus: self.corpus.clone(),
             codec: self.codec.clone(),
             image_count: self.image_count,
@@ -912,6 +968,10 @@ pub fn plot_rd_svg(curve: &[(f64, f64, f64)], calibration: &RDCalibration, title
     );
 
     let ba_knee = &calibration.butteraugli;
+    let ba = match calibration.butteraugli_norm {
+        ButteraugliNorm::Max => "ba",
+        ButteraugliNorm::PNorm => "ba-pnorm",
+    };
     // Find s2 value at the ba knee bpp (interpolate on the curve)
     let s2_at_ba_knee = interpolate_curve_s2(curve, ba_knee.bpp).unwrap_or(50.0);
     let bkx = x_of(ba_knee.bpp);
@@ -922,7 +982,7 @@ pub fn plot_rd_svg(curve: &[(f64, f64, f64)], calibration: &RDCalibration, title
     );
     let _ = write!(
         svg,
-        r##"<text x="{:.0}" y="{:.0}" fill="#3498db" font-size="10">ba knee {:.1}° ({:.2} bpp, ba={:.2})</text>"##,
+        r##"<text x="{:.0}" y="{:.0}" fill="#3498db" font-size="10">{ba} knee {:.1}° ({:.2} bpp, {ba}={:.2})</text>"##,
         bkx + 12.0,
         bky + 14.0,
         ba_knee.fixed_angle,
@@ -1006,7 +1066,8 @@ fn interpolate_curve_s2(curve: &[(f64, f64, f64)], target_bpp: f64) -> Option<f6
 /// Fixed frame: bpp_max=4.0, s2_max=100, ba_max=15.
 pub mod defaults {
     use super::{
-        AxisRange, FixedFrame, NormalizationContext, QualityDirection, RDCalibration, RDKnee,
+        AxisRange, ButteraugliNorm, FixedFrame, NormalizationContext, QualityDirection,
+        RDCalibration, RDKnee,
     };
 
     /// MozJPEG 4:2:0 progressive on CID22-training (209 images, 512x512).
@@ -1039,6 +1100,7 @@ pub mod defaults {
                     direction: QualityDirection::LowerIsBetter,
                 },
             },
+            butteraugli_norm: ButteraugliNorm::Max,
             corpus: "CID22-training".into(),
             codec: "mozjpeg-420-prog".into(),
             image_count: 209,
@@ -1076,6 +1138,7 @@ pub mod defaults {
                     direction: QualityDirection::LowerIsBetter,
                 },
             },
+            butteraugli_norm: ButteraugliNorm::Max,
             corpus: "CLIC2025-training".into(),
             codec: "mozjpeg-420-prog".into(),
             image_count: 32,
@@ -1091,8 +1154,9 @@ pub mod defaults {
 #[cfg(test)]
 mod tests {
     use super::{
-        AngleBin, AxisRange, BinScheme, CodecConfig, ConfiguredParetoFront, ConfiguredRDPoint,
-        CorpusAggregate, FixedFrame, NormalizationContext, ParamValue, QualityDirection, defaults,
+        AngleBin, AxisRange, BinScheme, ButteraugliNorm, CodecConfig, ConfiguredParetoFront,
+        ConfiguredRDPoint, CorpusAggregate, FixedFrame, NormalizationContext, ParamValue,
+        QualityDirection, defaults,
     };
 
     fn make_test_curve() -> Vec<(f64, f64, f64)> {
@@ -1195,6 +1259,7 @@ mod tests {
             codec: "test-codec".into(),
             curve,
             image_count: 1,
+            butteraugli_norm: ButteraugliNorm::Max,
         };
 
         let knee = agg
@@ -1225,6 +1290,7 @@ mod tests {
             codec: "test-codec".into(),
             curve,
             image_count: 1,
+            butteraugli_norm: ButteraugliNorm::Max,
         };
 
         let knee = agg
@@ -1244,6 +1310,7 @@ mod tests {
             codec: "test-codec".into(),
             curve,
             image_count: 1,
+            butteraugli_norm: ButteraugliNorm::Max,
         };
 
         let cal = agg.calibrate(&FixedFrame::WEB).expect("should calibrate");
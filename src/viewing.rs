@@ -219,6 +219,12 @@ impl ViewingCondition {
 
     /// Compute simulation parameters for a given image size.
     ///
+    /// The metrics are calibrated for [`REFERENCE_PPD`]. To simulate this viewing
+    /// condition, the image is resampled by `REFERENCE_PPD / effective_ppd()`:
+    /// undersized srcset images (or low-PPD displays) are upscaled so artifacts
+    /// span more pixels, oversized images and high-PPD displays are downscaled
+    /// so artifacts shrink, exactly as the browser and the eye would.
+    ///
     /// Returns the scale factor to apply and the adjusted PPD for metrics.
     ///
     /// # Arguments
@@ -239,6 +245,9 @@ impl ViewingCondition {
     /// let params = condition.simulation_params(1000, 800, SimulationMode::DownsampleOnly);
     /// assert_eq!(params.scale_factor, 1.0); // No upscaling
     /// assert!(params.adjusted_ppd < 40.0);  // Adjusted for missing upscale
+    ///
+    /// let params = condition.simulation_params(1000, 800, SimulationMode::Accurate);
+    /// assert_eq!((params.target_width, params.target_height), (2000, 1600));
     /// ```
     #[must_use]
     pub fn simulation_params(
@@ -249,54 +258,44 @@ impl ViewingCondition {
     ) -> SimulationParams {
         let ratio = self.srcset_ratio();
         let base_ppd = self.acuity_ppd;
+        let effective_ppd = self.effective_ppd();
+        let scale_factor = if effective_ppd > 0.0 {
+            REFERENCE_PPD / effective_ppd
+        } else {
+            1.0
+        };
+
+        if mode == SimulationMode::DownsampleOnly && scale_factor > 1.0 {
+            // Undersized: keep original size, adjust PPD instead
+            // The effective PPD is reduced because we're not simulating the upscale
+            // that would make artifacts more visible
+            let adjusted_ppd = base_ppd * ratio;
+
+            return SimulationParams {
+                scale_factor: 1.0,
+                target_width: image_width,
+                target_height: image_height,
+                adjusted_ppd,
+                requires_upscale: false, // We skip upscaling
+                requires_downscale: false,
+            };
+        }
 
-        match mode {
-            SimulationMode::Accurate => {
-                // Full simulation: scale by ratio
-                let scale_factor = ratio;
-                let target_width = (image_width as f64 * scale_factor).round() as u32;
-                let target_height = (image_height as f64 * scale_factor).round() as u32;
-
-                SimulationParams {
-                    scale_factor,
-                    target_width,
-                    target_height,
-                    adjusted_ppd: self.effective_ppd(),
-                    requires_upscale: ratio < 1.0,
-                    requires_downscale: ratio > 1.0,
-                }
-            }
-            SimulationMode::DownsampleOnly => {
-                if ratio >= 1.0 {
-                    // Oversized: downsample normally
-                    let scale_factor = ratio;
-                    let target_width = (image_width as f64 * scale_factor).round() as u32;
-                    let target_height = (image_height as f64 * scale_factor).round() as u32;
-
-                    SimulationParams {
-                        scale_factor,
-                        target_width,
-                        target_height,
-                        adjusted_ppd: self.effective_ppd(),
-                        requires_upscale: false,
-                        requires_downscale: ratio > 1.0,
-                    }
-                } else {
-                    // Undersized: keep original size, adjust PPD instead
-                    // The effective PPD is reduced because we're not simulating the upscale
-                    // that would make artifacts more visible
-                    let adjusted_ppd = base_ppd * ratio;
-
-                    SimulationParams {
-                        scale_factor: 1.0,
-                        target_width: image_width,
-                        target_height: image_height,
-                        adjusted_ppd,
-                        requires_upscale: false, // We skip upscaling
-                        requires_downscale: false,
-                    }
-                }
-            }
+        // Treat float noise around 1.0 as native resolution.
+        let scale_factor = if (scale_factor - 1.0).abs() < 1e-9 {
+            1.0
+        } else {
+            scale_factor
+        };
+        let scaled = |size: u32| ((f64::from(size) * scale_factor).round() as u32).max(1);
+
+        SimulationParams {
+            scale_factor,
+            target_width: scaled(image_width),
+            target_height: scaled(image_height),
+            adjusted_ppd: effective_ppd,
+            requires_upscale: scale_factor > 1.0,
+            requires_downscale: scale_factor < 1.0,
         }
     }
 }
@@ -306,7 +305,7 @@ impl ViewingCondition {
 /// Describes how to transform an image and adjust metrics for a viewing condition.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct SimulationParams {
-    /// Scale factor to apply to the image (1.0 = no scaling).
+    /// Scale factor to apply to the image (1.0 = no scaling, > 1.0 = upscale).
     pub scale_factor: f64,
 
     /// Target width after scaling.
@@ -349,6 +348,24 @@ impl SimulationParams {
         self.scale_factor.min(1.0)
     }
 
+    /// Resample an sRGB RGB8 image to the simulated viewing size.
+    ///
+    /// Resampling happens in linear light. Returns the input unchanged when no
+    /// scaling is required. The output is `target_width × target_height`.
+    #[must_use]
+    pub fn apply_rgb8(&self, rgb: &[u8], width: usize, height: usize) -> Vec<u8> {
+        if !self.requires_scaling() {
+            return rgb.to_vec();
+        }
+        crate::metrics::resample::resample_srgb8(
+            rgb,
+            width,
+            height,
+            self.target_width as usize,
+            self.target_height as usize,
+        )
+    }
+
     /// Compute threshold multiplier for metric values.
     ///
     /// This accounts for how viewing conditions affect artifact visibility.
@@ -784,9 +801,9 @@ mod tests {
         let params = v.simulation_params(1000, 800, SimulationMode::Accurate);
 
         // Should upscale to simulate browser behavior
-        assert!((params.scale_factor - 0.5).abs() < 0.001);
-        assert_eq!(params.target_width, 500);
-        assert_eq!(params.target_height, 400);
+        assert!((params.scale_factor - 2.0).abs() < 0.001);
+        assert_eq!(params.target_width, 2000);
+        assert_eq!(params.target_height, 1600);
         assert!(params.requires_upscale); // ratio < 1 means browser upscales
         assert!(!params.requires_downscale);
     }
@@ -801,9 +818,9 @@ mod tests {
         let params = v.simulation_params(1000, 800, SimulationMode::Accurate);
 
         // Should downscale
-        assert!((params.scale_factor - 2.0).abs() < 0.001);
-        assert_eq!(params.target_width, 2000);
-        assert_eq!(params.target_height, 1600);
+        assert!((params.scale_factor - 0.5).abs() < 0.001);
+        assert_eq!(params.target_width, 500);
+        assert_eq!(params.target_height, 400);
         assert!(!params.requires_upscale);
         assert!(params.requires_downscale);
     }
@@ -838,9 +855,9 @@ mod tests {
         let params = v.simulation_params(1000, 800, SimulationMode::DownsampleOnly);
 
         // Should downscale (oversized images are fine to downscale)
-        assert!((params.scale_factor - 2.0).abs() < 0.001);
-        assert_eq!(params.target_width, 2000);
-        assert_eq!(params.target_height, 1600);
+        assert!((params.scale_factor - 0.5).abs() < 0.001);
+        assert_eq!(params.target_width, 500);
+        assert_eq!(params.target_height, 400);
         assert!(!params.requires_upscale);
         assert!(params.requires_downscale);
     }
@@ -848,28 +865,56 @@ mod tests {
     #[test]
     fn test_simulation_params_helpers() {
         let params = SimulationParams {
-            scale_factor: 0.5,
-            target_width: 500,
-            target_height: 400,
+            scale_factor: 2.0,
+            target_width: 2000,
+            target_height: 1600,
             adjusted_ppd: 20.0,
             requires_upscale: true,
             requires_downscale: false,
         };
 
         assert!(params.requires_scaling());
-        assert!((params.downscale_only_factor() - 0.5).abs() < 0.001);
+        assert!((params.downscale_only_factor() - 1.0).abs() < 0.001);
 
         let params2 = SimulationParams {
-            scale_factor: 2.0,
-            target_width: 2000,
-            target_height: 1600,
+            scale_factor: 0.5,
+            target_width: 500,
+            target_height: 400,
             adjusted_ppd: 80.0,
             requires_upscale: false,
             requires_downscale: true,
         };
 
         assert!(params2.requires_scaling());
-        assert!((params2.downscale_only_factor() - 1.0).abs() < 0.001);
+        assert!((params2.downscale_only_factor() - 0.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_simulation_acuity_scales_native_images() {
+        // Native-resolution images on high-PPD displays are downscaled too.
+        let params = presets::native_phone().simulation_params(900, 600, SimulationMode::Accurate);
+        assert!(params.requires_downscale);
+        assert!(params.target_width < 900);
+
+        let params =
+            ViewingCondition::desktop().simulation_params(900, 600, SimulationMode::Accurate);
+        assert!(!params.requires_scaling());
+        assert_eq!((params.target_width, params.target_height), (900, 600));
+    }
+
+    #[test]
+    fn test_simulation_apply_rgb8() {
+        let v = ViewingCondition::new(40.0)
+            .with_browser_dppx(1.0)
+            .with_image_intrinsic_dppx(2.0);
+        let params = v.simulation_params(8, 6, SimulationMode::Accurate);
+        let rgb = vec![128u8; 8 * 6 * 3];
+        let out = params.apply_rgb8(&rgb, 8, 6);
+        assert_eq!(out.len(), 4 * 3 * 3);
+        assert!(out.iter().all(|&v| v == 128));
+
+        let native = ViewingCondition::desktop().simulation_params(8, 6, SimulationMode::Accurate);
+        assert_eq!(native.apply_rgb8(&rgb, 8, 6), rgb);
     }
 
     #[test]